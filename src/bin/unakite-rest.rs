@@ -0,0 +1,56 @@
+//! Iceberg REST catalog server over a Unakite catalogue.
+//!
+//! ```text
+//! unakite-rest [--catalogue db.db] [--warehouse lake] [--bind 127.0.0.1:8181]
+//! ```
+//!
+//! `--warehouse` is the local directory the tables are kept under.
+
+use std::path::PathBuf;
+
+use tokio::net::TcpListener;
+
+use unakite::catalogue::rest::server::RestCatalogServer;
+use unakite::lake_engine::DEFAULT_CATALOGUE_PATH;
+use unakite::utils::storage::storage::Storage;
+
+const DEFAULT_WAREHOUSE: &str = "lake";
+const DEFAULT_BIND_ADDRESS: &str = "127.0.0.1:8181";
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let mut catalogue = PathBuf::from(DEFAULT_CATALOGUE_PATH);
+    let mut warehouse = PathBuf::from(DEFAULT_WAREHOUSE);
+    let mut bind = DEFAULT_BIND_ADDRESS.to_string();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| anyhow::anyhow!("{} needs a value", flag))?;
+
+        match flag.as_str() {
+            "--catalogue" => {
+                catalogue = PathBuf::from(value);
+            }
+            "--warehouse" => {
+                warehouse = PathBuf::from(value);
+            }
+            "--bind" => {
+                bind = value;
+            }
+            other => {
+                return Err(anyhow::anyhow!("Unknown argument '{}'", other));
+            }
+        }
+    }
+
+    let storage = Storage::LocalFileSystem { base_path: warehouse };
+    let server = RestCatalogServer::new(catalogue, storage).await?;
+    let listener = TcpListener::bind(&bind).await?;
+
+    println!("Serving the Iceberg REST catalog on http://{}", listener.local_addr()?);
+
+    tokio::select! {
+        served = server.serve(listener) => served,
+        _ = tokio::signal::ctrl_c() => Ok(()),
+    }
+}
@@ -0,0 +1,441 @@
+use std::{ collections::{ btree_map::Entry, BTreeMap, HashMap }, sync::Arc };
+
+use arrow_array::RecordBatch;
+use arrow_schema::SchemaRef;
+
+use bytes::Bytes;
+
+use datafusion::physical_plan::SendableRecordBatchStream;
+use futures::{ future::BoxFuture, FutureExt, StreamExt };
+
+use object_store::{ buffered::BufWriter, path::Path };
+
+use parquet::arrow::async_reader::ParquetObjectReader;
+use parquet::arrow::async_writer::{ AsyncFileWriter, ParquetObjectWriter };
+use parquet::arrow::{ AsyncArrowWriter, ParquetRecordBatchStreamBuilder };
+use parquet::basic::Compression;
+use parquet::errors::ParquetError;
+use parquet::file::properties::{ WriterProperties, DEFAULT_MAX_ROW_GROUP_SIZE };
+
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc::{ Receiver, Sender };
+
+use uuid::Uuid;
+
+use crate::catalogue::{
+    partitioning::{ BoundPartition, PartitionSpec },
+    snapshots::DataFile,
+    statistics::StatsCollector,
+};
+use crate::utils::{ csv_tools::reader::BlobWriter, storage::storage::BackEnd };
+
+/// Table property holding the size in bytes data files are rolled over at.
+pub const TARGET_FILE_SIZE_PROPERTY: &str = "write.target-file-size-bytes";
+
+/// Bytes of encoded row groups an open file buffers before uploading them as a part
+/// of a multipart upload, the smallest part size S3 accepts.
+const UPLOAD_BUFFER_SIZE: usize = 5 * 1024 * 1024;
+
+/// Encoded parts a writer queues for [`upload_parts`] before it waits for them to
+/// be uploaded.
+pub(crate) const UPLOAD_QUEUE_LENGTH: usize = 4;
+
+/// How data files are laid out as they are written.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WriteOptions {
+    /// Size in bytes a data file is closed at, later rows go to a new file.
+    pub target_file_size: u64,
+    /// Rows a data file is closed at, unlimited when `None`.
+    pub target_file_rows: Option<u64>,
+    /// Rows per parquet row group. A row group is buffered until it fills, then
+    /// encoded and uploaded.
+    pub row_group_size: usize,
+    /// Bytes all files open at once may buffer together, row groups in progress and
+    /// their upload buffers. Past it the file buffering most is completed early.
+    pub max_buffered_bytes: usize,
+}
+
+impl WriteOptions {
+    /// The options a table's properties ask for, defaults for the rest.
+    pub fn from_properties(properties: &BTreeMap<String, String>) -> anyhow::Result<Self> {
+        let mut options = WriteOptions::default();
+
+        if let Some(size) = properties.get(TARGET_FILE_SIZE_PROPERTY) {
+            options.target_file_size = size
+                .parse()
+                .map_err(|_| {
+                    anyhow::anyhow!("Invalid value for '{}': {}", TARGET_FILE_SIZE_PROPERTY, size)
+                })?;
+        }
+
+        Ok(options)
+    }
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        WriteOptions {
+            target_file_size: 512 * 1024 * 1024,
+            target_file_rows: None,
+            row_group_size: DEFAULT_MAX_ROW_GROUP_SIZE,
+            max_buffered_bytes: 1024 * 1024 * 1024,
+        }
+    }
+}
+
+/// Splits written rows by a table's partition spec, streaming one parquet file per
+/// partition to the store.
+///
+/// Each open file holds at most one row group in memory, filled row groups are
+/// uploaded as parts of a multipart upload. A file reaching the target file size
+/// or row count is completed and the partition's next rows start a new one.
+///
+/// Writes spanning many partitions are bounded by the options' buffer budget, the
+/// file buffering most is completed whenever the open files exceed it together.
+pub(crate) struct PartitionedWriter<'a> {
+    store: &'a BackEnd,
+    prefix: Path,
+    schema_id: i64,
+    schema: SchemaRef,
+    spec_id: i32,
+    partition: BoundPartition,
+    options: WriteOptions,
+    /// Files being written, by partition values.
+    open: BTreeMap<Vec<Option<String>>, PartitionFile>,
+    /// Files already completed.
+    written: Vec<DataFile>,
+    /// Where encoded files go when they aren't uploaded by the writer itself.
+    uploads: Option<Sender<Upload>>,
+}
+
+/// A part of a file encoded by a [`PartitionedWriter`], for [`upload_parts`].
+pub(crate) enum Upload {
+    /// The file's next encoded bytes.
+    Part(Path, Bytes),
+    /// The file has been written in full.
+    Complete(Path),
+}
+
+/// Hands a file's encoded bytes to [`upload_parts`] instead of uploading them.
+struct QueuedFile {
+    path: Path,
+    uploads: Sender<Upload>,
+}
+
+impl AsyncFileWriter for QueuedFile {
+    fn write(&mut self, bs: Bytes) -> BoxFuture<'_, parquet::errors::Result<()>> {
+        self.queue(Upload::Part(self.path.clone(), bs))
+    }
+
+    fn complete(&mut self) -> BoxFuture<'_, parquet::errors::Result<()>> {
+        self.queue(Upload::Complete(self.path.clone()))
+    }
+}
+
+impl QueuedFile {
+    fn queue(&self, upload: Upload) -> BoxFuture<'_, parquet::errors::Result<()>> {
+        async move {
+            self.uploads
+                .send(upload)
+                .await
+                .map_err(|_| ParquetError::General(format!("Upload of {} stopped", self.path)))
+        }.boxed()
+    }
+}
+
+/// Uploads the files a [`PartitionedWriter`] encodes elsewhere, e.g. on a blocking
+/// thread, until the writer is dropped. Each file's parts arrive in order.
+pub(crate) async fn upload_parts(
+    store: &BackEnd,
+    mut uploads: Receiver<Upload>
+) -> anyhow::Result<()> {
+    let mut open: HashMap<Path, BufWriter> = HashMap::new();
+    let upload = |path: &Path| {
+        BufWriter::with_capacity(store.store(), path.clone(), UPLOAD_BUFFER_SIZE)
+    };
+
+    while let Some(next) = uploads.recv().await {
+        match next {
+            Upload::Part(path, bytes) => {
+                let file = open.entry(path).or_insert_with_key(upload);
+
+                file.put(bytes).await?;
+            }
+            Upload::Complete(path) => {
+                let mut file = open.remove(&path).unwrap_or_else(|| upload(&path));
+
+                file.shutdown().await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+struct PartitionFile {
+    path: Path,
+    writer: AsyncArrowWriter<Box<dyn AsyncFileWriter>>,
+    stats: StatsCollector,
+    row_count: u64,
+}
+
+impl PartitionFile {
+    /// Bytes held in memory, the row group in progress and the encoded bytes not
+    /// yet uploaded, at most an upload buffer's worth.
+    fn buffered(&self) -> usize {
+        self.writer.memory_size() + self.writer.bytes_written().min(UPLOAD_BUFFER_SIZE)
+    }
+}
+
+impl<'a> PartitionedWriter<'a> {
+    /// A writer of new files under `location`, the url of a table's directory.
+    ///
+    /// Files are uploaded as they are written, or with `uploads` handed to
+    /// [`upload_parts`] running in another task.
+    pub(crate) fn try_new(
+        store: &'a BackEnd,
+        location: &str,
+        schema_id: i64,
+        schema: SchemaRef,
+        spec: &PartitionSpec,
+        options: WriteOptions,
+        uploads: Option<Sender<Upload>>
+    ) -> anyhow::Result<Self> {
+        let partition = BoundPartition::bind(spec, &schema)?;
+
+        let mut writer = PartitionedWriter {
+            store,
+            prefix: store.path_for(location)?,
+            schema_id,
+            schema,
+            spec_id: spec.spec_id,
+            partition,
+            options,
+            open: BTreeMap::new(),
+            written: Vec::new(),
+            uploads,
+        };
+
+        // An unpartitioned write always produces its file, even without rows
+        if writer.partition.is_unpartitioned() {
+            writer.file(Vec::new())?;
+        }
+
+        Ok(writer)
+    }
+
+    pub(crate) async fn write(&mut self, batch: &RecordBatch) -> anyhow::Result<()> {
+        let row_group_size = self.options.row_group_size.max(1);
+        let target_file_size = self.options.target_file_size;
+        let target_file_rows = self.options.target_file_rows.unwrap_or(u64::MAX).max(1);
+
+        for (values, rows) in self.partition.split(batch)? {
+            let mut offset = 0;
+
+            // Written a row group at a time, so files roll over between row groups
+            while offset < rows.num_rows() {
+                let file = self.file(values.clone())?;
+                let length = (row_group_size - file.writer.in_progress_rows())
+                    .min(rows.num_rows() - offset)
+                    .min((target_file_rows - file.row_count).try_into().unwrap_or(usize::MAX));
+                let slice = rows.slice(offset, length);
+                offset += length;
+
+                file.row_count += length as u64;
+                file.stats.update(&slice)?;
+                file.writer.write(&slice).await?;
+
+                let size = file.writer.bytes_written() + file.writer.in_progress_size();
+
+                if (size as u64) >= target_file_size || file.row_count >= target_file_rows {
+                    let file = self.open.remove(&values).unwrap();
+
+                    self.complete(values.clone(), file).await?;
+                }
+
+                self.limit_buffers().await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Completes the files buffering most until the open files fit the buffer budget.
+    async fn limit_buffers(&mut self) -> anyhow::Result<()> {
+        loop {
+            let buffered = self.open.values().map(PartitionFile::buffered).sum::<usize>();
+
+            if buffered <= self.options.max_buffered_bytes {
+                return Ok(());
+            }
+
+            let largest = self.open
+                .iter()
+                .max_by_key(|(_, file)| file.buffered())
+                .map(|(values, _)| values.clone())
+                .unwrap();
+            let file = self.open.remove(&largest).unwrap();
+
+            self.complete(largest, file).await?;
+        }
+    }
+
+    /// The open file of a partition, started in the partition's directory, e.g.
+    /// `<location>/ts_day=2024-03-05/part-<uuid>.parquet`.
+    fn file(&mut self, values: Vec<Option<String>>) -> anyhow::Result<&mut PartitionFile> {
+        let file = match self.open.entry(values) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                // Path parts are percent-encoded, so any value makes a single directory
+                let path = self.partition
+                    .directories(entry.key())
+                    .iter()
+                    .fold(self.prefix.clone(), |path, directory| path.child(directory.as_str()))
+                    .child(format!("part-{}.parquet", Uuid::new_v4()));
+
+                let props = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .set_max_row_group_size(self.options.row_group_size.max(1))
+                    .build();
+                let output: Box<dyn AsyncFileWriter> = match &self.uploads {
+                    Some(uploads) => {
+                        Box::new(QueuedFile { path: path.clone(), uploads: uploads.clone() })
+                    }
+                    None => {
+                        let upload = BufWriter::with_capacity(
+                            self.store.store(),
+                            path.clone(),
+                            UPLOAD_BUFFER_SIZE
+                        );
+
+                        Box::new(ParquetObjectWriter::from_buf_writer(upload))
+                    }
+                };
+                let writer = AsyncArrowWriter::try_new(output, self.schema.clone(), Some(props))?;
+
+                entry.insert(PartitionFile {
+                    path,
+                    writer,
+                    stats: StatsCollector::new(&self.schema),
+                    row_count: 0,
+                })
+            }
+        };
+
+        Ok(file)
+    }
+
+    /// Writes a file's footer and completes its upload.
+    async fn complete(
+        &mut self,
+        values: Vec<Option<String>>,
+        mut file: PartitionFile
+    ) -> anyhow::Result<()> {
+        file.writer.finish().await?;
+
+        self.written.push(DataFile {
+            file_path: self.store.url_for(file.path.as_ref()),
+            row_count: file.row_count,
+            file_size: file.writer.bytes_written() as u64,
+            schema_id: self.schema_id,
+            spec_id: self.spec_id,
+            partition_values: values,
+            column_stats: file.stats.finish()?,
+        });
+
+        Ok(())
+    }
+
+    /// Completes every open file, returning all files written.
+    pub(crate) async fn finish(mut self) -> anyhow::Result<Vec<DataFile>> {
+        for (values, file) in std::mem::take(&mut self.open) {
+            self.complete(values, file).await?;
+        }
+
+        Ok(self.written)
+    }
+}
+
+impl BlobWriter {
+    /// Drains a record batch stream into new, uniquely named parquet files under
+    /// a table's location.
+    ///
+    /// Files are never overwritten, every call produces fresh `part-<uuid>.parquet`
+    /// files, one per partition of `spec` the rows fall into, more once a file
+    /// reaches the target file size. The files only become part of the table once
+    /// they are committed in a snapshot.
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - The backend the files are written to.
+    /// * `location` - Url of the table's directory, e.g. `file:///data/lake/orders/`.
+    /// * `schema_id` - The table schema version the rows conform to.
+    /// * `schema` - Schema of the batches produced by `stream`.
+    /// * `spec` - The table's partition spec.
+    /// * `options` - Target file size and row group size of the files.
+    /// * `stream` - The rows to write.
+    pub async fn write_stream(
+        store: &BackEnd,
+        location: &str,
+        schema_id: i64,
+        schema: SchemaRef,
+        spec: &PartitionSpec,
+        options: WriteOptions,
+        mut stream: SendableRecordBatchStream
+    ) -> anyhow::Result<Vec<DataFile>> {
+        let mut writer = PartitionedWriter::try_new(
+            store,
+            location,
+            schema_id,
+            schema,
+            spec,
+            options,
+            None
+        )?;
+
+        while let Some(maybe_batch) = stream.next().await {
+            writer.write(&maybe_batch?).await?;
+        }
+
+        writer.finish().await
+    }
+
+    /// Lists the parquet files directly under a location, reading row counts from
+    /// their footers. Directories below it are not searched, they may belong to
+    /// other tables.
+    pub async fn scan_data_files(
+        store: &BackEnd,
+        location: &str,
+        schema_id: i64
+    ) -> anyhow::Result<Vec<DataFile>> {
+        let prefix = store.path_for(location)?;
+        let object_store = store.store();
+
+        let listing = object_store.list_with_delimiter(Some(&prefix)).await?;
+
+        let mut files = Vec::new();
+        for object in listing.objects {
+            if object.location.extension() != Some("parquet") {
+                continue;
+            }
+
+            let reader = ParquetObjectReader::new(
+                Arc::clone(&object_store),
+                object.location.clone()
+            ).with_file_size(object.size);
+            let builder = ParquetRecordBatchStreamBuilder::new(reader).await?;
+
+            files.push(DataFile {
+                file_path: store.url_for(object.location.as_ref()),
+                row_count: builder.metadata().file_metadata().num_rows() as u64,
+                file_size: object.size,
+                schema_id,
+                spec_id: 0,
+                partition_values: Vec::new(),
+                column_stats: BTreeMap::new(),
+            });
+        }
+
+        Ok(files)
+    }
+}
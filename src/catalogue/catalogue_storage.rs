@@ -1,80 +1,966 @@
+use std::collections::BTreeMap;
+
 use anyhow::Ok;
-use rusqlite::params;
+use uuid::Uuid;
+use serde::{ Deserialize, Serialize };
+use rusqlite::{
+    params,
+    types::Type,
+    Error::FromSqlConversionFailure,
+    OptionalExtension,
+    Row,
+    TransactionBehavior,
+};
 
 use crate::catalogue::{
-    sql_strings::{ DELETE_SYS_SCHEMAS, DELETE_SYS_TABLES, INSERT_SYS_SCHEMAS, INSERT_SYS_TABLES },
-    tables::{ SchemaVec, Table },
+    partitioning::PartitionSpec,
+    snapshots::{ CommitConflict, Operation, Snapshot },
+    sql_strings::{
+        ADVANCE_SYS_TABLES_METADATA,
+        ADVANCE_SYS_TABLES_SCHEMA,
+        ADVANCE_SYS_TABLES_SNAPSHOT,
+        COUNT_NAMESPACE_SYS_TABLES,
+        DELETE_SYS_NAMESPACES,
+        DELETE_SYS_SCHEMAS,
+        DELETE_SYS_SNAPSHOTS,
+        DELETE_SYS_TABLES,
+        INSERT_SYS_NAMESPACES,
+        INSERT_SYS_SCHEMAS,
+        INSERT_SYS_SNAPSHOTS,
+        INSERT_SYS_TABLES,
+        LINK_SYS_SCHEMAS,
+        RENAME_SYS_SCHEMAS,
+        RENAME_SYS_TABLES,
+        SELECT_AS_OF_SYS_SNAPSHOTS,
+        SELECT_CURRENT_SYS_SNAPSHOTS,
+        SELECT_ID_SYS_NAMESPACES,
+        SELECT_ID_SYS_TABLES,
+        SELECT_EXISTS_SYS_SCHEMAS,
+        SELECT_METADATA_SYS_TABLES,
+        SELECT_NAMESPACE_SYS_TABLES,
+        SELECT_NEXT_ID_SYS_SCHEMAS,
+        SELECT_NAME_SYS_NAMESPACES,
+        SELECT_PARTITION_SYS_TABLES,
+        SELECT_PROPERTIES_SYS_NAMESPACES,
+        SELECT_PROPERTIES_SYS_TABLES,
+        SELECT_SCHEMA_FROM_SYS_SCHEMA,
+        SELECT_SCHEMA_ID_SYS_TABLES,
+        SELECT_TABLE_NAME_SYS_TABLES,
+        SELECT_TABLE_SYS_SCHEMAS,
+        SELECT_TABLE_SYS_SNAPSHOTS,
+        SELECT_URL_SYS_TABLES,
+        SELECT_VERSION_SYS_SNAPSHOTS,
+        UPDATE_PARTITION_SYS_TABLES,
+        UPDATE_PROPERTIES_SYS_NAMESPACES,
+        UPDATE_PROPERTIES_SYS_TABLES,
+    },
+    provider::DEFAULT_NAMESPACE,
+    tables::{ SchemaVec, SchemaVersion, Table, TableExists, TableIdent, TableMetadata },
     RootCatalogue,
 };
 
 pub trait Catalog {
-    /// Creates a new table. Errors if it already exists.
-    fn create_sys_table(&self, table: &Table) -> anyhow::Result<()>;
+    /// Creates a namespace with the given properties.
+    ///
+    /// Nested namespaces are dot separated, e.g. `team.analytics`, and can only be
+    /// created once their parent exists.
+    fn create_namespace(
+        &self,
+        namespace: &str,
+        properties: &BTreeMap<String, String>
+    ) -> anyhow::Result<()>;
+    /// Drops a namespace. Errors unless it exists and holds no tables or namespaces.
+    fn drop_namespace(&self, namespace: &str) -> anyhow::Result<()>;
+    /// Lists the namespaces directly under `parent`, or the top level ones.
+    fn list_namespaces(&self, parent: Option<&str>) -> anyhow::Result<Vec<String>>;
+    fn namespace_exists(&self, namespace: &str) -> anyhow::Result<bool>;
+    /// Fetches the properties a namespace was created or last updated with.
+    fn get_namespace_properties(&self, namespace: &str) -> anyhow::Result<BTreeMap<String, String>>;
+    /// Sets `updates` and removes `removals`, returning the resulting properties.
+    fn update_namespace_properties(
+        &self,
+        namespace: &str,
+        updates: &BTreeMap<String, String>,
+        removals: &[String]
+    ) -> anyhow::Result<BTreeMap<String, String>>;
+    /// Creates a new table and returns its id. Errors with [`TableExists`] if the
+    /// name is taken.
+    fn create_sys_table(&self, table: &Table) -> anyhow::Result<i64>;
+    /// Creates a new table with `snapshot` as its first commit and returns its id,
+    /// either both are catalogued or neither. Errors with [`TableExists`] if the
+    /// name is taken.
+    ///
+    /// The table's schema gets `schema_id`, the id the snapshot's files and manifests
+    /// were written with, see [`Catalog::next_schema_id`]. If another schema took the
+    /// id meanwhile a [`CommitConflict`] is returned. The snapshot's `table_id` is
+    /// ignored.
+    fn create_table_with_snapshot(
+        &self,
+        table: &Table,
+        schema_id: i64,
+        snapshot: &Snapshot
+    ) -> anyhow::Result<i64>;
+    /// The id the next schema added to the catalogue gets.
+    fn next_schema_id(&self) -> anyhow::Result<i64>;
     /// Drops a table. Errors if it does not exist, unless if_exists is true.
     /// Returns true if the table existed and was deleted.
     fn del_sys_table(&self, table: i64) -> anyhow::Result<()>;
+    /// Resolves a table name within a namespace to its id, if the table exists.
+    fn get_table_id(&self, namespace: &str, table_name: &str) -> anyhow::Result<Option<i64>>;
+    /// Whether a table exists.
+    fn table_exists(&self, ident: &TableIdent) -> anyhow::Result<bool>;
+    /// Loads a table's location, current schema, partition spec, properties and
+    /// current snapshot.
+    fn load_table(&self, ident: &TableIdent) -> anyhow::Result<TableMetadata>;
+    /// Lists the tables of a namespace, ordered by name.
+    fn list_tables(&self, namespace: &str) -> anyhow::Result<Vec<TableIdent>>;
+    /// Renames a table, possibly into another namespace of the same catalogue.
+    ///
+    /// Only the catalogue entry changes, the table's data files stay where they are.
+    fn rename_table(&self, from: &TableIdent, to: &TableIdent) -> anyhow::Result<()>;
+    /// Sets `updates` and removes `removals`, returning the resulting properties.
+    fn update_table_properties(
+        &self,
+        table_id: &i64,
+        updates: &BTreeMap<String, String>,
+        removals: &[String]
+    ) -> anyhow::Result<BTreeMap<String, String>>;
     /// Fetches a table schema
     ///
     fn get_table_schema(&self, table_id: &i64) -> anyhow::Result<SchemaVec>;
-    /// Returns a list of all table schemas.
-    fn list_tables_schemas(&self) -> anyhow::Result<()>;
+    /// Fetches the id of the `sys_schemas` entry a table currently reads with.
+    fn get_table_schema_id(&self, table_id: &i64) -> anyhow::Result<i64>;
+    /// Returns every schema version of a table, oldest first.
+    fn list_schema_versions(&self, table_id: &i64) -> anyhow::Result<Vec<SchemaVersion>>;
+    /// Appends a new schema version and makes it the table's current one.
+    ///
+    /// Like snapshot commits this only succeeds if the table still reads with
+    /// `expected_schema_id`, otherwise a [`CommitConflict`] is returned.
+    fn commit_schema(
+        &self,
+        table_id: &i64,
+        expected_schema_id: i64,
+        schema: &SchemaVec
+    ) -> anyhow::Result<SchemaVersion>;
+    /// Makes `spec` the partition spec new files of a table are written with,
+    /// adding it to the table's specs unless one with its id is already there.
+    ///
+    /// Files already written keep their spec. Only succeeds if the table still
+    /// writes with `expected_spec_id`, otherwise a [`CommitConflict`] is returned.
+    fn commit_partition_spec(
+        &self,
+        table_id: &i64,
+        expected_spec_id: i32,
+        spec: &PartitionSpec
+    ) -> anyhow::Result<PartitionSpec>;
+    /// Fetches the url of the directory holding a table's data files
+    ///
+    fn get_table_url(&self, table_id: &i64) -> anyhow::Result<String>;
+    /// Fetches the snapshot a table currently points at, `None` before its first commit.
+    fn current_snapshot(&self, table_id: &i64) -> anyhow::Result<Option<Snapshot>>;
+    /// Fetches the snapshot with the given sequence number.
+    fn get_snapshot_by_version(
+        &self,
+        table_id: &i64,
+        version: i64
+    ) -> anyhow::Result<Option<Snapshot>>;
+    /// Fetches the last snapshot committed at or before `timestamp_ms`.
+    fn get_snapshot_as_of(
+        &self,
+        table_id: &i64,
+        timestamp_ms: i64
+    ) -> anyhow::Result<Option<Snapshot>>;
+    /// Returns every snapshot of a table, oldest first.
+    fn list_snapshots(&self, table_id: &i64) -> anyhow::Result<Vec<Snapshot>>;
+    /// Points a table at a new Iceberg `metadata.json`.
+    ///
+    /// Only succeeds if the table still points at `expected`, otherwise a
+    /// [`CommitConflict`] is returned.
+    fn set_metadata_location(
+        &self,
+        table_id: &i64,
+        expected: Option<&str>,
+        metadata_location: &str
+    ) -> anyhow::Result<()>;
+    /// Records a pending snapshot and makes it the table's current one.
+    ///
+    /// The table pointer only advances if it still refers to the snapshot's parent,
+    /// otherwise nothing is written and a [`CommitConflict`] is returned.
+    fn commit_snapshot(&self, snapshot: &Snapshot) -> anyhow::Result<Snapshot>;
+    /// Whether the catalog writes its tables' `metadata.json` files itself, rather
+    /// than being pointed at the ones the engine writes.
+    fn writes_table_metadata(&self) -> bool {
+        false
+    }
 }
 
 impl Catalog for RootCatalogue {
-    fn create_sys_table(&self, table: &Table) -> anyhow::Result<()> {
+    fn create_namespace(
+        &self,
+        namespace: &str,
+        properties: &BTreeMap<String, String>
+    ) -> anyhow::Result<()> {
+        if namespace.split('.').any(|level| level.is_empty()) {
+            return Err(anyhow::anyhow!("Invalid namespace '{}'", namespace));
+        }
+
         let mut conn = self.db.get()?;
-        let tx = conn.transaction()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
 
-        tx.execute(INSERT_SYS_SCHEMAS, params![table.table_name, table.schema_bin])?;
+        if let Some((parent, _)) = namespace.rsplit_once('.') {
+            if namespace_id(&tx, parent)?.is_none() {
+                return Err(anyhow::anyhow!("Parent namespace '{}' not found", parent));
+            }
+        }
 
-        let schema_id = tx.last_insert_rowid();
-        tx.execute(INSERT_SYS_TABLES, params![table.table_name, schema_id])?;
-        let table_id = tx.last_insert_rowid();
+        if namespace_id(&tx, namespace)?.is_some() {
+            return Err(anyhow::anyhow!("Namespace '{}' already exists", namespace));
+        }
+
+        tx.execute(INSERT_SYS_NAMESPACES, params![namespace, serde_json::to_string(properties)?])?;
 
         tx.commit()?;
 
-        self.tables.insert(table_id, table.table_name.clone());
+        Ok(())
+    }
+
+    fn drop_namespace(&self, namespace: &str) -> anyhow::Result<()> {
+        if namespace == DEFAULT_NAMESPACE {
+            return Err(anyhow::anyhow!("The '{}' namespace can't be dropped", namespace));
+        }
+
+        if !self.list_namespaces(Some(namespace))?.is_empty() {
+            return Err(anyhow::anyhow!("Namespace '{}' has nested namespaces", namespace));
+        }
+
+        let mut conn = self.db.get()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        let namespace_id = namespace_id(&tx, namespace)?.ok_or_else(|| {
+            anyhow::anyhow!("Namespace '{}' not found", namespace)
+        })?;
+
+        let table_count = tx.query_row(COUNT_NAMESPACE_SYS_TABLES, [namespace_id], |row| {
+            row.get::<_, i64>(0)
+        })?;
+
+        if table_count > 0 {
+            return Err(anyhow::anyhow!("Namespace '{}' is not empty", namespace));
+        }
+
+        tx.execute(DELETE_SYS_NAMESPACES, [namespace_id])?;
+
+        tx.commit()?;
 
         Ok(())
     }
+
+    fn list_namespaces(&self, parent: Option<&str>) -> anyhow::Result<Vec<String>> {
+        let conn = self.db.get()?;
+
+        let mut statement = conn.prepare(SELECT_NAME_SYS_NAMESPACES)?;
+        let names = statement
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let children = names
+            .into_iter()
+            .filter(|name| {
+                let child = match parent {
+                    Some(parent) => {
+                        name.strip_prefix(parent).and_then(|rest| rest.strip_prefix('.'))
+                    }
+                    None => Some(name.as_str()),
+                };
+
+                matches!(child, Some(child) if !child.contains('.'))
+            })
+            .collect();
+
+        Ok(children)
+    }
+
+    fn namespace_exists(&self, namespace: &str) -> anyhow::Result<bool> {
+        let conn = self.db.get()?;
+
+        Ok(namespace_id(&conn, namespace)?.is_some())
+    }
+
+    fn get_namespace_properties(
+        &self,
+        namespace: &str
+    ) -> anyhow::Result<BTreeMap<String, String>> {
+        let conn = self.db.get()?;
+
+        let properties = conn
+            .query_row(SELECT_PROPERTIES_SYS_NAMESPACES, [namespace], |row| {
+                row.get::<_, String>(0)
+            })
+            .optional()?
+            .ok_or_else(|| anyhow::anyhow!("Namespace '{}' not found", namespace))?;
+
+        Ok(serde_json::from_str(&properties)?)
+    }
+
+    fn update_namespace_properties(
+        &self,
+        namespace: &str,
+        updates: &BTreeMap<String, String>,
+        removals: &[String]
+    ) -> anyhow::Result<BTreeMap<String, String>> {
+        let mut conn = self.db.get()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        let properties = tx
+            .query_row(SELECT_PROPERTIES_SYS_NAMESPACES, [namespace], |row| {
+                row.get::<_, String>(0)
+            })
+            .optional()?
+            .ok_or_else(|| anyhow::anyhow!("Namespace '{}' not found", namespace))?;
+
+        let mut properties: BTreeMap<String, String> = serde_json::from_str(&properties)?;
+
+        for key in removals {
+            properties.remove(key);
+        }
+        properties.extend(updates.clone());
+
+        tx.execute(
+            UPDATE_PROPERTIES_SYS_NAMESPACES,
+            params![serde_json::to_string(&properties)?, namespace]
+        )?;
+
+        tx.commit()?;
+
+        Ok(properties)
+    }
+
+    fn create_sys_table(&self, table: &Table) -> anyhow::Result<i64> {
+        let mut conn = self.db.get()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        let table_id = insert_table(&tx, table, None)?;
+
+        tx.commit()?;
+
+        self.tables.insert(table_id, TableIdent::new(&table.namespace, &table.table_name));
+
+        Ok(table_id)
+    }
+
+    fn create_table_with_snapshot(
+        &self,
+        table: &Table,
+        schema_id: i64,
+        snapshot: &Snapshot
+    ) -> anyhow::Result<i64> {
+        let mut conn = self.db.get()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        let taken = tx.query_row(SELECT_EXISTS_SYS_SCHEMAS, [schema_id], |row| row.get(0))?;
+
+        if taken {
+            return Err(
+                (CommitConflict {
+                    table_id: 0,
+                    pointer: "schema id",
+                    expected_id: Some(schema_id),
+                }).into()
+            );
+        }
+
+        let table_id = insert_table(&tx, table, Some(schema_id))?;
+
+        let snapshot = Snapshot { table_id, ..snapshot.clone() };
+        if !insert_snapshot(&tx, &snapshot)? {
+            return Err(anyhow::anyhow!("Table {} was committed to while created", table_id));
+        }
+
+        tx.commit()?;
+
+        self.tables.insert(table_id, TableIdent::new(&table.namespace, &table.table_name));
+
+        Ok(table_id)
+    }
+
+    fn next_schema_id(&self) -> anyhow::Result<i64> {
+        let conn = self.db.get()?;
+
+        Ok(conn.query_row(SELECT_NEXT_ID_SYS_SCHEMAS, [], |row| row.get(0))?)
+    }
     fn del_sys_table(&self, table_id: i64) -> anyhow::Result<()> {
         let mut conn = self.db.get()?;
         let tx = conn.transaction()?;
 
-        tx.execute(DELETE_SYS_TABLES, params![table_id])?;
+        tx.execute(DELETE_SYS_SNAPSHOTS, params![table_id])?;
 
         tx.execute(DELETE_SYS_SCHEMAS, params![table_id])?;
 
+        tx.execute(DELETE_SYS_TABLES, params![table_id])?;
+
+        tx.commit()?;
+
         self.tables.remove(&table_id);
 
         Ok(())
     }
 
+    fn get_table_id(&self, namespace: &str, table_name: &str) -> anyhow::Result<Option<i64>> {
+        let conn = self.db.get()?;
+
+        let mut statement = conn.prepare(SELECT_ID_SYS_TABLES)?;
+
+        let table_id = statement
+            .query_row([namespace, table_name], |row| row.get::<_, i64>(0))
+            .optional()?;
+
+        Ok(table_id)
+    }
+
+    fn table_exists(&self, ident: &TableIdent) -> anyhow::Result<bool> {
+        Ok(self.get_table_id(&ident.namespace, &ident.name)?.is_some())
+    }
+
+    fn load_table(&self, ident: &TableIdent) -> anyhow::Result<TableMetadata> {
+        let table_id = self
+            .get_table_id(&ident.namespace, &ident.name)?
+            .ok_or_else(|| anyhow::anyhow!("Table '{}' not found", ident))?;
+
+        let (table_uuid, location, schema_id, partition_string, properties, metadata_location) = {
+            let conn = self.db.get()?;
+
+            conn.query_row(SELECT_METADATA_SYS_TABLES, [table_id], |row| {
+                rusqlite::Result::Ok((
+                    row.get::<_, Option<String>>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                ))
+            })?
+        };
+
+        let schema = self.get_table_schema(&table_id)?;
+        let specs = PartitionSpecs::from_json(partition_string)?;
+        let partition_spec = specs.specs
+            .iter()
+            .find(|spec| spec.spec_id == specs.default_spec_id)
+            .map(|spec| spec.describe(&schema))
+            .unwrap_or_default();
+
+        Ok(TableMetadata {
+            table_id,
+            ident: ident.clone(),
+            table_uuid: table_uuid.unwrap_or_default(),
+            location: location.unwrap_or_default(),
+            schema_id,
+            schema,
+            partition_spec,
+            partition_specs: specs.specs,
+            default_spec_id: specs.default_spec_id,
+            properties: properties_from_json(properties)?,
+            metadata_location,
+            current_snapshot: self.current_snapshot(&table_id)?,
+        })
+    }
+
+    fn list_tables(&self, namespace: &str) -> anyhow::Result<Vec<TableIdent>> {
+        let conn = self.db.get()?;
+
+        if namespace_id(&conn, namespace)?.is_none() {
+            return Err(anyhow::anyhow!("Namespace '{}' not found", namespace));
+        }
+
+        let mut statement = conn.prepare(SELECT_NAMESPACE_SYS_TABLES)?;
+        let tables = statement
+            .query_map([namespace], |row| row.get::<_, String>(0))?
+            .map(|name| name.map(|name| TableIdent::new(namespace, &name)))
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(tables)
+    }
+
+    fn rename_table(&self, from: &TableIdent, to: &TableIdent) -> anyhow::Result<()> {
+        let table_id = self
+            .get_table_id(&from.namespace, &from.name)?
+            .ok_or_else(|| anyhow::anyhow!("Table '{}' not found", from))?;
+
+        let mut conn = self.db.get()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        let namespace_id = namespace_id(&tx, &to.namespace)?.ok_or_else(|| {
+            anyhow::anyhow!("Namespace '{}' not found", to.namespace)
+        })?;
+
+        let taken = tx
+            .query_row(SELECT_ID_SYS_TABLES, [&to.namespace, &to.name], |row| row.get::<_, i64>(0))
+            .optional()?;
+
+        if taken.is_some() {
+            return Err(TableExists(to.clone()).into());
+        }
+
+        tx.execute(RENAME_SYS_TABLES, params![namespace_id, to.name, table_id])?;
+        tx.execute(RENAME_SYS_SCHEMAS, params![to.name, table_id])?;
+
+        tx.commit()?;
+
+        self.tables.insert(table_id, to.clone());
+
+        Ok(())
+    }
+
+    fn update_table_properties(
+        &self,
+        table_id: &i64,
+        updates: &BTreeMap<String, String>,
+        removals: &[String]
+    ) -> anyhow::Result<BTreeMap<String, String>> {
+        let mut conn = self.db.get()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        let properties = tx
+            .query_row(SELECT_PROPERTIES_SYS_TABLES, [table_id], |row| {
+                row.get::<_, Option<String>>(0)
+            })
+            .optional()?
+            .ok_or_else(|| anyhow::anyhow!("Table {} not found", table_id))?;
+
+        let mut properties = properties_from_json(properties)?;
+
+        for key in removals {
+            properties.remove(key);
+        }
+        properties.extend(updates.clone());
+
+        tx.execute(
+            UPDATE_PROPERTIES_SYS_TABLES,
+            params![serde_json::to_string(&properties)?, table_id]
+        )?;
+
+        tx.commit()?;
+
+        Ok(properties)
+    }
+
     fn get_table_schema(&self, table_id: &i64) -> anyhow::Result<SchemaVec> {
         let conn = self.db.get()?;
 
-        let mut statement = conn.prepare("SELECT schema_bin FROM sys_schemas WHERE table_id = ?")?;
+        let mut statement = conn.prepare(SELECT_SCHEMA_FROM_SYS_SCHEMA)?;
 
         let row = statement.query_row([table_id], |row| row.get::<_, Vec<u8>>(0))?;
 
-        let schema = SchemaVec::de_serialize_schema(row);
+        let schema = SchemaVec::de_serialize_schema(row)?;
 
         Ok(schema)
     }
 
-    fn list_tables_schemas(&self) -> anyhow::Result<()> {
+    fn get_table_schema_id(&self, table_id: &i64) -> anyhow::Result<i64> {
         let conn = self.db.get()?;
 
-        let mut statement = conn.prepare("SELECT schema_bin FROM sys_schemas")?;
-        let mut rows = statement.query([])?;
+        let mut statement = conn.prepare(SELECT_SCHEMA_ID_SYS_TABLES)?;
 
+        let schema_id = statement.query_row([table_id], |row| row.get::<_, i64>(0))?;
+
+        Ok(schema_id)
+    }
+
+    fn list_schema_versions(&self, table_id: &i64) -> anyhow::Result<Vec<SchemaVersion>> {
+        let conn = self.db.get()?;
+
+        let mut statement = conn.prepare(SELECT_TABLE_SYS_SCHEMAS)?;
+        let mut rows = statement.query([table_id])?;
+
+        let mut versions = Vec::new();
         while let Some(row) = rows.next()? {
-            // Serialize Table schema
-            let bin = row.get::<_, Vec<u8>>(0)?;
-            let schema = SchemaVec::de_serialize_schema(bin);
-            println!("--> Schema {:?}", schema);
+            let version = row.get::<_, Option<String>>(1)?;
+
+            versions.push(SchemaVersion {
+                schema_id: row.get(0)?,
+                version: version.as_deref().unwrap_or("1").parse()?,
+                schema: SchemaVec::de_serialize_schema(row.get::<_, Vec<u8>>(2)?)?,
+            });
+        }
+
+        Ok(versions)
+    }
+
+    fn commit_schema(
+        &self,
+        table_id: &i64,
+        expected_schema_id: i64,
+        schema: &SchemaVec
+    ) -> anyhow::Result<SchemaVersion> {
+        let version = self
+            .list_schema_versions(table_id)?
+            .iter()
+            .map(|version| version.version)
+            .max()
+            .unwrap_or_default() + 1;
+
+        let mut conn = self.db.get()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        // Read from the database, the table may have been created by another process
+        let table_name = tx
+            .query_row(SELECT_TABLE_NAME_SYS_TABLES, [table_id], |row| row.get::<_, String>(0))
+            .optional()?
+            .ok_or_else(|| anyhow::anyhow!("Table {} not found", table_id))?;
+
+        tx.execute(
+            INSERT_SYS_SCHEMAS,
+            params![
+                Option::<i64>::None,
+                table_name,
+                SchemaVec::serialize_schema(schema),
+                version.to_string(),
+                table_id
+            ]
+        )?;
+
+        let schema_id = tx.last_insert_rowid();
+
+        let advanced = tx.execute(
+            ADVANCE_SYS_TABLES_SCHEMA,
+            params![schema_id, table_id, expected_schema_id]
+        )?;
+
+        if advanced == 0 {
+            return Err(
+                (CommitConflict {
+                    table_id: *table_id,
+                    pointer: "schema",
+                    expected_id: Some(expected_schema_id),
+                }).into()
+            );
+        }
+
+        tx.commit()?;
+
+        Ok(SchemaVersion {
+            schema_id,
+            version,
+            schema: schema.clone(),
+        })
+    }
+
+    fn commit_partition_spec(
+        &self,
+        table_id: &i64,
+        expected_spec_id: i32,
+        spec: &PartitionSpec
+    ) -> anyhow::Result<PartitionSpec> {
+        let mut conn = self.db.get()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        let partition_string = tx
+            .query_row(SELECT_PARTITION_SYS_TABLES, [table_id], |row| {
+                row.get::<_, Option<String>>(0)
+            })
+            .optional()?
+            .ok_or_else(|| anyhow::anyhow!("Table {} not found", table_id))?;
+
+        let mut specs = PartitionSpecs::from_json(partition_string)?;
+
+        if specs.default_spec_id != expected_spec_id {
+            return Err(
+                (CommitConflict {
+                    table_id: *table_id,
+                    pointer: "partition spec",
+                    expected_id: Some(expected_spec_id as i64),
+                }).into()
+            );
+        }
+
+        if specs.specs.iter().all(|known| known.spec_id != spec.spec_id) {
+            specs.specs.push(spec.clone());
+        }
+        specs.default_spec_id = spec.spec_id;
+
+        tx.execute(UPDATE_PARTITION_SYS_TABLES, params![specs.to_json()?, table_id])?;
+
+        tx.commit()?;
+
+        Ok(spec.clone())
+    }
+
+    fn get_table_url(&self, table_id: &i64) -> anyhow::Result<String> {
+        let conn = self.db.get()?;
+
+        let mut statement = conn.prepare(SELECT_URL_SYS_TABLES)?;
+
+        let url = statement.query_row([table_id], |row| row.get::<_, Option<String>>(0))?;
+
+        url.ok_or_else(|| anyhow::anyhow!("Table {} has no location", table_id))
+    }
+
+    fn current_snapshot(&self, table_id: &i64) -> anyhow::Result<Option<Snapshot>> {
+        let conn = self.db.get()?;
+
+        let mut statement = conn.prepare(SELECT_CURRENT_SYS_SNAPSHOTS)?;
+
+        let snapshot = statement.query_row([table_id], snapshot_from_row).optional()?;
+
+        Ok(snapshot)
+    }
+
+    fn get_snapshot_by_version(
+        &self,
+        table_id: &i64,
+        version: i64
+    ) -> anyhow::Result<Option<Snapshot>> {
+        let conn = self.db.get()?;
+
+        let mut statement = conn.prepare(SELECT_VERSION_SYS_SNAPSHOTS)?;
+
+        let snapshot = statement
+            .query_row(params![table_id, version], snapshot_from_row)
+            .optional()?;
+
+        Ok(snapshot)
+    }
+
+    fn get_snapshot_as_of(
+        &self,
+        table_id: &i64,
+        timestamp_ms: i64
+    ) -> anyhow::Result<Option<Snapshot>> {
+        let conn = self.db.get()?;
+
+        let mut statement = conn.prepare(SELECT_AS_OF_SYS_SNAPSHOTS)?;
+
+        let snapshot = statement
+            .query_row(params![table_id, timestamp_ms], snapshot_from_row)
+            .optional()?;
+
+        Ok(snapshot)
+    }
+
+    fn list_snapshots(&self, table_id: &i64) -> anyhow::Result<Vec<Snapshot>> {
+        let conn = self.db.get()?;
+
+        let mut statement = conn.prepare(SELECT_TABLE_SYS_SNAPSHOTS)?;
+        let mut rows = statement.query([table_id])?;
+
+        let mut snapshots = Vec::new();
+        while let Some(row) = rows.next()? {
+            snapshots.push(snapshot_from_row(row)?);
+        }
+
+        Ok(snapshots)
+    }
+
+    fn set_metadata_location(
+        &self,
+        table_id: &i64,
+        expected: Option<&str>,
+        metadata_location: &str
+    ) -> anyhow::Result<()> {
+        let conn = self.db.get()?;
+
+        let advanced = conn.execute(
+            ADVANCE_SYS_TABLES_METADATA,
+            params![metadata_location, table_id, expected]
+        )?;
+
+        if advanced == 0 {
+            return Err(
+                (CommitConflict {
+                    table_id: *table_id,
+                    pointer: "metadata",
+                    expected_id: None,
+                }).into()
+            );
         }
 
         Ok(())
     }
+
+    fn commit_snapshot(&self, snapshot: &Snapshot) -> anyhow::Result<Snapshot> {
+        let mut conn = self.db.get()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        if !insert_snapshot(&tx, snapshot)? {
+            // Dropping the transaction rolls the snapshot insert back
+            return Err(
+                (CommitConflict {
+                    table_id: snapshot.table_id,
+                    pointer: "snapshot",
+                    expected_id: snapshot.parent_snapshot_id,
+                }).into()
+            );
+        }
+
+        let snapshot_id = tx.last_insert_rowid();
+
+        tx.commit()?;
+
+        Ok(Snapshot {
+            snapshot_id,
+            ..snapshot.clone()
+        })
+    }
+}
+
+/// Inserts a table with its first schema inside a transaction, returning the table's
+/// id. The schema gets `schema_id`, or the next free id if `None`.
+///
+/// Errors with [`TableExists`] if the name is taken, the transaction should be
+/// immediate so no other table can take it before the insert.
+fn insert_table(
+    tx: &rusqlite::Transaction,
+    table: &Table,
+    schema_id: Option<i64>
+) -> anyhow::Result<i64> {
+    let namespace_id = namespace_id(tx, &table.namespace)?.ok_or_else(|| {
+        anyhow::anyhow!("Namespace '{}' not found", table.namespace)
+    })?;
+
+    let exists = tx
+        .query_row(SELECT_ID_SYS_TABLES, [&table.namespace, &table.table_name], |row| {
+            row.get::<_, i64>(0)
+        })
+        .optional()?;
+
+    if exists.is_some() {
+        return Err(TableExists(TableIdent::new(&table.namespace, &table.table_name)).into());
+    }
+
+    tx.execute(
+        INSERT_SYS_SCHEMAS,
+        params![schema_id, table.table_name, table.schema_bin, "1", Option::<i64>::None]
+    )?;
+
+    let schema_id = tx.last_insert_rowid();
+    let schema = SchemaVec::de_serialize_schema(table.schema_bin.clone())?;
+    let partition_spec = PartitionSpec::from_terms(&table.partition_spec, &schema)?;
+    let partition_string = match partition_spec.is_unpartitioned() {
+        true => None,
+        false => Some(PartitionSpecs::new(partition_spec).to_json()?),
+    };
+
+    tx.execute(
+        INSERT_SYS_TABLES,
+        params![
+            namespace_id,
+            table.table_name,
+            Uuid::new_v4().to_string(),
+            table.url,
+            schema_id,
+            partition_string,
+            serde_json::to_string(&table.properties)?
+        ]
+    )?;
+    let table_id = tx.last_insert_rowid();
+
+    tx.execute(LINK_SYS_SCHEMAS, params![table_id, schema_id])?;
+
+    Ok(table_id)
+}
+
+/// Inserts a snapshot inside a transaction and advances its table to it, `false`
+/// if the table no longer points at the snapshot's parent. The new snapshot's id
+/// is the transaction's last insert rowid.
+fn insert_snapshot(tx: &rusqlite::Transaction, snapshot: &Snapshot) -> anyhow::Result<bool> {
+    tx.execute(
+        INSERT_SYS_SNAPSHOTS,
+        params![
+            // Ids chosen by the writer are kept, `0` lets the catalogue assign one
+            (snapshot.snapshot_id != 0).then_some(snapshot.snapshot_id),
+            snapshot.table_id,
+            snapshot.parent_snapshot_id,
+            snapshot.sequence_number,
+            snapshot.operation.as_str(),
+            Snapshot::serialize_files(&snapshot.data_files)?,
+            serde_json::to_string(&snapshot.summary)?,
+            snapshot.timestamp_ms,
+            snapshot.manifest_list
+        ]
+    )?;
+
+    let snapshot_id = tx.last_insert_rowid();
+
+    let advanced = tx.execute(
+        ADVANCE_SYS_TABLES_SNAPSHOT,
+        params![snapshot_id, snapshot.table_id, snapshot.parent_snapshot_id]
+    )?;
+
+    Ok(advanced != 0)
+}
+
+/// Looks a namespace up by name, on a plain connection or inside a transaction.
+fn namespace_id(conn: &rusqlite::Connection, namespace: &str) -> anyhow::Result<Option<i64>> {
+    let namespace_id = conn
+        .query_row(SELECT_ID_SYS_NAMESPACES, [namespace], |row| row.get::<_, i64>(0))
+        .optional()?;
+
+    Ok(namespace_id)
+}
+
+/// What a table's `partition_string` holds: every partition spec the table has had
+/// and the one new files are written with.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct PartitionSpecs {
+    default_spec_id: i32,
+    specs: Vec<PartitionSpec>,
+}
+
+impl PartitionSpecs {
+    fn new(spec: PartitionSpec) -> Self {
+        PartitionSpecs { default_spec_id: spec.spec_id, specs: vec![spec] }
+    }
+
+    /// Decodes a `partition_string`, which is `NULL` for tables that were never
+    /// partitioned. Catalogues from before specs could evolve hold a single spec.
+    fn from_json(partition_string: Option<String>) -> anyhow::Result<Self> {
+        let Some(json) = partition_string else {
+            return Ok(PartitionSpecs::new(PartitionSpec::unpartitioned()));
+        };
+
+        match serde_json::from_str::<PartitionSpecs>(&json) {
+            Result::Ok(specs) => Ok(specs),
+            Err(_) => Ok(PartitionSpecs::new(serde_json::from_str(&json)?)),
+        }
+    }
+
+    fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+/// Decodes a `properties` column, which is `NULL` for tables created without any.
+fn properties_from_json(properties: Option<String>) -> anyhow::Result<BTreeMap<String, String>> {
+    match properties {
+        Some(properties) => Ok(serde_json::from_str(&properties)?),
+        None => Ok(BTreeMap::new()),
+    }
+}
+
+/// Decodes a `sys_snapshots` row.
+fn snapshot_from_row(row: &Row<'_>) -> rusqlite::Result<Snapshot> {
+    let operation = Operation::parse(&row.get::<_, String>(4)?).map_err(|e| {
+        FromSqlConversionFailure(4, Type::Text, e.into())
+    })?;
+
+    let summary = serde_json::from_str(&row.get::<_, String>(6)?).map_err(|e| {
+        FromSqlConversionFailure(6, Type::Text, e.into())
+    })?;
+
+    let data_files = Snapshot::de_serialize_files(row.get::<_, Vec<u8>>(5)?).map_err(|e| {
+        FromSqlConversionFailure(5, Type::Blob, e.into())
+    })?;
+
+    rusqlite::Result::Ok(Snapshot {
+        snapshot_id: row.get(0)?,
+        table_id: row.get(1)?,
+        parent_snapshot_id: row.get(2)?,
+        sequence_number: row.get(3)?,
+        operation,
+        data_files,
+        manifest_list: row.get(8)?,
+        summary,
+        timestamp_ms: row.get(7)?,
+    })
 }
@@ -0,0 +1,824 @@
+//! Delta Lake tables: the `_delta_log` JSON commit protocol.
+//!
+//! Like Iceberg tables, the catalogue stays the source of truth. Every snapshot the
+//! engine commits to a table whose [`TABLE_FORMAT_PROPERTY`] is `delta` is also
+//! written as commit `<sequence number - 1>` of the table's log, so engines reading
+//! Delta can read the table from its location. Existing Delta tables are read by
+//! replaying their log from its last checkpoint.
+
+use std::{ collections::BTreeMap, sync::Arc };
+
+use arrow_schema::{ DataType, TimeUnit };
+
+use bytes::Bytes;
+
+use datafusion::catalog::Session;
+
+use futures::TryStreamExt;
+
+use object_store::{ path::Path, ObjectMeta, ObjectStore, PutMode, PutOptions };
+
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::async_reader::ParquetObjectReader;
+use parquet::arrow::ParquetRecordBatchStreamBuilder;
+
+use serde::{ Deserialize, Serialize };
+use serde_json::{ json, Map, Value };
+
+use crate::catalogue::{
+    iceberg::object,
+    snapshots::{ CommitConflict, DataFile, Operation, Snapshot },
+    tables::{ Column, SchemaVec, TableFormat, TableMetadata, TABLE_FORMAT_PROPERTY },
+};
+
+/// Directory under a table's location holding its Delta log.
+const LOG_DIR: &str = "_delta_log";
+
+/// Table property keeping the `metaData` id of a Delta table registered from
+/// elsewhere, tables created by the engine use their uuid.
+pub const DELTA_TABLE_ID_PROPERTY: &str = "delta-table-id";
+
+const COLUMN_MAPPING_MODE: &str = "delta.columnMapping.mode";
+const COLUMN_MAPPING_ID: &str = "delta.columnMapping.id";
+const COLUMN_MAPPING_PHYSICAL_NAME: &str = "delta.columnMapping.physicalName";
+const COLUMN_MAPPING_MAX_ID: &str = "delta.columnMapping.maxColumnId";
+const APPEND_ONLY: &str = "delta.appendOnly";
+
+/// Reader table features the log can be read with. Tables using deletion vectors
+/// are only read while none of their files has one.
+const READER_FEATURES: [&str; 3] = ["columnMapping", "timestampNtz", "deletionVectors"];
+
+/// One line of a commit file or one row of a checkpoint, a single action is set.
+///
+/// Actions the engine has no use for, e.g. `txn` or `cdc`, are skipped.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Action {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    protocol: Option<Protocol>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    meta_data: Option<MetaData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    add: Option<Add>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    remove: Option<Remove>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    commit_info: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Protocol {
+    min_reader_version: i32,
+    min_writer_version: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reader_features: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    writer_features: Option<Vec<String>>,
+}
+
+impl Protocol {
+    /// The lowest protocol a table with `schema` can be written with.
+    fn for_schema(schema: &SchemaVec, column_mapping: bool) -> Self {
+        let timestamp_ntz = schema.columns
+            .iter()
+            .any(|column| matches!(column.datatype, DataType::Timestamp(_, None)));
+
+        if timestamp_ntz {
+            let mut features = vec!["timestampNtz".to_string()];
+            if column_mapping {
+                features.push("columnMapping".to_string());
+            }
+
+            return Protocol {
+                min_reader_version: 3,
+                min_writer_version: 7,
+                reader_features: Some(features.clone()),
+                writer_features: Some(features),
+            };
+        }
+
+        let (min_reader_version, min_writer_version) = match column_mapping {
+            true => (2, 5),
+            false => (1, 2),
+        };
+
+        Protocol {
+            min_reader_version,
+            min_writer_version,
+            reader_features: None,
+            writer_features: None,
+        }
+    }
+
+    fn check_readable(&self) -> anyhow::Result<()> {
+        if self.min_reader_version > 3 {
+            return Err(
+                anyhow::anyhow!("Delta reader version {} is not supported", self.min_reader_version)
+            );
+        }
+
+        let unsupported = self.reader_features
+            .iter()
+            .flatten()
+            .find(|feature| !READER_FEATURES.contains(&feature.as_str()));
+
+        match unsupported {
+            Some(feature) => {
+                Err(anyhow::anyhow!("Delta reader feature '{}' is not supported", feature))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MetaData {
+    id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    format: Format,
+    /// The table's schema as a JSON encoded Delta struct type.
+    schema_string: String,
+    #[serde(default)]
+    partition_columns: Vec<String>,
+    #[serde(default)]
+    configuration: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created_time: Option<i64>,
+}
+
+impl MetaData {
+    /// Whether the table's parquet files are matched to its columns by field id.
+    fn column_mapping(&self) -> anyhow::Result<bool> {
+        column_mapping(&self.configuration)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Format {
+    provider: String,
+    #[serde(default)]
+    options: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Add {
+    /// Relative to the table's location and URL encoded, or an absolute url.
+    path: String,
+    #[serde(default)]
+    partition_values: BTreeMap<String, Option<String>>,
+    size: i64,
+    #[serde(default)]
+    modification_time: i64,
+    #[serde(default)]
+    data_change: bool,
+    /// JSON encoded file statistics, of which only `numRecords` is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stats: Option<String>,
+    /// Set when rows of the file were deleted, which is not supported.
+    #[serde(default, skip_serializing)]
+    deletion_vector: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Remove {
+    path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deletion_timestamp: Option<i64>,
+    #[serde(default)]
+    data_change: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Stats {
+    num_records: Option<u64>,
+}
+
+/// A field of a Delta struct type.
+#[derive(Debug, Deserialize)]
+struct StructField {
+    name: String,
+    /// A primitive type name, nested types are JSON objects.
+    #[serde(rename = "type")]
+    datatype: Value,
+    nullable: bool,
+    #[serde(default)]
+    metadata: Map<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StructType {
+    fields: Vec<StructField>,
+}
+
+/// A Delta table's state as of the latest commit in its log.
+pub(crate) struct DeltaLog {
+    /// The table's latest version, commits are numbered from 0.
+    pub(crate) version: i64,
+    /// When the latest commit or checkpoint was written.
+    pub(crate) timestamp_ms: i64,
+    metadata: MetaData,
+    /// Live files by path.
+    files: BTreeMap<String, Add>,
+}
+
+impl DeltaLog {
+    /// The table's schema, field ids come from column mapping if the table uses it
+    /// and follow the column order otherwise.
+    pub(crate) fn schema(&self) -> anyhow::Result<SchemaVec> {
+        let schema: StructType = serde_json::from_str(&self.metadata.schema_string)?;
+        let column_mapping = self.metadata.column_mapping()?;
+
+        let columns = schema.fields
+            .into_iter()
+            .enumerate()
+            .map(|(index, field)| {
+                let field_id = match column_mapping {
+                    true =>
+                        field.metadata
+                            .get(COLUMN_MAPPING_ID)
+                            .and_then(Value::as_i64)
+                            .ok_or_else(|| {
+                                anyhow::anyhow!("Column '{}' has no column mapping id", field.name)
+                            })? as i32,
+                    false => (index as i32) + 1,
+                };
+
+                let datatype = match &field.datatype {
+                    Value::String(name) => arrow_type(name)?,
+                    _ => {
+                        return Err(
+                            anyhow::anyhow!("Nested Delta column '{}' is not supported", field.name)
+                        );
+                    }
+                };
+
+                Ok(Column {
+                    field_id,
+                    name: field.name,
+                    datatype,
+                    nullable: field.nullable,
+                    unique: false,
+                    references: None,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(SchemaVec { columns })
+    }
+
+    /// The catalogue properties of the table: its configuration, its format and id.
+    pub(crate) fn properties(&self) -> BTreeMap<String, String> {
+        let mut properties = self.metadata.configuration.clone();
+
+        properties.insert(
+            TABLE_FORMAT_PROPERTY.to_string(),
+            TableFormat::Delta.as_str().to_string()
+        );
+        properties.insert(DELTA_TABLE_ID_PROPERTY.to_string(), self.metadata.id.clone());
+
+        properties
+    }
+
+    /// The table's live files under `location`, given `schema_id`.
+    ///
+    /// Row counts come from the files' statistics, or their footers when the writer
+    /// kept none.
+    pub(crate) async fn data_files(
+        &self,
+        state: &dyn Session,
+        location: &str,
+        schema_id: i64
+    ) -> anyhow::Result<Vec<DataFile>> {
+        let mut data_files = Vec::with_capacity(self.files.len());
+
+        for add in self.files.values() {
+            if add.deletion_vector.is_some() {
+                return Err(
+                    anyhow::anyhow!("File {} has a deletion vector, which is unsupported", add.path)
+                );
+            }
+
+            let file_path = file_url(location, &add.path);
+
+            let stats = add.stats
+                .as_deref()
+                .map(serde_json::from_str::<Stats>)
+                .transpose()?;
+
+            let row_count = match stats.and_then(|stats| stats.num_records) {
+                Some(row_count) => row_count,
+                None => {
+                    let (store, path) = object(state, &file_path)?;
+                    let reader = ParquetObjectReader::new(store, path).with_file_size(
+                        add.size as u64
+                    );
+                    let builder = ParquetRecordBatchStreamBuilder::new(reader).await?;
+
+                    builder.metadata().file_metadata().num_rows() as u64
+                }
+            };
+
+            data_files.push(DataFile {
+                file_path,
+                row_count,
+                file_size: add.size as u64,
+                schema_id,
+                spec_id: 0,
+                partition_values: Vec::new(),
+                column_stats: BTreeMap::new(),
+            });
+        }
+
+        Ok(data_files)
+    }
+}
+
+/// The properties a new Delta table is created with, on top of `properties`.
+///
+/// Columns are mapped by id, which is how the engine itself matches files to the
+/// table's schema.
+pub(crate) fn delta_table_properties(
+    mut properties: BTreeMap<String, String>
+) -> BTreeMap<String, String> {
+    properties.entry(COLUMN_MAPPING_MODE.to_string()).or_insert_with(|| "id".to_string());
+
+    properties
+}
+
+/// Fails a write the table's configuration does not allow.
+pub(crate) fn check_delta_write(table: &TableMetadata, operation: Operation) -> anyhow::Result<()> {
+    let append_only = table.properties
+        .get(APPEND_ONLY)
+        .is_some_and(|value| value.eq_ignore_ascii_case("true"));
+
+    if append_only && operation != Operation::Append {
+        return Err(anyhow::anyhow!("Table '{}' is append only", table.ident));
+    }
+
+    Ok(())
+}
+
+/// Writes a snapshot as the next commit of the table's log, returning the commit
+/// file's url. The snapshot is committed to the catalogue only once this succeeds.
+///
+/// The first commit declares the protocol. The table's metadata is restated
+/// whenever its schema changed since `parent`, overwrites remove the parent's files.
+///
+/// A log that already has the snapshot's version fails with a [`CommitConflict`],
+/// another writer got there first.
+pub(crate) async fn write_delta_commit(
+    state: &dyn Session,
+    table: &TableMetadata,
+    snapshot: &Snapshot,
+    parent: Option<&Snapshot>,
+    added: &[DataFile]
+) -> anyhow::Result<String> {
+    let mut configuration = table.properties.clone();
+    configuration.remove(TABLE_FORMAT_PROPERTY);
+    configuration.remove(DELTA_TABLE_ID_PROPERTY);
+
+    let column_mapping = column_mapping(&configuration)?;
+    if column_mapping {
+        let max_id = configuration
+            .get(COLUMN_MAPPING_MAX_ID)
+            .and_then(|max_id| max_id.parse::<i32>().ok())
+            .unwrap_or_default()
+            .max(table.schema.last_field_id());
+
+        configuration.insert(COLUMN_MAPPING_MAX_ID.to_string(), max_id.to_string());
+    }
+
+    let mut actions = Vec::new();
+
+    if parent.is_none() {
+        actions.push(Action {
+            protocol: Some(Protocol::for_schema(&table.schema, column_mapping)),
+            ..Action::default()
+        });
+    }
+
+    // Snapshots read as the schema their newest files were written with
+    let parent_schema_id = parent.and_then(|parent| {
+        parent.data_files
+            .iter()
+            .map(|file| file.schema_id)
+            .max()
+    });
+
+    if parent_schema_id != Some(table.schema_id) {
+        let metadata = MetaData {
+            id: table.properties
+                .get(DELTA_TABLE_ID_PROPERTY)
+                .unwrap_or(&table.table_uuid)
+                .clone(),
+            name: Some(table.ident.name.clone()),
+            description: None,
+            format: Format {
+                provider: "parquet".to_string(),
+                options: BTreeMap::new(),
+            },
+            schema_string: delta_schema(&table.schema, column_mapping)?.to_string(),
+            partition_columns: Vec::new(),
+            configuration,
+            created_time: parent.is_none().then_some(snapshot.timestamp_ms),
+        };
+
+        actions.push(Action { meta_data: Some(metadata), ..Action::default() });
+    }
+
+    if let (Operation::Overwrite, Some(parent)) = (snapshot.operation, parent) {
+        for file in &parent.data_files {
+            actions.push(Action {
+                remove: Some(Remove {
+                    path: relative_path(&table.location, &file.file_path),
+                    deletion_timestamp: Some(snapshot.timestamp_ms),
+                    data_change: true,
+                    size: Some(file.file_size as i64),
+                }),
+                ..Action::default()
+            });
+        }
+    }
+
+    for file in added {
+        actions.push(Action {
+            add: Some(Add {
+                path: relative_path(&table.location, &file.file_path),
+                partition_values: BTreeMap::new(),
+                size: file.file_size as i64,
+                modification_time: snapshot.timestamp_ms,
+                data_change: true,
+                stats: Some(json!({ "numRecords": file.row_count }).to_string()),
+                deletion_vector: None,
+            }),
+            ..Action::default()
+        });
+    }
+
+    let mode = match snapshot.operation {
+        Operation::Append => "Append",
+        Operation::Overwrite => "Overwrite",
+    };
+
+    actions.push(Action {
+        commit_info: Some(
+            json!({
+                "timestamp": snapshot.timestamp_ms,
+                "operation": "WRITE",
+                "operationParameters": { "mode": mode },
+                "isBlindAppend": snapshot.operation == Operation::Append,
+                "engineInfo": concat!("unakite/", env!("CARGO_PKG_VERSION")),
+            })
+        ),
+        ..Action::default()
+    });
+
+    let mut commit = Vec::new();
+    for action in &actions {
+        serde_json::to_writer(&mut commit, action)?;
+        commit.push(b'\n');
+    }
+
+    let version = snapshot.sequence_number - 1;
+    let url = log_url(&table.location, &format!("{:020}.json", version));
+    let (store, path) = object(state, &url)?;
+
+    // Put-if-absent is what makes a Delta commit atomic
+    let options = PutOptions { mode: PutMode::Create, ..PutOptions::default() };
+
+    match store.put_opts(&path, commit.into(), options).await {
+        Ok(_) => Ok(url),
+        Err(object_store::Error::AlreadyExists { .. }) => {
+            Err(
+                (CommitConflict {
+                    table_id: snapshot.table_id,
+                    pointer: "Delta log",
+                    expected_id: parent.map(|parent| parent.snapshot_id),
+                }).into()
+            )
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Removes a commit written by [`write_delta_commit`] whose snapshot the catalogue
+/// refused, so the log doesn't run ahead of the catalogue.
+pub(crate) async fn remove_delta_commit(state: &dyn Session, url: &str) -> anyhow::Result<()> {
+    let (store, path) = object(state, url)?;
+    store.delete(&path).await?;
+
+    Ok(())
+}
+
+/// Reads a Delta table's state from the log under `location`.
+///
+/// The log is replayed from its latest complete checkpoint, every later commit has
+/// to be present.
+pub(crate) async fn read_delta_log(
+    state: &dyn Session,
+    location: &str
+) -> anyhow::Result<DeltaLog> {
+    let (store, prefix) = object(state, &log_url(location, ""))?;
+    let objects: Vec<ObjectMeta> = store.list(Some(&prefix)).try_collect().await?;
+
+    let mut commits = BTreeMap::new();
+    // Parts of each checkpoint by version, with the number of parts expected
+    let mut checkpoints: BTreeMap<i64, Vec<(u32, u32, &ObjectMeta)>> = BTreeMap::new();
+
+    for object in &objects {
+        match object.location.filename().and_then(log_file) {
+            Some((version, LogFile::Commit)) => {
+                commits.insert(version, object);
+            }
+            Some((version, LogFile::Checkpoint { part, parts })) => {
+                checkpoints.entry(version).or_default().push((part, parts, object));
+            }
+            None => {}
+        }
+    }
+
+    let checkpoint = checkpoints
+        .into_iter()
+        .rev()
+        .find(|(_, parts)| parts.iter().all(|(_, count, _)| *count as usize == parts.len()));
+
+    let mut replay = Replay::default();
+    let mut version = -1;
+    let mut timestamp_ms = 0;
+
+    if let Some((checkpoint_version, mut parts)) = checkpoint {
+        parts.sort_by_key(|(part, _, _)| *part);
+
+        for (_, _, object) in parts {
+            for action in checkpoint_actions(read(&store, &object.location).await?)? {
+                replay.apply(action);
+            }
+
+            timestamp_ms = object.last_modified.timestamp_millis();
+        }
+
+        version = checkpoint_version;
+    }
+
+    let latest = commits.keys().next_back().copied().unwrap_or(version);
+    if latest < 0 {
+        return Err(anyhow::anyhow!("No Delta log found under {}", location));
+    }
+
+    for next in version + 1..=latest {
+        let object = commits.get(&next).ok_or_else(|| {
+            anyhow::anyhow!("Delta log of {} is missing version {}", location, next)
+        })?;
+
+        let bytes = read(&store, &object.location).await?;
+        for line in bytes.split(|byte| *byte == b'\n') {
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+
+            replay.apply(serde_json::from_slice(line)?);
+        }
+
+        timestamp_ms = object.last_modified.timestamp_millis();
+    }
+
+    let protocol = replay.protocol.ok_or_else(|| anyhow::anyhow!("Delta log has no protocol"))?;
+    protocol.check_readable()?;
+
+    let metadata = replay.metadata.ok_or_else(|| anyhow::anyhow!("Delta log has no metadata"))?;
+
+    if !metadata.format.provider.eq_ignore_ascii_case("parquet") {
+        return Err(anyhow::anyhow!("Unsupported Delta file format '{}'", metadata.format.provider));
+    }
+
+    if !metadata.partition_columns.is_empty() {
+        return Err(anyhow::anyhow!("Partitioned Delta tables are not supported"));
+    }
+
+    Ok(DeltaLog {
+        version: latest,
+        timestamp_ms,
+        metadata,
+        files: replay.files,
+    })
+}
+
+/// The table state built up while replaying a log.
+#[derive(Default)]
+struct Replay {
+    protocol: Option<Protocol>,
+    metadata: Option<MetaData>,
+    files: BTreeMap<String, Add>,
+}
+
+impl Replay {
+    fn apply(&mut self, action: Action) {
+        if let Some(protocol) = action.protocol {
+            self.protocol = Some(protocol);
+        }
+
+        if let Some(metadata) = action.meta_data {
+            self.metadata = Some(metadata);
+        }
+
+        if let Some(add) = action.add {
+            self.files.insert(add.path.clone(), add);
+        }
+
+        if let Some(remove) = action.remove {
+            self.files.remove(&remove.path);
+        }
+    }
+}
+
+enum LogFile {
+    Commit,
+    /// Part `part` of a checkpoint written in `parts` files.
+    Checkpoint {
+        part: u32,
+        parts: u32,
+    },
+}
+
+/// The version and kind of a file in the log, `None` for files that are neither a
+/// commit nor a classic checkpoint, e.g. `_last_checkpoint` or `.crc` files.
+fn log_file(name: &str) -> Option<(i64, LogFile)> {
+    let (version, rest) = name.split_once('.')?;
+
+    if version.len() != 20 {
+        return None;
+    }
+
+    let version = version.parse().ok()?;
+
+    match rest {
+        "json" => Some((version, LogFile::Commit)),
+        "checkpoint.parquet" => Some((version, LogFile::Checkpoint { part: 1, parts: 1 })),
+        multi_part => {
+            let (part, parts) = multi_part
+                .strip_prefix("checkpoint.")?
+                .strip_suffix(".parquet")?
+                .split_once('.')?;
+
+            let checkpoint = LogFile::Checkpoint {
+                part: part.parse().ok()?,
+                parts: parts.parse().ok()?,
+            };
+
+            Some((version, checkpoint))
+        }
+    }
+}
+
+/// The actions stored in a parquet checkpoint.
+///
+/// Rows are converted to JSON so they decode like the lines of a commit file.
+fn checkpoint_actions(bytes: Bytes) -> anyhow::Result<Vec<Action>> {
+    let reader = ParquetRecordBatchReaderBuilder::try_new(bytes)?.build()?;
+    let mut actions = Vec::new();
+
+    for batch in reader {
+        let batch = batch?;
+        if batch.num_rows() == 0 {
+            continue;
+        }
+
+        let mut writer = arrow_json::ArrayWriter::new(Vec::new());
+        writer.write(&batch)?;
+        writer.finish()?;
+
+        actions.extend(serde_json::from_slice::<Vec<Action>>(&writer.into_inner())?);
+    }
+
+    Ok(actions)
+}
+
+/// Whether the configuration maps columns by id. Name mapping, where files are
+/// written with generated column names, is not supported.
+fn column_mapping(configuration: &BTreeMap<String, String>) -> anyhow::Result<bool> {
+    match configuration.get(COLUMN_MAPPING_MODE).map(String::as_str) {
+        None | Some("none") => Ok(false),
+        Some("id") => Ok(true),
+        Some(mode) => Err(anyhow::anyhow!("Delta column mapping mode '{}' is not supported", mode)),
+    }
+}
+
+/// A schema as a Delta struct type.
+///
+/// With column mapping every column carries its field id, its physical name is
+/// derived from the id so it survives renames.
+fn delta_schema(schema: &SchemaVec, column_mapping: bool) -> anyhow::Result<Value> {
+    let fields = schema.columns
+        .iter()
+        .map(|column| {
+            let mut metadata = Map::new();
+
+            if column_mapping {
+                metadata.insert(COLUMN_MAPPING_ID.to_string(), json!(column.field_id));
+                metadata.insert(
+                    COLUMN_MAPPING_PHYSICAL_NAME.to_string(),
+                    json!(format!("col-{}", column.field_id))
+                );
+            }
+
+            Ok(
+                json!({
+                    "name": column.name,
+                    "type": delta_type(&column.datatype)?,
+                    "nullable": column.nullable,
+                    "metadata": metadata,
+                })
+            )
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(json!({ "type": "struct", "fields": fields }))
+}
+
+/// The Delta name of a primitive arrow type.
+fn delta_type(datatype: &DataType) -> anyhow::Result<String> {
+    let name = match datatype {
+        DataType::Boolean => "boolean".to_string(),
+        DataType::Int8 => "byte".to_string(),
+        DataType::Int16 => "short".to_string(),
+        DataType::Int32 => "integer".to_string(),
+        DataType::Int64 => "long".to_string(),
+        DataType::Float32 => "float".to_string(),
+        DataType::Float64 => "double".to_string(),
+        DataType::Decimal128(precision, scale) => format!("decimal({},{})", precision, scale),
+        DataType::Date32 => "date".to_string(),
+        DataType::Timestamp(_, None) => "timestamp_ntz".to_string(),
+        DataType::Timestamp(_, Some(_)) => "timestamp".to_string(),
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => "string".to_string(),
+        DataType::Binary | DataType::LargeBinary | DataType::BinaryView => "binary".to_string(),
+        other => {
+            return Err(anyhow::anyhow!("Type {} has no Delta equivalent", other));
+        }
+    };
+
+    Ok(name)
+}
+
+/// The arrow type of a Delta primitive type name.
+fn arrow_type(name: &str) -> anyhow::Result<DataType> {
+    let datatype = match name {
+        "boolean" => DataType::Boolean,
+        "byte" => DataType::Int8,
+        "short" => DataType::Int16,
+        "integer" => DataType::Int32,
+        "long" => DataType::Int64,
+        "float" => DataType::Float32,
+        "double" => DataType::Float64,
+        "date" => DataType::Date32,
+        "timestamp" => DataType::Timestamp(TimeUnit::Microsecond, Some("+00:00".into())),
+        "timestamp_ntz" => DataType::Timestamp(TimeUnit::Microsecond, None),
+        "string" => DataType::Utf8,
+        "binary" => DataType::Binary,
+        decimal if decimal.starts_with("decimal(") && decimal.ends_with(')') => {
+            let (precision, scale) = decimal["decimal(".len()..decimal.len() - 1]
+                .split_once(',')
+                .ok_or_else(|| anyhow::anyhow!("Malformed type '{}'", decimal))?;
+
+            DataType::Decimal128(precision.trim().parse()?, scale.trim().parse()?)
+        }
+        other => {
+            return Err(anyhow::anyhow!("Unsupported Delta type '{}'", other));
+        }
+    };
+
+    Ok(datatype)
+}
+
+/// Url of a file in the `_delta_log/` directory under a table's location.
+fn log_url(location: &str, file_name: &str) -> String {
+    format!("{}/{}/{}", location.trim_end_matches('/'), LOG_DIR, file_name)
+}
+
+/// Url of a file named in the log, paths are relative to the table unless absolute.
+fn file_url(location: &str, path: &str) -> String {
+    match path.contains("://") {
+        true => path.to_string(),
+        false => format!("{}/{}", location.trim_end_matches('/'), path),
+    }
+}
+
+/// How the log names a data file, relative to the table if it lies under it.
+fn relative_path(location: &str, file_path: &str) -> String {
+    let prefix = format!("{}/", location.trim_end_matches('/'));
+
+    file_path.strip_prefix(&prefix).unwrap_or(file_path).to_string()
+}
+
+async fn read(store: &Arc<dyn ObjectStore>, path: &Path) -> anyhow::Result<Bytes> {
+    Ok(store.get(path).await?.bytes().await?)
+}
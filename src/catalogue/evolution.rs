@@ -0,0 +1,352 @@
+use std::sync::LazyLock;
+
+use arrow_schema::{ DataType, TimeUnit };
+
+use datafusion::sql::sqlparser::ast::{
+    AlterColumnOperation,
+    AlterTableOperation,
+    ColumnOption,
+    DataType as SqlDataType,
+    Ident,
+};
+use datafusion::sql::sqlparser::dialect::GenericDialect;
+use datafusion::sql::sqlparser::keywords::Keyword;
+use datafusion::sql::sqlparser::parser::Parser;
+use datafusion::sql::sqlparser::tokenizer::{ Location, Token };
+
+use regex::Regex;
+
+use crate::catalogue::{ partitioning::PartitionSpec, tables::{ Column, SchemaVec } };
+
+/// A single step from one schema version of a table to the next.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaChange {
+    /// Appends a nullable column, existing files read it as `NULL`.
+    AddColumn {
+        name: String,
+        datatype: DataType,
+    },
+    DropColumn {
+        name: String,
+    },
+    RenameColumn {
+        from: String,
+        to: String,
+    },
+    /// Promotes a column to a wider type, `int32 -> int64` or `float32 -> float64`.
+    WidenColumn {
+        name: String,
+        datatype: DataType,
+    },
+}
+
+impl SchemaChange {
+    /// Translates one `ALTER TABLE` operation.
+    pub fn from_sql(operation: &AlterTableOperation) -> anyhow::Result<Self> {
+        match operation {
+            AlterTableOperation::AddColumn { column_def, .. } => {
+                let not_null = column_def.options
+                    .iter()
+                    .any(|option| matches!(option.option, ColumnOption::NotNull));
+
+                if not_null {
+                    return Err(
+                        anyhow::anyhow!("Added column '{}' must be nullable", column_def.name)
+                    );
+                }
+
+                Ok(SchemaChange::AddColumn {
+                    name: normalize(&column_def.name),
+                    datatype: arrow_data_type(&column_def.data_type)?,
+                })
+            }
+            AlterTableOperation::DropColumn { column_name, .. } => {
+                Ok(SchemaChange::DropColumn { name: normalize(column_name) })
+            }
+            AlterTableOperation::RenameColumn { old_column_name, new_column_name } => {
+                Ok(SchemaChange::RenameColumn {
+                    from: normalize(old_column_name),
+                    to: normalize(new_column_name),
+                })
+            }
+            AlterTableOperation::AlterColumn {
+                column_name,
+                op: AlterColumnOperation::SetDataType { data_type, .. },
+            } => {
+                Ok(SchemaChange::WidenColumn {
+                    name: normalize(column_name),
+                    datatype: arrow_data_type(data_type)?,
+                })
+            }
+            other => Err(anyhow::anyhow!("Unsupported ALTER TABLE operation: {}", other)),
+        }
+    }
+}
+
+/// A single step from one partition spec of a table to the next. Terms are read
+/// by [`Transform::parse_term`].
+///
+/// [`Transform::parse_term`]: crate::catalogue::partitioning::Transform::parse_term
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartitionChange {
+    /// Partitions new files by one more term, e.g. `hour(ts)`.
+    AddField {
+        term: String,
+    },
+    /// Stops partitioning new files by a term, named by the term or by its
+    /// partition field, e.g. `day(ts)` or `ts_day`.
+    DropField {
+        term: String,
+    },
+    /// Swaps one term for another in place, e.g. `day(ts)` for `hour(ts)`.
+    ReplaceField {
+        from: String,
+        to: String,
+    },
+}
+
+/// The terms after `PARTITION FIELD`, with the replacement after `WITH`.
+static PARTITION_TERMS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?is)^(.+?)(?:\s+WITH\s+(.+?))?\s*;?\s*$"#).unwrap()
+});
+
+impl PartitionChange {
+    /// Reads `ALTER TABLE <table> ADD PARTITION FIELD <term>`, `.. DROP PARTITION
+    /// FIELD <term>` and `.. REPLACE PARTITION FIELD <term> WITH <term>`, which the
+    /// SQL parser does not know, into the table name and the change.
+    ///
+    /// The table name is read by the SQL parser, so quoted and qualified names work
+    /// as in any other statement. `None` if `sql` is no such statement.
+    pub fn from_sql(sql: &str) -> Option<anyhow::Result<(String, Self)>> {
+        let mut parser = Parser::new(&GenericDialect).try_with_sql(sql).ok()?;
+
+        if !parser.parse_keywords(&[Keyword::ALTER, Keyword::TABLE]) {
+            return None;
+        }
+
+        let table = parser.parse_object_name(false).ok()?.to_string();
+        let operation = parser.parse_one_of_keywords(
+            &[Keyword::ADD, Keyword::DROP, Keyword::REPLACE]
+        )?;
+
+        let is_word = |token: Token, word: &str| {
+            matches!(
+                token,
+                Token::Word(w) if w.quote_style.is_none() && w.value.eq_ignore_ascii_case(word)
+            )
+        };
+
+        if !is_word(parser.next_token().token, "PARTITION") ||
+            !is_word(parser.next_token().token, "FIELD")
+        {
+            return None;
+        }
+
+        let terms = &sql[byte_offset(sql, parser.peek_token().span.start)..];
+
+        let Some(captures) = PARTITION_TERMS.captures(terms) else {
+            return Some(Err(anyhow::anyhow!("{:?} PARTITION FIELD needs a term", operation)));
+        };
+
+        let term = captures[1].trim().to_string();
+        let with = captures.get(2).map(|with| with.as_str().trim().to_string());
+
+        let change = match (operation, with) {
+            (Keyword::ADD, None) => Ok(PartitionChange::AddField { term }),
+            (Keyword::DROP, None) => Ok(PartitionChange::DropField { term }),
+            (Keyword::REPLACE, Some(to)) => Ok(PartitionChange::ReplaceField { from: term, to }),
+            (Keyword::REPLACE, None) => {
+                Err(anyhow::anyhow!("REPLACE PARTITION FIELD needs a WITH <term> replacement"))
+            }
+            (operation, _) => {
+                Err(anyhow::anyhow!("{:?} PARTITION FIELD takes a single term", operation))
+            }
+        };
+
+        Some(change.map(|change| (table, change)))
+    }
+
+    /// Applies the change to the terms of a spec, returning the terms of the next.
+    pub fn apply(&self, terms: &[String], schema: &SchemaVec) -> anyhow::Result<Vec<String>> {
+        let mut next = terms.to_vec();
+
+        match self {
+            PartitionChange::AddField { term } => {
+                if partition_field(terms, term, schema)?.is_some() {
+                    return Err(anyhow::anyhow!("Partition field '{}' already exists", term));
+                }
+
+                next.push(term.clone());
+            }
+            PartitionChange::DropField { term } => {
+                next.remove(existing_partition_field(terms, term, schema)?);
+            }
+            PartitionChange::ReplaceField { from, to } => {
+                let position = existing_partition_field(terms, from, schema)?;
+
+                if partition_field(terms, to, schema)?.is_some_and(|other| other != position) {
+                    return Err(anyhow::anyhow!("Partition field '{}' already exists", to));
+                }
+
+                next[position] = to.clone();
+            }
+        }
+
+        Ok(next)
+    }
+}
+
+/// Byte offset into `sql` of a token's 1-based line and column, which count chars.
+fn byte_offset(sql: &str, location: Location) -> usize {
+    let line_start: usize = sql
+        .split_inclusive('\n')
+        .take(location.line.saturating_sub(1) as usize)
+        .map(str::len)
+        .sum();
+
+    let column = sql[line_start..]
+        .chars()
+        .take(location.column.saturating_sub(1) as usize)
+        .map(char::len_utf8)
+        .sum::<usize>();
+
+    (line_start + column).min(sql.len())
+}
+
+/// Position of the field `term` names among a spec's terms, matched by source
+/// column and transform or by the partition field's name.
+fn partition_field(
+    terms: &[String],
+    term: &str,
+    schema: &SchemaVec
+) -> anyhow::Result<Option<usize>> {
+    let spec = PartitionSpec::from_terms(terms, schema)?;
+
+    // Partition field names, e.g. `ts_day`, are no valid term
+    let named = PartitionSpec::from_terms(&[term.to_string()], schema).ok();
+    let named = named.as_ref().and_then(|named| named.fields.first());
+
+    Ok(
+        spec.fields.iter().position(|field| {
+            field.name == term ||
+                named.is_some_and(|named| {
+                    named.source_id == field.source_id && named.transform == field.transform
+                })
+        })
+    )
+}
+
+fn existing_partition_field(
+    terms: &[String],
+    term: &str,
+    schema: &SchemaVec
+) -> anyhow::Result<usize> {
+    partition_field(terms, term, schema)?.ok_or_else(|| {
+        anyhow::anyhow!("Partition field '{}' not found", term)
+    })
+}
+
+impl SchemaVec {
+    /// Applies a change and returns the next schema version.
+    ///
+    /// `last_field_id` is the highest id the table has ever used, added columns are
+    /// numbered after it so ids of dropped columns are never handed out again.
+    pub fn evolve(&self, change: &SchemaChange, last_field_id: i32) -> anyhow::Result<SchemaVec> {
+        let mut next = self.clone();
+
+        match change {
+            SchemaChange::AddColumn { name, datatype } => {
+                if self.column_by_name(name).is_some() {
+                    return Err(anyhow::anyhow!("Column '{}' already exists", name));
+                }
+
+                next.add(Column {
+                    field_id: last_field_id.max(self.last_field_id()) + 1,
+                    name: name.clone(),
+                    datatype: datatype.clone(),
+                    nullable: true,
+                    unique: false,
+                    references: None,
+                });
+            }
+            SchemaChange::DropColumn { name } => {
+                self.existing(name)?;
+
+                next.columns.retain(|column| &column.name != name);
+            }
+            SchemaChange::RenameColumn { from, to } => {
+                self.existing(from)?;
+
+                if self.column_by_name(to).is_some() {
+                    return Err(anyhow::anyhow!("Column '{}' already exists", to));
+                }
+
+                for column in next.columns.iter_mut().filter(|column| &column.name == from) {
+                    column.name = to.clone();
+                }
+            }
+            SchemaChange::WidenColumn { name, datatype } => {
+                let column = self.existing(name)?;
+
+                if !can_widen(&column.datatype, datatype) {
+                    return Err(
+                        anyhow::anyhow!(
+                            "Cannot change column '{}' from {} to {}",
+                            name,
+                            column.datatype,
+                            datatype
+                        )
+                    );
+                }
+
+                for column in next.columns.iter_mut().filter(|column| &column.name == name) {
+                    column.datatype = datatype.clone();
+                }
+            }
+        }
+
+        Ok(next)
+    }
+
+    fn existing(&self, name: &str) -> anyhow::Result<&Column> {
+        self.column_by_name(name).ok_or_else(|| anyhow::anyhow!("Column '{}' not found", name))
+    }
+}
+
+/// Type promotions that never lose information, so old files can be read as the new type.
+pub fn can_widen(from: &DataType, to: &DataType) -> bool {
+    matches!(
+        (from, to),
+        (DataType::Int32, DataType::Int64) | (DataType::Float32, DataType::Float64)
+    ) || from == to
+}
+
+/// Column identifiers are case-folded unless quoted, as the SQL planner does.
+pub(crate) fn normalize(ident: &Ident) -> String {
+    match ident.quote_style {
+        Some(_) => ident.value.clone(),
+        None => ident.value.to_lowercase(),
+    }
+}
+
+fn arrow_data_type(data_type: &SqlDataType) -> anyhow::Result<DataType> {
+    let datatype = match data_type {
+        SqlDataType::TinyInt(_) => DataType::Int8,
+        SqlDataType::SmallInt(_) => DataType::Int16,
+        SqlDataType::Int(_) | SqlDataType::Integer(_) | SqlDataType::Int32 => DataType::Int32,
+        SqlDataType::BigInt(_) | SqlDataType::Int64 => DataType::Int64,
+        SqlDataType::Float(_) | SqlDataType::Real | SqlDataType::Float32 => DataType::Float32,
+        SqlDataType::Double(_) | SqlDataType::DoublePrecision | SqlDataType::Float64 => {
+            DataType::Float64
+        }
+        SqlDataType::Bool | SqlDataType::Boolean => DataType::Boolean,
+        SqlDataType::Varchar(_) | SqlDataType::Text | SqlDataType::String(_) => DataType::Utf8,
+        SqlDataType::Date => DataType::Date32,
+        SqlDataType::Timestamp(_, _) => DataType::Timestamp(TimeUnit::Nanosecond, None),
+        other => {
+            return Err(anyhow::anyhow!("Unsupported column type {}", other));
+        }
+    };
+
+    Ok(datatype)
+}
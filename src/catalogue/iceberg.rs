@@ -0,0 +1,697 @@
+//! Iceberg v2 table files: `metadata.json`, manifest lists and Avro manifests.
+//!
+//! The catalogue stays the source of truth for a table. Every snapshot the engine
+//! commits also gets a manifest list naming its files, and a new `metadata.json` is
+//! written once the commit lands, so engines reading Iceberg can read the table
+//! from its location.
+
+use std::{ collections::BTreeMap, sync::Arc };
+
+use apache_avro::{ from_value, types::Value as AvroValue, Reader, Schema as AvroSchema, Writer };
+
+use arrow_schema::DataType;
+
+use datafusion::catalog::Session;
+use datafusion::datasource::listing::ListingTableUrl;
+
+use object_store::{ path::Path, ObjectStore };
+
+use serde::de::DeserializeOwned;
+use serde::{ Deserialize, Serialize };
+use serde_json::{ json, Value };
+
+use uuid::Uuid;
+
+use crate::catalogue::{
+    catalogue_storage::Catalog,
+    partitioning::PartitionSpec,
+    rest::model::{ IcebergSchema, IcebergTableMetadata },
+    snapshots::{ CommitConflict, DataFile, Operation, Snapshot },
+    tables::{ TableIdent, TableMetadata },
+};
+use crate::lake_engine::MAX_COMMIT_ATTEMPTS;
+
+/// Directory under a table's location holding its Iceberg files.
+const METADATA_DIR: &str = "metadata";
+/// Names the current `v<n>.metadata.json` of tables written without a catalog.
+const VERSION_HINT: &str = "version-hint.text";
+
+/// Manifest entry status of a file carried over from an earlier snapshot.
+const STATUS_EXISTING: i32 = 0;
+/// Manifest entry status of a file added by the manifest's snapshot.
+const STATUS_ADDED: i32 = 1;
+/// Manifest entry status of a file removed by the manifest's snapshot.
+const STATUS_DELETED: i32 = 2;
+/// Manifest and data file content of plain data, as opposed to delete files.
+const CONTENT_DATA: i32 = 0;
+
+/// Avro schema of a v2 manifest list, with the field ids of the Iceberg spec.
+const MANIFEST_LIST_SCHEMA: &str =
+    r#"
+{
+    "type": "record",
+    "name": "manifest_file",
+    "fields": [
+        { "name": "manifest_path", "type": "string", "field-id": 500 },
+        { "name": "manifest_length", "type": "long", "field-id": 501 },
+        { "name": "partition_spec_id", "type": "int", "field-id": 502 },
+        { "name": "content", "type": "int", "field-id": 517 },
+        { "name": "sequence_number", "type": "long", "field-id": 515 },
+        { "name": "min_sequence_number", "type": "long", "field-id": 516 },
+        { "name": "added_snapshot_id", "type": "long", "field-id": 503 },
+        { "name": "added_files_count", "type": "int", "field-id": 504 },
+        { "name": "existing_files_count", "type": "int", "field-id": 505 },
+        { "name": "deleted_files_count", "type": "int", "field-id": 506 },
+        { "name": "added_rows_count", "type": "long", "field-id": 512 },
+        { "name": "existing_rows_count", "type": "long", "field-id": 513 },
+        { "name": "deleted_rows_count", "type": "long", "field-id": 514 }
+    ]
+}
+"#;
+
+/// An entry of an Iceberg manifest list.
+///
+/// Fields only used when the entry is carried into a new manifest list default to
+/// zero, counts are optional in format v1 manifest lists.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ManifestFile {
+    manifest_path: String,
+    #[serde(default)]
+    manifest_length: i64,
+    #[serde(default)]
+    partition_spec_id: i32,
+    /// Absent from format v1 manifest lists, which only track data.
+    #[serde(default)]
+    content: i32,
+    #[serde(default)]
+    sequence_number: i64,
+    #[serde(default)]
+    min_sequence_number: i64,
+    #[serde(default)]
+    added_snapshot_id: i64,
+    #[serde(default)]
+    added_files_count: i32,
+    #[serde(default)]
+    existing_files_count: i32,
+    #[serde(default)]
+    deleted_files_count: i32,
+    #[serde(default)]
+    added_rows_count: i64,
+    #[serde(default)]
+    existing_rows_count: i64,
+    #[serde(default)]
+    deleted_rows_count: i64,
+}
+
+/// An entry of an Iceberg manifest.
+#[derive(Debug, Deserialize)]
+struct ManifestEntry {
+    status: i32,
+    data_file: ManifestDataFile,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestDataFile {
+    #[serde(default)]
+    content: i32,
+    file_path: String,
+    file_format: String,
+    record_count: i64,
+    file_size_in_bytes: i64,
+}
+
+/// A data file to record in a manifest.
+struct Entry<'a> {
+    status: i32,
+    /// The snapshot that added the file, or for deleted entries removed it.
+    snapshot_id: i64,
+    /// Data sequence number of the file, `None` inherits the manifest's.
+    sequence_number: Option<i64>,
+    file: &'a DataFile,
+}
+
+impl Entry<'_> {
+    /// `partition_types` are the result types of the spec's fields, in spec order.
+    fn to_avro(
+        &self,
+        spec: &PartitionSpec,
+        partition_types: &[DataType]
+    ) -> anyhow::Result<AvroValue> {
+        let optional = |value: Option<i64>| {
+            match value {
+                Some(value) => AvroValue::Union(1, Box::new(AvroValue::Long(value))),
+                None => AvroValue::Union(0, Box::new(AvroValue::Null)),
+            }
+        };
+
+        // Files registered from elsewhere may carry no partition values
+        let partition = spec.fields
+            .iter()
+            .zip(partition_types)
+            .enumerate()
+            .map(|(index, (field, datatype))| {
+                let value = self.file.partition_values.get(index).cloned().flatten();
+                Ok((field.name.clone(), partition_value(datatype, value)?))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let data_file = vec![
+            ("content".to_string(), AvroValue::Int(CONTENT_DATA)),
+            ("file_path".to_string(), AvroValue::String(self.file.file_path.clone())),
+            ("file_format".to_string(), AvroValue::String("PARQUET".to_string())),
+            ("partition".to_string(), AvroValue::Record(partition)),
+            ("record_count".to_string(), AvroValue::Long(self.file.row_count as i64)),
+            ("file_size_in_bytes".to_string(), AvroValue::Long(self.file.file_size as i64))
+        ];
+
+        Ok(
+            AvroValue::Record(
+                vec![
+                    ("status".to_string(), AvroValue::Int(self.status)),
+                    ("snapshot_id".to_string(), optional(Some(self.snapshot_id))),
+                    ("sequence_number".to_string(), optional(self.sequence_number)),
+                    ("file_sequence_number".to_string(), optional(self.sequence_number)),
+                    ("data_file".to_string(), AvroValue::Record(data_file))
+                ]
+            )
+        )
+    }
+}
+
+/// A random positive id for a snapshot about to be committed, Iceberg snapshot ids
+/// are unique per table.
+pub(crate) fn new_snapshot_id() -> i64 {
+    (Uuid::new_v4().as_u64_pair().0 >> 1) as i64
+}
+
+/// The live data files of a snapshot.
+///
+/// Snapshots written by this engine list their files. Those of tables registered
+/// from elsewhere are read from their manifests, the files are given `schema_id`.
+pub(crate) async fn snapshot_files(
+    state: &dyn Session,
+    snapshot: &Snapshot,
+    schema_id: i64
+) -> anyhow::Result<Vec<DataFile>> {
+    match &snapshot.manifest_list {
+        Some(manifest_list) if snapshot.data_files.is_empty() => {
+            read_manifest_list(state, manifest_list, schema_id).await
+        }
+        _ => Ok(snapshot.data_files.clone()),
+    }
+}
+
+/// Writes the manifests of a pending snapshot and the manifest list naming them,
+/// returning the manifest list's url.
+///
+/// `added` files get a manifest of their own. Appends carry the manifests of the
+/// parent over, overwrites record the parent's files as deleted.
+pub(crate) async fn write_manifests(
+    state: &dyn Session,
+    table: &TableMetadata,
+    snapshot: &Snapshot,
+    parent: Option<&Snapshot>,
+    added: &[DataFile]
+) -> anyhow::Result<String> {
+    let entries = added
+        .iter()
+        .map(|file| Entry {
+            status: STATUS_ADDED,
+            snapshot_id: snapshot.snapshot_id,
+            sequence_number: None,
+            file,
+        })
+        .collect::<Vec<_>>();
+
+    let mut manifests = write_manifests_by_spec(state, table, snapshot, &entries).await?;
+
+    match (snapshot.operation, parent) {
+        (_, None) => {}
+        (Operation::Append, Some(parent)) => {
+            match &parent.manifest_list {
+                Some(manifest_list) => {
+                    manifests.extend(read_avro::<ManifestFile>(state, manifest_list).await?);
+                }
+                None => {
+                    // Committed before manifests were written, its files are carried
+                    // over as added by the parent
+                    let entries = parent.data_files
+                        .iter()
+                        .map(|file| Entry {
+                            status: STATUS_EXISTING,
+                            snapshot_id: parent.snapshot_id,
+                            sequence_number: Some(parent.sequence_number),
+                            file,
+                        })
+                        .collect::<Vec<_>>();
+
+                    manifests.extend(
+                        write_manifests_by_spec(state, table, snapshot, &entries).await?
+                    );
+                }
+            }
+        }
+        (Operation::Overwrite, Some(parent)) => {
+            let removed = snapshot_files(state, parent, table.schema_id).await?;
+            let entries = removed
+                .iter()
+                .map(|file| Entry {
+                    status: STATUS_DELETED,
+                    snapshot_id: snapshot.snapshot_id,
+                    sequence_number: None,
+                    file,
+                })
+                .collect::<Vec<_>>();
+
+            manifests.extend(write_manifests_by_spec(state, table, snapshot, &entries).await?);
+        }
+    }
+
+    let schema = AvroSchema::parse_str(MANIFEST_LIST_SCHEMA)?;
+    let mut writer = Writer::new(&schema, Vec::new())?;
+
+    writer.add_user_metadata("snapshot-id".to_string(), snapshot.snapshot_id.to_string())?;
+    writer.add_user_metadata(
+        "parent-snapshot-id".to_string(),
+        snapshot.parent_snapshot_id.map_or("null".to_string(), |id| id.to_string())
+    )?;
+    writer.add_user_metadata("sequence-number".to_string(), snapshot.sequence_number.to_string())?;
+    writer.add_user_metadata("format-version".to_string(), "2")?;
+
+    for manifest in manifests {
+        writer.append_ser(manifest)?;
+    }
+
+    let url = metadata_url(
+        &table.location,
+        &format!("snap-{}-{}.avro", snapshot.snapshot_id, Uuid::new_v4())
+    );
+    put(state, &url, writer.into_inner()?).await?;
+
+    Ok(url)
+}
+
+/// Writes a table's current state as its next `metadata.json` and points the
+/// catalogue at it, returning its url.
+///
+/// The file is rebuilt from the catalogue if another writer moved the pointer first.
+pub(crate) async fn write_table_metadata(
+    state: &dyn Session,
+    catalogue: &(dyn Catalog + Send + Sync),
+    ident: &TableIdent
+) -> anyhow::Result<String> {
+    for _ in 0..MAX_COMMIT_ATTEMPTS {
+        let table = catalogue.load_table(ident)?;
+        let versions = catalogue.list_schema_versions(&table.table_id)?;
+        let snapshots = catalogue.list_snapshots(&table.table_id)?;
+
+        let metadata = IcebergTableMetadata::new(&table, &versions, &snapshots)?;
+        let version = table.metadata_location.as_deref().map_or(0, metadata_version) + 1;
+
+        let url = metadata_url(
+            &table.location,
+            &format!("{:05}-{}.metadata.json", version, Uuid::new_v4())
+        );
+        put(state, &url, serde_json::to_vec_pretty(&metadata)?).await?;
+
+        let expected = table.metadata_location.as_deref();
+        match catalogue.set_metadata_location(&table.table_id, expected, &url) {
+            Ok(()) => {
+                return Ok(url);
+            }
+            Err(e) if e.is::<CommitConflict>() => {
+                continue;
+            }
+            Err(e) => {
+                return Err(e);
+            }
+        }
+    }
+
+    Err(
+        anyhow::anyhow!(
+            "Gave up writing the metadata of table '{}' after {} conflicting attempts",
+            ident,
+            MAX_COMMIT_ATTEMPTS
+        )
+    )
+}
+
+/// Reads the current `metadata.json` of an Iceberg table, returning its url too.
+///
+/// `location` is either the url of a `metadata.json` or the table's location, whose
+/// `metadata/` directory is searched for `version-hint.text` and otherwise for the
+/// highest numbered `metadata.json`.
+pub(crate) async fn read_table_metadata(
+    state: &dyn Session,
+    location: &str
+) -> anyhow::Result<(String, IcebergTableMetadata)> {
+    let url = match location.ends_with(".metadata.json") {
+        true => location.to_string(),
+        false => find_table_metadata(state, location).await?,
+    };
+
+    let bytes = get(state, &url)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Table metadata {} not found", url))?;
+
+    Ok((url.clone(), serde_json::from_slice(&bytes)?))
+}
+
+async fn find_table_metadata(state: &dyn Session, location: &str) -> anyhow::Result<String> {
+    if let Some(hint) = get(state, &metadata_url(location, VERSION_HINT)).await? {
+        let version = String::from_utf8(hint)?;
+
+        return Ok(metadata_url(location, &format!("v{}.metadata.json", version.trim())));
+    }
+
+    let (store, path) = object(state, &metadata_url(location, ""))?;
+    let listing = store.list_with_delimiter(Some(&path)).await?;
+
+    listing.objects
+        .iter()
+        .filter_map(|object| object.location.filename())
+        .filter(|name| name.ends_with(".metadata.json"))
+        .max_by_key(|name| metadata_version(name))
+        .map(|name| metadata_url(location, name))
+        .ok_or_else(|| anyhow::anyhow!("No Iceberg table metadata found under {}", location))
+}
+
+/// Reads the live data files of an Iceberg snapshot from its manifest list, giving
+/// them `schema_id`.
+///
+/// Partition values are not read, the files count as written with the table's
+/// first partition spec.
+async fn read_manifest_list(
+    state: &dyn Session,
+    manifest_list: &str,
+    schema_id: i64
+) -> anyhow::Result<Vec<DataFile>> {
+    let mut data_files = Vec::new();
+
+    for manifest in read_avro::<ManifestFile>(state, manifest_list).await? {
+        if manifest.content != CONTENT_DATA {
+            return Err(
+                anyhow::anyhow!("Tables with delete files are not supported: {}", manifest_list)
+            );
+        }
+
+        for entry in read_avro::<ManifestEntry>(state, &manifest.manifest_path).await? {
+            if entry.status == STATUS_DELETED {
+                continue;
+            }
+
+            let file = entry.data_file;
+
+            if file.content != CONTENT_DATA {
+                return Err(anyhow::anyhow!("Unexpected delete file {}", file.file_path));
+            }
+
+            if !file.file_format.eq_ignore_ascii_case("parquet") {
+                return Err(
+                    anyhow::anyhow!("Unsupported {} data file {}", file.file_format, file.file_path)
+                );
+            }
+
+            data_files.push(DataFile {
+                file_path: file.file_path,
+                row_count: file.record_count as u64,
+                file_size: file.file_size_in_bytes as u64,
+                schema_id,
+                spec_id: 0,
+                partition_values: Vec::new(),
+                column_stats: BTreeMap::new(),
+            });
+        }
+    }
+
+    Ok(data_files)
+}
+
+/// Writes a manifest for each partition spec `entries` were written with, a
+/// manifest only holds files of a single spec.
+async fn write_manifests_by_spec(
+    state: &dyn Session,
+    table: &TableMetadata,
+    snapshot: &Snapshot,
+    entries: &[Entry<'_>]
+) -> anyhow::Result<Vec<ManifestFile>> {
+    let mut by_spec: BTreeMap<i32, Vec<&Entry>> = BTreeMap::new();
+    for entry in entries {
+        by_spec.entry(entry.file.spec_id).or_default().push(entry);
+    }
+
+    // An empty manifest is still written for a snapshot that adds nothing
+    if by_spec.is_empty() {
+        by_spec.insert(table.default_spec_id, Vec::new());
+    }
+
+    let mut manifests = Vec::with_capacity(by_spec.len());
+    for (spec_id, entries) in by_spec {
+        let spec = table.partition_specs
+            .iter()
+            .find(|spec| spec.spec_id == spec_id)
+            .cloned()
+            .unwrap_or_else(|| table.current_spec());
+
+        manifests.push(write_manifest(state, table, &spec, snapshot, &entries).await?);
+    }
+
+    Ok(manifests)
+}
+
+/// Writes a manifest of `entries` for `snapshot`, returning its manifest list entry.
+async fn write_manifest(
+    state: &dyn Session,
+    table: &TableMetadata,
+    spec: &PartitionSpec,
+    snapshot: &Snapshot,
+    entries: &[&Entry<'_>]
+) -> anyhow::Result<ManifestFile> {
+    let partition_types = spec.partition_types(&table.schema)?;
+    let iceberg_schema = IcebergSchema::from_schema(table.schema_id, &table.schema)?;
+
+    let schema = manifest_entry_schema(spec, &partition_types)?;
+    let schema = AvroSchema::parse_str(&schema.to_string())?;
+    let mut writer = Writer::new(&schema, Vec::new())?;
+
+    writer.add_user_metadata("schema".to_string(), serde_json::to_string(&iceberg_schema)?)?;
+    writer.add_user_metadata("schema-id".to_string(), table.schema_id.to_string())?;
+    writer.add_user_metadata("partition-spec".to_string(), serde_json::to_string(&spec.fields)?)?;
+    writer.add_user_metadata("partition-spec-id".to_string(), spec.spec_id.to_string())?;
+    writer.add_user_metadata("format-version".to_string(), "2")?;
+    writer.add_user_metadata("content".to_string(), "data")?;
+
+    for entry in entries {
+        writer.append_value(entry.to_avro(spec, &partition_types)?)?;
+    }
+
+    let bytes = writer.into_inner()?;
+    let url = metadata_url(&table.location, &format!("{}-m0.avro", Uuid::new_v4()));
+    let manifest_length = bytes.len() as i64;
+
+    put(state, &url, bytes).await?;
+
+    let count = |status: i32| entries.iter().filter(|entry| entry.status == status).count();
+    let rows = |status: i32| {
+        entries
+            .iter()
+            .filter(|entry| entry.status == status)
+            .map(|entry| entry.file.row_count as i64)
+            .sum::<i64>()
+    };
+
+    Ok(ManifestFile {
+        manifest_path: url,
+        manifest_length,
+        partition_spec_id: spec.spec_id,
+        content: CONTENT_DATA,
+        sequence_number: snapshot.sequence_number,
+        min_sequence_number: entries
+            .iter()
+            .filter(|entry| entry.status != STATUS_DELETED)
+            .map(|entry| entry.sequence_number.unwrap_or(snapshot.sequence_number))
+            .min()
+            .unwrap_or(snapshot.sequence_number),
+        added_snapshot_id: snapshot.snapshot_id,
+        added_files_count: count(STATUS_ADDED) as i32,
+        existing_files_count: count(STATUS_EXISTING) as i32,
+        deleted_files_count: count(STATUS_DELETED) as i32,
+        added_rows_count: rows(STATUS_ADDED),
+        existing_rows_count: rows(STATUS_EXISTING),
+        deleted_rows_count: rows(STATUS_DELETED),
+    })
+}
+
+/// Avro schema of a v2 manifest of a table, its partition struct follows the
+/// table's partition spec.
+fn manifest_entry_schema(
+    spec: &PartitionSpec,
+    partition_types: &[DataType]
+) -> anyhow::Result<Value> {
+    let partition = spec.fields
+        .iter()
+        .zip(partition_types)
+        .map(|(field, datatype)| {
+            Ok(
+                json!({
+                    "name": field.name,
+                    "type": ["null", avro_type(datatype)?],
+                    "default": null,
+                    "field-id": field.field_id,
+                })
+            )
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(
+        json!({
+            "type": "record",
+            "name": "manifest_entry",
+            "fields": [
+                { "name": "status", "type": "int", "field-id": 0 },
+                { "name": "snapshot_id", "type": ["null", "long"], "default": null, "field-id": 1 },
+                {
+                    "name": "sequence_number",
+                    "type": ["null", "long"],
+                    "default": null,
+                    "field-id": 3,
+                },
+                {
+                    "name": "file_sequence_number",
+                    "type": ["null", "long"],
+                    "default": null,
+                    "field-id": 4,
+                },
+                {
+                    "name": "data_file",
+                    "field-id": 2,
+                    "type": {
+                        "type": "record",
+                        "name": "r2",
+                        "fields": [
+                            { "name": "content", "type": "int", "field-id": 134 },
+                            { "name": "file_path", "type": "string", "field-id": 100 },
+                            { "name": "file_format", "type": "string", "field-id": 101 },
+                            {
+                                "name": "partition",
+                                "field-id": 102,
+                                "type": { "type": "record", "name": "r102", "fields": partition },
+                            },
+                            { "name": "record_count", "type": "long", "field-id": 103 },
+                            { "name": "file_size_in_bytes", "type": "long", "field-id": 104 },
+                        ],
+                    },
+                },
+            ],
+        })
+    )
+}
+
+/// The Avro type Iceberg stores values of a primitive type as.
+fn avro_type(datatype: &DataType) -> anyhow::Result<Value> {
+    let avro = match datatype {
+        DataType::Boolean => json!("boolean"),
+        DataType::Int8 | DataType::Int16 | DataType::Int32 => json!("int"),
+        DataType::Int64 => json!("long"),
+        DataType::Float32 => json!("float"),
+        DataType::Float64 => json!("double"),
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => json!("string"),
+        DataType::Binary | DataType::LargeBinary | DataType::BinaryView => json!("bytes"),
+        DataType::Date32 => json!({ "type": "int", "logicalType": "date" }),
+        // Partition values of any timestamp are kept in microseconds
+        DataType::Timestamp(_, timezone) => {
+            json!({
+                "type": "long",
+                "logicalType": "timestamp-micros",
+                "adjust-to-utc": timezone.is_some(),
+            })
+        }
+        other => {
+            return Err(anyhow::anyhow!("Partitioning by {} columns is not supported", other));
+        }
+    };
+
+    Ok(avro)
+}
+
+/// A partition value as written to a manifest, see [`Transform::apply`] for the
+/// text it is parsed from.
+///
+/// [`Transform::apply`]: crate::catalogue::partitioning::Transform::apply
+fn partition_value(datatype: &DataType, value: Option<String>) -> anyhow::Result<AvroValue> {
+    let value = match value {
+        Some(value) => value,
+        None => {
+            return Ok(AvroValue::Union(0, Box::new(AvroValue::Null)));
+        }
+    };
+
+    let avro = match datatype {
+        DataType::Boolean => AvroValue::Boolean(value.parse()?),
+        DataType::Int8 | DataType::Int16 | DataType::Int32 => AvroValue::Int(value.parse()?),
+        DataType::Int64 => AvroValue::Long(value.parse()?),
+        DataType::Date32 => AvroValue::Date(value.parse()?),
+        DataType::Timestamp(_, _) => AvroValue::TimestampMicros(value.parse()?),
+        _ => AvroValue::String(value),
+    };
+
+    Ok(AvroValue::Union(1, Box::new(avro)))
+}
+
+/// The version a `metadata.json` is numbered with, `00003-<uuid>.metadata.json`
+/// and `v3.metadata.json` alike. Unnumbered files count as version 0.
+fn metadata_version(location: &str) -> u64 {
+    let name = location.rsplit('/').next().unwrap_or(location);
+
+    name.trim_start_matches('v')
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect::<String>()
+        .parse()
+        .unwrap_or_default()
+}
+
+/// Url of a file in the `metadata/` directory under a table's location.
+fn metadata_url(location: &str, file_name: &str) -> String {
+    format!("{}/{}/{}", location.trim_end_matches('/'), METADATA_DIR, file_name)
+}
+
+/// The object store registered with the session for a url, and the url's path in it.
+pub(crate) fn object(
+    state: &dyn Session,
+    url: &str
+) -> anyhow::Result<(Arc<dyn ObjectStore>, Path)> {
+    let url = ListingTableUrl::parse(url)?;
+    let store = state.runtime_env().object_store(url.object_store())?;
+
+    Ok((store, url.prefix().clone()))
+}
+
+async fn put(state: &dyn Session, url: &str, bytes: Vec<u8>) -> anyhow::Result<()> {
+    let (store, path) = object(state, url)?;
+
+    store.put(&path, bytes.into()).await?;
+
+    Ok(())
+}
+
+/// Reads a whole object, `None` if there is none at `url`.
+async fn get(state: &dyn Session, url: &str) -> anyhow::Result<Option<Vec<u8>>> {
+    let (store, path) = object(state, url)?;
+
+    match store.get(&path).await {
+        Ok(object) => Ok(Some(object.bytes().await?.to_vec())),
+        Err(object_store::Error::NotFound { .. }) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Reads every record of an Avro object container file.
+async fn read_avro<T: DeserializeOwned>(state: &dyn Session, url: &str) -> anyhow::Result<Vec<T>> {
+    let bytes = get(state, url).await?.ok_or_else(|| anyhow::anyhow!("{} not found", url))?;
+
+    Reader::new(&bytes[..])?
+        .map(|value| Ok(from_value::<T>(&value?)?))
+        .collect()
+}
@@ -0,0 +1,340 @@
+use std::{ any::Any, fmt, sync::Arc };
+
+use arrow_array::{ new_null_array, RecordBatch, RecordBatchOptions };
+use arrow_schema::{ Schema, SchemaRef };
+
+use datafusion::arrow::compute::{ can_cast_types, cast };
+use datafusion::catalog::Session;
+use datafusion::datasource::listing::{ ListingTableUrl, PartitionedFile };
+use datafusion::datasource::physical_plan::{ FileGroup, FileScanConfigBuilder, ParquetSource };
+use datafusion::datasource::schema_adapter::{ SchemaAdapter, SchemaAdapterFactory, SchemaMapper };
+use datafusion::datasource::source::DataSourceExec;
+use datafusion::execution::object_store::ObjectStoreUrl;
+use datafusion::datasource::{ TableProvider, TableType };
+use datafusion::error::{ DataFusionError, Result };
+use datafusion::logical_expr::{ Expr, TableProviderFilterPushDown };
+use datafusion::physical_plan::{
+    empty::EmptyExec,
+    project_schema,
+    union::UnionExec,
+    ExecutionPlan,
+};
+
+use crate::catalogue::{
+    iceberg::snapshot_files,
+    partitioning::PartitionSpec,
+    provider::external,
+    snapshots::{ DataFile, Snapshot },
+    statistics::prune_files,
+    tables::{ SchemaVec, SchemaVersion },
+};
+
+/// The data files of one snapshot of a table, read as the table's current schema.
+///
+/// Columns are resolved by the `PARQUET:field_id` stored in each file, so renamed,
+/// reordered, widened and dropped columns read correctly from files written before
+/// the change. Files without field ids are resolved through the schema version they
+/// were written with.
+///
+/// Files that can't match a query's filters are skipped, judged by their partition
+/// values and the column statistics recorded when they were written.
+pub(crate) struct LakeTable {
+    schema: SchemaRef,
+    /// The schema version the table is read as.
+    schema_id: i64,
+    /// Every schema version of the table.
+    versions: Vec<SchemaVersion>,
+    /// Every partition spec of the table, each file is judged by its own.
+    specs: Vec<PartitionSpec>,
+    snapshot: Snapshot,
+}
+
+impl LakeTable {
+    pub(crate) fn new(
+        schema: SchemaRef,
+        schema_id: i64,
+        versions: Vec<SchemaVersion>,
+        specs: Vec<PartitionSpec>,
+        snapshot: Snapshot
+    ) -> Self {
+        LakeTable { schema, schema_id, versions, specs, snapshot }
+    }
+
+    /// The snapshot's files grouped by the schema version they were written with,
+    /// which resolves their columns when they carry no field ids.
+    ///
+    /// Snapshots of tables written elsewhere list their files in manifests, which
+    /// are read here. Iceberg files always carry field ids, they are grouped with the
+    /// schema the table is read as.
+    async fn file_groups(
+        &self,
+        state: &dyn Session,
+        filters: &[Expr]
+    ) -> Result<Vec<(Arc<SchemaVec>, Vec<DataFile>)>> {
+        let data_files = snapshot_files(state, &self.snapshot, self.schema_id).await.map_err(
+            external
+        )?;
+        let data_files = prune_files(state, &self.schema, &self.specs, filters, data_files).map_err(
+            external
+        )?;
+
+        let groups = self.versions
+            .iter()
+            .filter_map(|version| {
+                let files: Vec<DataFile> = data_files
+                    .iter()
+                    .filter(|file| file.schema_id == version.schema_id)
+                    .cloned()
+                    .collect();
+
+                (!files.is_empty()).then(|| (Arc::new(version.schema.clone()), files))
+            })
+            .collect::<Vec<_>>();
+
+        if groups.iter().map(|(_, files)| files.len()).sum::<usize>() != data_files.len() {
+            return Err(
+                DataFusionError::Internal(
+                    format!("Table {} has files with unknown schemas", self.snapshot.table_id)
+                )
+            );
+        }
+
+        Ok(groups)
+    }
+
+    /// Plans reading files written with one schema version, one scan per object
+    /// store the files live in.
+    fn scan_group(
+        &self,
+        written_with: &Arc<SchemaVec>,
+        data_files: &[DataFile],
+        target_partitions: usize,
+        projection: Option<&Vec<usize>>,
+        limit: Option<usize>
+    ) -> Result<Vec<Arc<dyn ExecutionPlan>>> {
+        // Tables catalogued before urls named the lake's location mix both kinds
+        let mut by_store: Vec<(ObjectStoreUrl, Vec<PartitionedFile>)> = Vec::new();
+
+        for file in data_files {
+            let url = ListingTableUrl::parse(&file.file_path)?;
+            let object_store_url = url.object_store();
+            let partitioned = PartitionedFile::new(url.prefix().to_string(), file.file_size);
+
+            match by_store.iter_mut().find(|(store, _)| *store == object_store_url) {
+                Some((_, files)) => files.push(partitioned),
+                None => by_store.push((object_store_url, vec![partitioned])),
+            }
+        }
+
+        let plans = by_store
+            .into_iter()
+            .map(|(object_store_url, files)| {
+                let source = ParquetSource::default().with_schema_adapter_factory(
+                    Arc::new(FieldIdAdapterFactory { written_with: written_with.clone() })
+                );
+
+                let config = FileScanConfigBuilder::new(
+                    object_store_url,
+                    self.schema.clone(),
+                    Arc::new(source)
+                )
+                    .with_file_groups(FileGroup::new(files).split_files(target_partitions))
+                    .with_projection(projection.cloned())
+                    .with_limit(limit)
+                    .build();
+
+                DataSourceExec::from_data_source(config) as Arc<dyn ExecutionPlan>
+            })
+            .collect();
+
+        Ok(plans)
+    }
+}
+
+impl fmt::Debug for LakeTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LakeTable").field("schema", &self.schema).finish()
+    }
+}
+
+#[async_trait::async_trait]
+impl TableProvider for LakeTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    async fn scan(
+        &self,
+        state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let target_partitions = state.config().target_partitions();
+
+        let mut plans = self
+            .file_groups(state, filters).await?
+            .iter()
+            .map(|(written_with, files)| {
+                self.scan_group(written_with, files, target_partitions, projection, limit)
+            })
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+
+        match plans.len() {
+            0 => Ok(Arc::new(EmptyExec::new(project_schema(&self.schema, projection)?))),
+            1 => Ok(plans.remove(0)),
+            _ => Ok(Arc::new(UnionExec::new(plans))),
+        }
+    }
+
+    /// Filters only prune whole files, they are applied to the rows read as well.
+    fn supports_filters_pushdown(
+        &self,
+        filters: &[&Expr]
+    ) -> Result<Vec<TableProviderFilterPushDown>> {
+        Ok(vec![TableProviderFilterPushDown::Inexact; filters.len()])
+    }
+}
+
+/// Maps parquet file columns onto the table schema by field id.
+#[derive(Debug)]
+struct FieldIdAdapterFactory {
+    /// Names of this schema version stand in for the ids of files that have none.
+    written_with: Arc<SchemaVec>,
+}
+
+impl SchemaAdapterFactory for FieldIdAdapterFactory {
+    fn create(
+        &self,
+        projected_table_schema: SchemaRef,
+        _table_schema: SchemaRef
+    ) -> Box<dyn SchemaAdapter> {
+        Box::new(FieldIdAdapter {
+            projected_table_schema,
+            written_with: self.written_with.clone(),
+        })
+    }
+}
+
+struct FieldIdAdapter {
+    projected_table_schema: SchemaRef,
+    written_with: Arc<SchemaVec>,
+}
+
+impl FieldIdAdapter {
+    /// Index of the file column holding the table field with `field_id`.
+    fn file_index(&self, field_id: i32, file_schema: &Schema) -> Option<usize> {
+        file_schema.fields()
+            .iter()
+            .position(|field| self.file_field_id(field) == Some(field_id))
+    }
+
+    fn file_field_id(&self, field: &arrow_schema::Field) -> Option<i32> {
+        SchemaVec::field_id(field).or_else(|| {
+            self.written_with.column_by_name(field.name()).map(|column| column.field_id)
+        })
+    }
+}
+
+impl SchemaAdapter for FieldIdAdapter {
+    fn map_column_index(&self, index: usize, file_schema: &Schema) -> Option<usize> {
+        let field_id = SchemaVec::field_id(self.projected_table_schema.field(index))?;
+
+        self.file_index(field_id, file_schema)
+    }
+
+    fn map_schema(&self, file_schema: &Schema) -> Result<(Arc<dyn SchemaMapper>, Vec<usize>)> {
+        let mut matches = Vec::new();
+
+        for (table_index, table_field) in self.projected_table_schema.fields().iter().enumerate() {
+            let file_index = match SchemaVec::field_id(table_field) {
+                Some(field_id) => self.file_index(field_id, file_schema),
+                None => file_schema.index_of(table_field.name()).ok(),
+            };
+
+            let Some(file_index) = file_index else {
+                continue;
+            };
+
+            let file_field = file_schema.field(file_index);
+            if !can_cast_types(file_field.data_type(), table_field.data_type()) {
+                return Err(
+                    DataFusionError::Plan(
+                        format!(
+                            "Cannot read file column {} of type {} as {} of type {}",
+                            file_field.name(),
+                            file_field.data_type(),
+                            table_field.name(),
+                            table_field.data_type()
+                        )
+                    )
+                );
+            }
+
+            matches.push((file_index, table_index));
+        }
+
+        // The parquet reader returns projected columns in file order
+        matches.sort();
+
+        let mut projection = Vec::with_capacity(matches.len());
+        let mut field_mappings = vec![None; self.projected_table_schema.fields().len()];
+
+        for (file_index, table_index) in matches {
+            field_mappings[table_index] = Some(projection.len());
+            projection.push(file_index);
+        }
+
+        Ok((
+            Arc::new(FieldIdMapping {
+                projected_table_schema: self.projected_table_schema.clone(),
+                field_mappings,
+            }),
+            projection,
+        ))
+    }
+}
+
+/// Casts the projected file columns to the table types and fills columns the file
+/// predates with nulls.
+#[derive(Debug)]
+struct FieldIdMapping {
+    projected_table_schema: SchemaRef,
+    /// For each table column, its index in the projected file batch.
+    field_mappings: Vec<Option<usize>>,
+}
+
+impl SchemaMapper for FieldIdMapping {
+    fn map_batch(&self, batch: RecordBatch) -> Result<RecordBatch> {
+        let num_rows = batch.num_rows();
+
+        let columns = self.projected_table_schema
+            .fields()
+            .iter()
+            .zip(&self.field_mappings)
+            .map(|(field, mapping)| {
+                match mapping {
+                    Some(index) => Ok(cast(batch.column(*index), field.data_type())?),
+                    None => Ok(new_null_array(field.data_type(), num_rows)),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let options = RecordBatchOptions::new().with_row_count(Some(num_rows));
+
+        let schema = self.projected_table_schema.clone();
+
+        Ok(RecordBatch::try_new_with_options(schema, columns, &options)?)
+    }
+}
@@ -3,14 +3,76 @@
 pub mod tables;
 pub mod sql_strings;
 pub mod catalogue_storage;
+pub mod evolution;
+pub mod provider;
+pub mod lake_table;
+pub mod iceberg;
+pub mod partitioning;
+pub mod statistics;
+pub mod delta;
+pub mod snapshots;
+pub mod time_travel;
+pub mod rest;
 
-use std::{ fs::remove_file, path::PathBuf };
+use std::{ fs::remove_file, path::{ Path, PathBuf }, sync::Arc };
 use dashmap::DashMap;
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 use anyhow;
+use uuid::Uuid;
 
-use crate::catalogue::sql_strings::CREATE_SYSTEM_TABLE_SQL;
+use crate::catalogue::{
+    catalogue_storage::Catalog,
+    rest::client::RestCatalogue,
+    sql_strings::{
+        CREATE_SYSTEM_TABLE_SQL,
+        MIGRATE_SYSTEM_TABLES_V1_SQL,
+        MIGRATE_SYSTEM_TABLES_V2_SQL,
+        SELECT_LEGACY_SYS_TABLES,
+        SELECT_NAME_SYS_TABLES,
+    },
+    tables::TableIdent,
+};
+
+/// Layout version of the system tables, stored in the database's `user_version`.
+const CATALOGUE_VERSION: i64 = 2;
+
+/// Catalogue path that keeps the catalogue in memory for as long as it is open.
+pub const IN_MEMORY_CATALOGUE: &str = ":memory:";
+
+/// Where a catalogue keeps its metadata.
+#[derive(Debug, Clone)]
+pub enum CatalogueLocation {
+    /// A SQLite catalogue, a file path or SQLite `file:` URI. [`IN_MEMORY_CATALOGUE`]
+    /// keeps it in memory for as long as it is open.
+    Sqlite(PathBuf),
+    /// An Iceberg REST catalog, e.g. `http://localhost:8181`.
+    Rest {
+        uri: String,
+        /// Sent as a bearer token with every request.
+        token: Option<String>,
+    },
+}
+
+impl CatalogueLocation {
+    pub(crate) fn open(&self) -> anyhow::Result<Arc<dyn Catalog + Send + Sync>> {
+        match self {
+            CatalogueLocation::Sqlite(path) => Ok(Arc::new(RootCatalogue::start(path.clone())?)),
+            CatalogueLocation::Rest { uri, token } => {
+                Ok(Arc::new(RestCatalogue::connect(uri, token.as_deref())?))
+            }
+        }
+    }
+}
+
+impl From<PathBuf> for CatalogueLocation {
+    fn from(path: PathBuf) -> Self {
+        CatalogueLocation::Sqlite(path)
+    }
+}
+
+// TODO:
+// - Implement schema serialization and deserialization
 
 ///
 ///
@@ -21,48 +83,144 @@ use crate::catalogue::sql_strings::CREATE_SYSTEM_TABLE_SQL;
 ///
 /// which is provided by the catalog when you load a table from local or cloud stores in the
 /// form of parquet files.
-
-// TODO:
-// - Implement schema serialization and deserialization
-// - Finalize write path by implementing partitioning
-
 pub(crate) struct RootCatalogue {
     pub(crate) db: Pool<SqliteConnectionManager>,
-    pub(crate) tables: DashMap<i64, String>,
+    pub(crate) tables: DashMap<i64, TableIdent>,
+    /// `None` for an in-memory catalogue.
+    pub(crate) db_path: Option<PathBuf>,
 }
 
 impl RootCatalogue {
-    pub(crate) fn start() -> anyhow::Result<Self> {
-        let db_path = PathBuf::from("db.db");
+    /// Opens the catalogue stored at `location`, creating it if needed.
+    ///
+    /// `location` is a file path or a SQLite `file:` URI, [`IN_MEMORY_CATALOGUE`]
+    /// opens a fresh in-memory catalogue instead.
+    pub(crate) fn start(location: PathBuf) -> anyhow::Result<Self> {
+        if location == Path::new(IN_MEMORY_CATALOGUE) {
+            return RootCatalogue::in_memory();
+        }
+
+        let manager = SqliteConnectionManager::file(&location);
+
+        let pool = Pool::builder().build(manager)?;
 
-        let manager = SqliteConnectionManager::file(&db_path);
+        RootCatalogue::open(pool, database_file(&location))
+    }
+
+    /// Opens an empty catalogue that lives only as long as it is open.
+    ///
+    /// Every pooled connection shares the same private database, which is dropped
+    /// together with the pool.
+    pub(crate) fn in_memory() -> anyhow::Result<Self> {
+        let uri = format!("file:unakite-{}?mode=memory&cache=shared", Uuid::new_v4());
+        let manager = SqliteConnectionManager::file(uri);
 
-        let pool = Pool::builder().build(manager).expect("DB pool failed");
+        // The database is gone once its last connection closes, so pooled
+        // connections are never retired
+        let pool = Pool::builder()
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .build(manager)?;
 
-        let conn = pool.get().unwrap();
-        conn.execute_batch(CREATE_SYSTEM_TABLE_SQL).expect("System table creation failed");
+        RootCatalogue::open(pool, None)
+    }
 
-        let mut statement = conn.prepare("SELECT table_id, table_name FROM sys_tables")?;
+    fn open(
+        pool: Pool<SqliteConnectionManager>,
+        db_path: Option<PathBuf>
+    ) -> anyhow::Result<Self> {
+        let mut conn = pool.get()?;
+        RootCatalogue::migrate(&mut conn)?;
+
+        let mut statement = conn.prepare(SELECT_NAME_SYS_TABLES)?;
 
         let table_iter = statement.query_map([], |row| {
-            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            Ok((
+                row.get::<_, i64>(0)?,
+                TableIdent {
+                    namespace: row.get(1)?,
+                    name: row.get(2)?,
+                },
+            ))
         })?;
 
-        let tables: DashMap<i64, String> = DashMap::new();
+        let tables: DashMap<i64, TableIdent> = DashMap::new();
         for table_result in table_iter {
-            let (table_id, table_name) = table_result?;
-            tables.insert(table_id, table_name);
+            let (table_id, ident) = table_result?;
+            tables.insert(table_id, ident);
         }
 
         Ok(RootCatalogue {
             db: pool,
             tables,
+            db_path,
         })
     }
 
+    /// Creates the system tables, upgrading ones written by an older version in place.
+    fn migrate(conn: &mut rusqlite::Connection) -> anyhow::Result<()> {
+        let tx = conn.transaction()?;
+
+        let version: i64 = tx.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version > CATALOGUE_VERSION {
+            return Err(
+                anyhow::anyhow!(
+                    "Catalogue version {} is newer than the supported version {}",
+                    version,
+                    CATALOGUE_VERSION
+                )
+            );
+        }
+
+        tx.execute_batch(CREATE_SYSTEM_TABLE_SQL)?;
+
+        if version < 1 && tx.query_row(SELECT_LEGACY_SYS_TABLES, [], |row| row.get(0))? {
+            tx.execute_batch(MIGRATE_SYSTEM_TABLES_V1_SQL)?;
+        }
+
+        if version < 2 {
+            tx.execute_batch(MIGRATE_SYSTEM_TABLES_V2_SQL)?;
+        }
+
+        tx.pragma_update(None, "user_version", CATALOGUE_VERSION)?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    #[allow(dead_code)]
     pub(crate) fn destroy(self) -> anyhow::Result<()> {
-        remove_file(PathBuf::from("db.db"))?;
+        let RootCatalogue { db, db_path, .. } = self;
+        drop(db);
+
+        if let Some(db_path) = db_path {
+            remove_file(db_path)?;
+        }
 
         Ok(())
     }
 }
+
+/// The database file behind a catalogue location, `None` if it lives in memory.
+fn database_file(location: &Path) -> Option<PathBuf> {
+    let uri = match location.to_str().and_then(|location| location.strip_prefix("file:")) {
+        Some(uri) => uri,
+        None => {
+            return Some(location.to_path_buf());
+        }
+    };
+
+    let (path, query) = uri.split_once('?').unwrap_or((uri, ""));
+
+    if path == IN_MEMORY_CATALOGUE || query.split('&').any(|param| param == "mode=memory") {
+        return None;
+    }
+
+    // `file://host/path` names the host before the path
+    let path = match path.strip_prefix("//") {
+        Some(rest) => &rest[rest.find('/').unwrap_or(rest.len())..],
+        None => path,
+    };
+
+    Some(PathBuf::from(path))
+}
@@ -0,0 +1,768 @@
+use std::{ collections::HashMap, fmt };
+
+use datafusion::arrow::compute::{ cast, take_record_batch };
+use arrow_array::{
+    cast::AsArray,
+    types::Int64Type,
+    Array,
+    ArrayRef,
+    BooleanArray,
+    Int64Array,
+    RecordBatch,
+    StringArray,
+    UInt32Array,
+};
+use arrow_schema::{ DataType, Schema, TimeUnit };
+
+use datafusion::common::ScalarValue;
+
+use serde::{ Deserialize, Serialize };
+
+use crate::catalogue::tables::SchemaVec;
+
+/// Iceberg assigns partition field ids from 1000 up, apart from column ids.
+pub const PARTITION_FIELD_ID_START: i32 = 1000;
+
+const MICROS_PER_HOUR: i64 = 3_600_000_000;
+const MICROS_PER_DAY: i64 = 24 * MICROS_PER_HOUR;
+
+/// How a partition value is derived from its source column, named as in the
+/// Iceberg spec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    Identity,
+    /// Years since 1970.
+    Year,
+    /// Months since 1970-01.
+    Month,
+    /// Days since 1970-01-01.
+    Day,
+    /// Hours since 1970-01-01 00:00.
+    Hour,
+    /// Murmur3 hash of the value modulo the bucket count.
+    Bucket(u32),
+    /// Integers rounded down to a multiple of the width, strings cut to the width.
+    Truncate(u32),
+}
+
+impl Transform {
+    /// Parses an Iceberg transform name, e.g. `day` or `bucket[16]`.
+    pub fn parse(name: &str) -> anyhow::Result<Self> {
+        let name = name.trim().to_ascii_lowercase();
+
+        let transform = match name.as_str() {
+            "identity" => Transform::Identity,
+            "year" | "years" => Transform::Year,
+            "month" | "months" => Transform::Month,
+            "day" | "days" => Transform::Day,
+            "hour" | "hours" => Transform::Hour,
+            other => {
+                let (kind, argument) = other
+                    .strip_suffix(']')
+                    .and_then(|other| other.split_once('['))
+                    .ok_or_else(|| anyhow::anyhow!("Unsupported partition transform '{}'", name))?;
+
+                Transform::with_argument(kind, argument)?
+            }
+        };
+
+        Ok(transform)
+    }
+
+    fn with_argument(kind: &str, argument: &str) -> anyhow::Result<Self> {
+        let argument: u32 = argument
+            .trim()
+            .parse()
+            .ok()
+            .filter(|argument| *argument > 0)
+            .ok_or_else(|| {
+                anyhow::anyhow!("Partition transform '{}' needs a positive width", kind)
+            })?;
+
+        match kind {
+            "bucket" => Ok(Transform::Bucket(argument)),
+            "truncate" => Ok(Transform::Truncate(argument)),
+            other => Err(anyhow::anyhow!("Unsupported partition transform '{}'", other)),
+        }
+    }
+
+    /// Parses a partition term, a column name or a transform applied to one:
+    /// `region`, `day(ts)`, `bucket(16, user_id)`, `truncate(10, name)`.
+    ///
+    /// Iceberg's own `bucket[16](user_id)` spelling is accepted as well.
+    pub fn parse_term(term: &str) -> anyhow::Result<(Transform, String)> {
+        let term = term.trim();
+
+        let (function, arguments) = match
+            term.strip_suffix(')').and_then(|term| term.split_once('('))
+        {
+            Some(call) => call,
+            None => {
+                return Ok((Transform::Identity, unquote(term)));
+            }
+        };
+
+        let arguments: Vec<&str> = arguments.split(',').map(str::trim).collect();
+        let function = function.trim();
+
+        match arguments.as_slice() {
+            [column] => Ok((Transform::parse(function)?, unquote(column))),
+            [argument, column] => {
+                let transform = Transform::with_argument(&function.to_ascii_lowercase(), argument)?;
+
+                Ok((transform, unquote(column)))
+            }
+            _ => Err(anyhow::anyhow!("Malformed partition term '{}'", term)),
+        }
+    }
+
+    /// The term [`Transform::parse_term`] reads back as this transform of `column`.
+    pub fn term(&self, column: &str) -> String {
+        match self {
+            Transform::Identity => column.to_string(),
+            Transform::Bucket(count) => format!("bucket({}, {})", count, column),
+            Transform::Truncate(width) => format!("truncate({}, {})", width, column),
+            transform => format!("{}({})", transform, column),
+        }
+    }
+
+    /// Default name of the partition field, following Iceberg's `ts_day` convention.
+    fn field_name(&self, column: &str) -> String {
+        match self {
+            Transform::Identity => column.to_string(),
+            Transform::Year => format!("{}_year", column),
+            Transform::Month => format!("{}_month", column),
+            Transform::Day => format!("{}_day", column),
+            Transform::Hour => format!("{}_hour", column),
+            Transform::Bucket(_) => format!("{}_bucket", column),
+            Transform::Truncate(_) => format!("{}_trunc", column),
+        }
+    }
+
+    /// Type of the partition values produced from a `source` column, errors if the
+    /// transform can't be applied to it.
+    pub fn result_type(&self, source: &DataType) -> anyhow::Result<DataType> {
+        let kind = SourceKind::of(source)?;
+
+        let supported = match self {
+            Transform::Identity => true,
+            Transform::Bucket(_) => kind != SourceKind::Boolean,
+            Transform::Year | Transform::Month | Transform::Day => {
+                matches!(kind, SourceKind::Days | SourceKind::Micros)
+            }
+            Transform::Hour => kind == SourceKind::Micros,
+            Transform::Truncate(_) => matches!(kind, SourceKind::Int | SourceKind::String),
+        };
+
+        if !supported {
+            return Err(
+                anyhow::anyhow!("Partition transform '{}' can't be applied to {}", self, source)
+            );
+        }
+
+        let datatype = match self {
+            Transform::Identity | Transform::Truncate(_) => source.clone(),
+            Transform::Day => DataType::Date32,
+            _ => DataType::Int32,
+        };
+
+        Ok(datatype)
+    }
+
+    /// Partition values of every row of `column`, each as the transformed value's
+    /// plain text: integers, days since epoch for dates and microseconds since
+    /// epoch for timestamps.
+    pub fn apply(&self, column: &ArrayRef) -> anyhow::Result<Vec<Option<String>>> {
+        self.result_type(column.data_type())?;
+
+        let values = match Source::read(column)? {
+            Source::Int(values) => {
+                values
+                    .iter()
+                    .map(|value| value.map(|value| self.apply_int(value, SourceKind::Int)))
+                    .collect()
+            }
+            Source::Days(values) => {
+                values
+                    .iter()
+                    .map(|value| value.map(|value| self.apply_int(value, SourceKind::Days)))
+                    .collect()
+            }
+            Source::Micros(values) => {
+                values
+                    .iter()
+                    .map(|value| value.map(|value| self.apply_int(value, SourceKind::Micros)))
+                    .collect()
+            }
+            Source::String(values) => {
+                values
+                    .iter()
+                    .map(|value| value.map(|value| self.apply_str(value)))
+                    .collect()
+            }
+            Source::Boolean(values) => {
+                values
+                    .iter()
+                    .map(|value| value.map(|value| value.to_string()))
+                    .collect()
+            }
+        };
+
+        Ok(values)
+    }
+
+    fn apply_int(&self, value: i64, kind: SourceKind) -> String {
+        let days = || {
+            match kind {
+                SourceKind::Micros => value.div_euclid(MICROS_PER_DAY),
+                _ => value,
+            }
+        };
+
+        match self {
+            Transform::Identity => value.to_string(),
+            Transform::Year => (civil_from_days(days()).0 - 1970).to_string(),
+            Transform::Month => {
+                let (year, month, _) = civil_from_days(days());
+                ((year - 1970) * 12 + (month as i64) - 1).to_string()
+            }
+            Transform::Day => days().to_string(),
+            Transform::Hour => value.div_euclid(MICROS_PER_HOUR).to_string(),
+            Transform::Bucket(count) => bucket(&value.to_le_bytes(), *count).to_string(),
+            Transform::Truncate(width) => (value - value.rem_euclid(*width as i64)).to_string(),
+        }
+    }
+
+    fn apply_str(&self, value: &str) -> String {
+        match self {
+            Transform::Bucket(count) => bucket(value.as_bytes(), *count).to_string(),
+            Transform::Truncate(width) => value.chars().take(*width as usize).collect(),
+            _ => value.to_string(),
+        }
+    }
+
+    /// Bounds of the `source` values that produce a partition value, as scalars of
+    /// the source type. `None` bounds are unknown, bucketed values have neither.
+    pub(crate) fn source_bounds(
+        &self,
+        source: &DataType,
+        value: &str
+    ) -> (Option<ScalarValue>, Option<ScalarValue>) {
+        let number = value.parse::<i64>().ok();
+        let micros = |micros: i64| {
+            let timezone = match source {
+                DataType::Timestamp(_, timezone) => timezone.clone(),
+                _ => None,
+            };
+
+            ScalarValue::TimestampMicrosecond(Some(micros), timezone)
+        };
+
+        let (min, max) = match (self, SourceKind::of(source), number) {
+            (Transform::Identity, Ok(SourceKind::String), _) => {
+                (Some(ScalarValue::from(value)), Some(ScalarValue::from(value)))
+            }
+            (Transform::Identity, Ok(SourceKind::Boolean), _) => {
+                let value = value.parse::<bool>().ok();
+                (Some(ScalarValue::Boolean(value)), Some(ScalarValue::Boolean(value)))
+            }
+            (Transform::Identity, Ok(SourceKind::Int), Some(number)) => {
+                (Some(ScalarValue::from(number)), Some(ScalarValue::from(number)))
+            }
+            (Transform::Identity, Ok(SourceKind::Days), Some(days)) => {
+                let days = ScalarValue::Date32(Some(days as i32));
+                (Some(days.clone()), Some(days))
+            }
+            (Transform::Identity, Ok(SourceKind::Micros), Some(number)) => {
+                (Some(micros(number)), Some(micros(number)))
+            }
+            (Transform::Hour, Ok(SourceKind::Micros), Some(hours)) => {
+                let first = hours * MICROS_PER_HOUR;
+                (Some(micros(first)), Some(micros(first + MICROS_PER_HOUR - 1)))
+            }
+            (Transform::Year | Transform::Month | Transform::Day, Ok(kind), Some(number)) => {
+                let (first, last) = self.day_range(number);
+
+                match kind {
+                    SourceKind::Days => {
+                        (
+                            Some(ScalarValue::Date32(Some(first as i32))),
+                            Some(ScalarValue::Date32(Some(last as i32))),
+                        )
+                    }
+                    SourceKind::Micros => {
+                        (
+                            Some(micros(first * MICROS_PER_DAY)),
+                            Some(micros((last + 1) * MICROS_PER_DAY - 1)),
+                        )
+                    }
+                    _ => (None, None),
+                }
+            }
+            (Transform::Truncate(width), Ok(SourceKind::Int), Some(number)) => {
+                let last = number + (*width as i64) - 1;
+                (Some(ScalarValue::from(number)), Some(ScalarValue::from(last)))
+            }
+            // Every value starts with the truncated one, nothing bounds it from above
+            (Transform::Truncate(_), Ok(SourceKind::String), _) => {
+                (Some(ScalarValue::from(value)), None)
+            }
+            _ => (None, None),
+        };
+
+        let cast = |bound: Option<ScalarValue>| bound.and_then(|bound| bound.cast_to(source).ok());
+
+        (cast(min), cast(max))
+    }
+
+    /// First and last day since epoch of a year, month or day partition value.
+    fn day_range(&self, value: i64) -> (i64, i64) {
+        match self {
+            Transform::Year => {
+                (days_from_civil(1970 + value, 1, 1), days_from_civil(1971 + value, 1, 1) - 1)
+            }
+            Transform::Month => {
+                let (year, month) = (1970 + value.div_euclid(12), value.rem_euclid(12) as u32);
+                let next = match month {
+                    11 => days_from_civil(year + 1, 1, 1),
+                    month => days_from_civil(year, month + 2, 1),
+                };
+
+                (days_from_civil(year, month + 1, 1), next - 1)
+            }
+            _ => (value, value),
+        }
+    }
+
+    /// Human readable form of a partition value for directory names, e.g.
+    /// `2024-03-05` for a day rather than its number of days since epoch.
+    fn human(&self, source: &DataType, value: &str) -> String {
+        let number = || value.parse::<i64>().unwrap_or_default();
+
+        match self {
+            Transform::Year => format!("{}", 1970 + number()),
+            Transform::Month => {
+                let months = number();
+                format!("{:04}-{:02}", 1970 + months.div_euclid(12), months.rem_euclid(12) + 1)
+            }
+            Transform::Day => format_date(number()),
+            Transform::Hour => {
+                let hours = number();
+                format!("{}-{:02}", format_date(hours.div_euclid(24)), hours.rem_euclid(24))
+            }
+            Transform::Identity if *source == DataType::Date32 => format_date(number()),
+            _ => value.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transform::Identity => write!(f, "identity"),
+            Transform::Year => write!(f, "year"),
+            Transform::Month => write!(f, "month"),
+            Transform::Day => write!(f, "day"),
+            Transform::Hour => write!(f, "hour"),
+            Transform::Bucket(count) => write!(f, "bucket[{}]", count),
+            Transform::Truncate(width) => write!(f, "truncate[{}]", width),
+        }
+    }
+}
+
+/// An Iceberg partition spec, the catalogue keeps every spec of a table as JSON
+/// in its `partition_string`.
+///
+/// Fields reference their source column by field id, so the spec survives column
+/// renames.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PartitionSpec {
+    #[serde(default)]
+    pub spec_id: i32,
+    pub fields: Vec<PartitionField>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PartitionField {
+    pub source_id: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field_id: Option<i32>,
+    pub name: String,
+    pub transform: String,
+}
+
+impl PartitionSpec {
+    /// The unpartitioned spec.
+    pub fn unpartitioned() -> Self {
+        PartitionSpec { spec_id: 0, fields: Vec::new() }
+    }
+
+    /// Builds a spec from partition terms, see [`Transform::parse_term`].
+    pub fn from_terms(terms: &[String], schema: &SchemaVec) -> anyhow::Result<Self> {
+        let fields = terms
+            .iter()
+            .enumerate()
+            .map(|(index, term)| {
+                let (transform, name) = Transform::parse_term(term)?;
+
+                let column = schema
+                    .column_by_name(&name)
+                    .ok_or_else(|| anyhow::anyhow!("Partition column '{}' not found", name))?;
+
+                transform.result_type(&column.datatype)?;
+
+                Ok(PartitionField {
+                    source_id: column.field_id,
+                    field_id: Some(PARTITION_FIELD_ID_START + index as i32),
+                    name: transform.field_name(&column.name),
+                    transform: transform.to_string(),
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut names: Vec<&str> = fields
+            .iter()
+            .map(|field| field.name.as_str())
+            .collect();
+        names.sort_unstable();
+
+        if let Some(pair) = names.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(anyhow::anyhow!("Partition field '{}' is defined twice", pair[0]));
+        }
+
+        Ok(PartitionSpec { spec_id: 0, fields })
+    }
+
+    /// The spec partitioning by `terms` that follows `specs`, every spec a table
+    /// has had.
+    ///
+    /// Fields kept from an earlier spec keep their field id, new ones are numbered
+    /// after the highest id used so far. Going back to an earlier spec reuses its id.
+    pub fn evolve(
+        specs: &[PartitionSpec],
+        terms: &[String],
+        schema: &SchemaVec
+    ) -> anyhow::Result<Self> {
+        let mut spec = PartitionSpec::from_terms(terms, schema)?;
+        let mut last_field_id = PartitionSpec::last_field_id(specs);
+
+        for field in spec.fields.iter_mut() {
+            let known = specs
+                .iter()
+                .flat_map(|spec| &spec.fields)
+                .find(|known| {
+                    known.source_id == field.source_id && known.transform == field.transform
+                })
+                .and_then(|known| known.field_id);
+
+            field.field_id = Some(
+                known.unwrap_or_else(|| {
+                    last_field_id += 1;
+                    last_field_id
+                })
+            );
+        }
+
+        let next_spec_id = specs
+            .iter()
+            .map(|spec| spec.spec_id + 1)
+            .max()
+            .unwrap_or_default();
+
+        spec.spec_id = specs
+            .iter()
+            .find(|earlier| earlier.fields == spec.fields)
+            .map_or(next_spec_id, |earlier| earlier.spec_id);
+
+        Ok(spec)
+    }
+
+    /// The highest partition field id any of `specs` assigned.
+    pub fn last_field_id(specs: &[PartitionSpec]) -> i32 {
+        specs
+            .iter()
+            .flat_map(|spec| &spec.fields)
+            .filter_map(|field| field.field_id)
+            .max()
+            .unwrap_or(PARTITION_FIELD_ID_START - 1)
+    }
+
+    pub fn is_unpartitioned(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Types of the spec's partition values, in spec order.
+    pub fn partition_types(&self, schema: &SchemaVec) -> anyhow::Result<Vec<DataType>> {
+        self.fields
+            .iter()
+            .map(|field| {
+                let column = schema.column_by_id(field.source_id).ok_or_else(|| {
+                    anyhow::anyhow!("Partition source column {} not found", field.source_id)
+                })?;
+
+                Transform::parse(&field.transform)?.result_type(&column.datatype)
+            })
+            .collect()
+    }
+
+    /// The spec's partition terms, as accepted by [`PartitionSpec::from_terms`].
+    pub fn describe(&self, schema: &SchemaVec) -> Vec<String> {
+        self.fields
+            .iter()
+            .map(|field| {
+                let column = schema
+                    .column_by_id(field.source_id)
+                    .map(|column| column.name.clone())
+                    .unwrap_or_else(|| field.name.clone());
+
+                match Transform::parse(&field.transform) {
+                    Ok(transform) => transform.term(&column),
+                    Err(_) => format!("{}({})", field.transform, column),
+                }
+            })
+            .collect()
+    }
+}
+
+/// A partition spec resolved against the schema of the batches being written.
+pub(crate) struct BoundPartition {
+    fields: Vec<BoundField>,
+}
+
+struct BoundField {
+    name: String,
+    /// Index of the source column in the written batches.
+    column: usize,
+    source_type: DataType,
+    transform: Transform,
+}
+
+impl BoundPartition {
+    /// Resolves the spec's source columns by field id.
+    pub(crate) fn bind(spec: &PartitionSpec, schema: &Schema) -> anyhow::Result<Self> {
+        let fields = spec.fields
+            .iter()
+            .map(|field| {
+                let column = schema
+                    .fields()
+                    .iter()
+                    .position(|column| SchemaVec::field_id(column) == Some(field.source_id))
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("Partition source column {} not found", field.source_id)
+                    })?;
+
+                let source_type = schema.field(column).data_type().clone();
+                let transform = Transform::parse(&field.transform)?;
+                transform.result_type(&source_type)?;
+
+                Ok(BoundField {
+                    name: field.name.clone(),
+                    column,
+                    source_type,
+                    transform,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(BoundPartition { fields })
+    }
+
+    pub(crate) fn is_unpartitioned(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Splits a batch by partition, returning each partition's values in spec
+    /// order along with its rows.
+    pub(crate) fn split(
+        &self,
+        batch: &RecordBatch
+    ) -> anyhow::Result<Vec<(Vec<Option<String>>, RecordBatch)>> {
+        if self.is_unpartitioned() {
+            return Ok(vec![(Vec::new(), batch.clone())]);
+        }
+
+        let columns = self.fields
+            .iter()
+            .map(|field| field.transform.apply(batch.column(field.column)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut partitions: Vec<(Vec<Option<String>>, Vec<u32>)> = Vec::new();
+        let mut index: HashMap<Vec<Option<String>>, usize> = HashMap::new();
+
+        for row in 0..batch.num_rows() {
+            let values: Vec<Option<String>> = columns
+                .iter()
+                .map(|column| column[row].clone())
+                .collect();
+
+            let position = *index.entry(values.clone()).or_insert_with(|| {
+                partitions.push((values, Vec::new()));
+                partitions.len() - 1
+            });
+
+            partitions[position].1.push(row as u32);
+        }
+
+        partitions
+            .into_iter()
+            .map(|(values, rows)| {
+                let rows = take_record_batch(batch, &UInt32Array::from(rows))?;
+                Ok((values, rows))
+            })
+            .collect()
+    }
+
+    /// Nested directories of a partition's files, e.g. `["ts_day=2024-03-05", "id_bucket=3"]`.
+    pub(crate) fn directories(&self, values: &[Option<String>]) -> Vec<String> {
+        self.fields
+            .iter()
+            .zip(values)
+            .map(|(field, value)| {
+                let value = match value {
+                    Some(value) => field.transform.human(&field.source_type, value),
+                    None => "null".to_string(),
+                };
+
+                format!("{}={}", field.name, value)
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SourceKind {
+    Int,
+    Days,
+    Micros,
+    String,
+    Boolean,
+}
+
+impl SourceKind {
+    fn of(datatype: &DataType) -> anyhow::Result<Self> {
+        let kind = match datatype {
+            | DataType::Int8
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::Int64 => SourceKind::Int,
+            DataType::Date32 => SourceKind::Days,
+            DataType::Timestamp(_, _) => SourceKind::Micros,
+            DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => SourceKind::String,
+            DataType::Boolean => SourceKind::Boolean,
+            other => {
+                return Err(anyhow::anyhow!("Can't partition by a column of type {}", other));
+            }
+        };
+
+        Ok(kind)
+    }
+}
+
+/// A source column normalized to the representation transforms work on.
+enum Source {
+    Int(Int64Array),
+    Days(Int64Array),
+    Micros(Int64Array),
+    String(StringArray),
+    Boolean(BooleanArray),
+}
+
+impl Source {
+    fn read(column: &ArrayRef) -> anyhow::Result<Self> {
+        let source = match SourceKind::of(column.data_type())? {
+            SourceKind::Int => Source::Int(cast(column, &DataType::Int64)?.as_primitive().clone()),
+            SourceKind::Days => {
+                let days = cast(column, &DataType::Int32)?;
+                Source::Days(cast(&days, &DataType::Int64)?.as_primitive().clone())
+            }
+            SourceKind::Micros => {
+                let micros = cast(column, &DataType::Timestamp(TimeUnit::Microsecond, None))?;
+                let micros = cast(&micros, &DataType::Int64)?;
+                Source::Micros(micros.as_primitive::<Int64Type>().clone())
+            }
+            SourceKind::String => {
+                Source::String(cast(column, &DataType::Utf8)?.as_string().clone())
+            }
+            SourceKind::Boolean => Source::Boolean(column.as_boolean().clone()),
+        };
+
+        Ok(source)
+    }
+}
+
+/// Iceberg's bucket function, the positive part of the value's 32 bit murmur3
+/// hash modulo the bucket count.
+fn bucket(bytes: &[u8], count: u32) -> i32 {
+    (murmur3_32(bytes) as i32 & i32::MAX) % (count as i32)
+}
+
+/// MurmurHash3 x86 32 bit with seed 0, as specified for Iceberg bucketing.
+fn murmur3_32(data: &[u8]) -> u32 {
+    const C1: u32 = 0xcc9e_2d51;
+    const C2: u32 = 0x1b87_3593;
+
+    let scramble = |k: u32| k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+
+    let chunks = data.chunks_exact(4);
+    let tail = chunks.remainder();
+
+    let mut hash: u32 = 0;
+    for chunk in chunks {
+        hash ^= scramble(u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]));
+        hash = hash.rotate_left(13).wrapping_mul(5).wrapping_add(0xe654_6b64);
+    }
+
+    if !tail.is_empty() {
+        let k = tail
+            .iter()
+            .enumerate()
+            .fold(0u32, |k, (index, byte)| k ^ ((*byte as u32) << (8 * index)));
+        hash ^= scramble(k);
+    }
+
+    hash ^= data.len() as u32;
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2_ae35);
+    hash ^ (hash >> 16)
+}
+
+/// Year, month and day of a number of days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + ((month <= 2) as i64);
+
+    (year, month as u32, day as u32)
+}
+
+/// Days since 1970-01-01 of a calendar date.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let shifted_month = if month > 2 { month as i64 - 3 } else { month as i64 + 9 };
+    let day_of_year = (153 * shifted_month + 2) / 5 + (day as i64) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn format_date(days: i64) -> String {
+    let (year, month, day) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn unquote(column: &str) -> String {
+    column.trim().trim_matches('"').to_string()
+}
@@ -0,0 +1,204 @@
+use std::{ any::Any, collections::BTreeMap, fmt, sync::Arc };
+
+use async_trait::async_trait;
+
+use datafusion::catalog::{ CatalogProvider, SchemaProvider };
+use datafusion::datasource::empty::EmptyTable;
+use datafusion::datasource::TableProvider;
+use datafusion::error::DataFusionError;
+
+use crate::catalogue::{
+    catalogue_storage::Catalog,
+    lake_table::LakeTable,
+    tables::TableIdent,
+    time_travel::TableVersion,
+};
+
+pub const DEFAULT_CATALOGUE_NAME: &str = "lake";
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// Exposes a [`Catalog`] to DataFusion, its namespaces as schemas.
+///
+/// Nothing is registered up front, namespaces and tables are resolved against the
+/// catalogue whenever a query names them, so tables created after the session
+/// started are visible straight away. `CREATE SCHEMA` and `DROP SCHEMA` create
+/// and drop namespaces.
+pub struct LakeCatalogProvider {
+    catalogue: Arc<dyn Catalog + Send + Sync>,
+}
+
+impl LakeCatalogProvider {
+    pub(crate) fn new(catalogue: Arc<dyn Catalog + Send + Sync>) -> Self {
+        LakeCatalogProvider { catalogue }
+    }
+
+    fn schema_provider(&self, namespace: &str) -> Arc<dyn SchemaProvider> {
+        Arc::new(LakeSchemaProvider {
+            catalogue: self.catalogue.clone(),
+            namespace: namespace.to_string(),
+        })
+    }
+}
+
+impl fmt::Debug for LakeCatalogProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LakeCatalogProvider").finish()
+    }
+}
+
+impl CatalogProvider for LakeCatalogProvider {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema_names(&self) -> Vec<String> {
+        // Walk the namespace tree, nested namespaces are schemas of their own
+        let mut names = Vec::new();
+        let mut pending = self.catalogue.list_namespaces(None).unwrap_or_default();
+
+        while let Some(namespace) = pending.pop() {
+            pending.extend(self.catalogue.list_namespaces(Some(&namespace)).unwrap_or_default());
+            names.push(namespace);
+        }
+
+        names.sort();
+        names
+    }
+
+    fn schema(&self, name: &str) -> Option<Arc<dyn SchemaProvider>> {
+        match self.catalogue.namespace_exists(name) {
+            Ok(true) => Some(self.schema_provider(name)),
+            _ => None,
+        }
+    }
+
+    fn register_schema(
+        &self,
+        name: &str,
+        _schema: Arc<dyn SchemaProvider>
+    ) -> Result<Option<Arc<dyn SchemaProvider>>, DataFusionError> {
+        self.catalogue.create_namespace(name, &BTreeMap::new()).map_err(external)?;
+
+        Ok(None)
+    }
+
+    fn deregister_schema(
+        &self,
+        name: &str,
+        cascade: bool
+    ) -> Result<Option<Arc<dyn SchemaProvider>>, DataFusionError> {
+        if !self.catalogue.namespace_exists(name).map_err(external)? {
+            return Ok(None);
+        }
+
+        if cascade {
+            return Err(DataFusionError::NotImplemented("DROP SCHEMA .. CASCADE".to_string()));
+        }
+
+        self.catalogue.drop_namespace(name).map_err(external)?;
+
+        Ok(Some(self.schema_provider(name)))
+    }
+}
+
+/// The tables of one namespace.
+pub struct LakeSchemaProvider {
+    catalogue: Arc<dyn Catalog + Send + Sync>,
+    namespace: String,
+}
+
+impl fmt::Debug for LakeSchemaProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LakeSchemaProvider").field("namespace", &self.namespace).finish()
+    }
+}
+
+#[async_trait]
+impl SchemaProvider for LakeSchemaProvider {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn table_names(&self) -> Vec<String> {
+        self.catalogue
+            .list_tables(&self.namespace)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|ident| ident.name)
+            .collect()
+    }
+
+    async fn table(&self, name: &str) -> Result<Option<Arc<dyn TableProvider>>, DataFusionError> {
+        let (name, version) = TableVersion::split_name(name);
+
+        let ident = TableIdent::new(&self.namespace, name);
+
+        if !self.catalogue.table_exists(&ident).map_err(external)? {
+            return Ok(None);
+        }
+
+        Ok(Some(table_provider(self.catalogue.as_ref(), &ident, version).map_err(external)?))
+    }
+
+    fn table_exist(&self, name: &str) -> bool {
+        let (name, _) = TableVersion::split_name(name);
+
+        matches!(self.catalogue.get_table_id(&self.namespace, name), Ok(Some(_)))
+    }
+}
+
+/// Builds a parquet table over the data files of one of a table's snapshots, using
+/// the schema stored in the catalogue rather than inferring one from the files.
+/// File columns are matched to the schema by field id.
+///
+/// Files that were written but never committed are not visible.
+pub(crate) fn table_provider(
+    catalogue: &dyn Catalog,
+    ident: &TableIdent,
+    version: TableVersion
+) -> anyhow::Result<Arc<dyn TableProvider>> {
+    let metadata = catalogue.load_table(ident)?;
+    let table_id = &metadata.table_id;
+
+    let schema = Arc::new(metadata.schema.to_arrow_schema());
+    let schema_id = metadata.schema_id;
+
+    let snapshot = match version {
+        TableVersion::Current => catalogue.current_snapshot(table_id)?,
+        TableVersion::Version(version) => {
+            Some(
+                catalogue
+                    .get_snapshot_by_version(table_id, version)?
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("Table {} has no version {}", table_id, version)
+                    })?
+            )
+        }
+        TableVersion::Timestamp(timestamp_ms) => {
+            Some(
+                catalogue
+                    .get_snapshot_as_of(table_id, timestamp_ms)?
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("Table {} has no snapshot as of {}", table_id, timestamp_ms)
+                    })?
+            )
+        }
+    };
+
+    let snapshot = match snapshot {
+        Some(snapshot) if !snapshot.data_files.is_empty() || snapshot.manifest_list.is_some() => {
+            snapshot
+        }
+        _ => {
+            return Ok(Arc::new(EmptyTable::new(schema)));
+        }
+    };
+
+    let versions = catalogue.list_schema_versions(table_id)?;
+
+    Ok(Arc::new(LakeTable::new(schema, schema_id, versions, metadata.partition_specs, snapshot)))
+}
+
+pub(crate) fn external(error: anyhow::Error) -> DataFusionError {
+    DataFusionError::External(error.into())
+}
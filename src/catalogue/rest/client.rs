@@ -0,0 +1,668 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{ AtomicI64, Ordering };
+
+use dashmap::DashMap;
+
+use serde::{ de::DeserializeOwned, Serialize };
+
+use tokio::runtime::{ Handle, RuntimeFlavor };
+use tokio::task::block_in_place;
+
+use ureq::http::Response;
+use ureq::{ Agent, Body };
+
+use crate::catalogue::{
+    catalogue_storage::Catalog,
+    partitioning::PartitionSpec,
+    rest::model::{
+        namespace_levels,
+        CatalogConfig,
+        CommitTableRequest,
+        CommitTableResponse,
+        CreateTableRequest,
+        ErrorResponse,
+        IcebergSchema,
+        IcebergSnapshot,
+        IcebergTableMetadata,
+        ListNamespacesResponse,
+        ListTablesResponse,
+        LoadTableResult,
+        NamespaceModel,
+        RenameTableRequest,
+        TableIdentifier,
+        TableRequirement,
+        TableUpdate,
+        UpdateNamespacePropertiesRequest,
+    },
+    snapshots::{ CommitConflict, Operation, Snapshot },
+    tables::{ SchemaVec, SchemaVersion, Table, TableExists, TableIdent, TableMetadata },
+};
+
+/// A [`Catalog`] backed by an Iceberg REST catalog.
+///
+/// Tables are read through the manifests their snapshots point at. Iceberg tables
+/// have no numeric ids, the ids this catalogue hands out are only valid for as long
+/// as it is open.
+///
+/// Committing snapshots is not supported, tables of a REST catalog are read-only
+/// apart from their schema, partition spec and properties.
+///
+/// Requests block the calling thread. Used from a current-thread runtime, that
+/// runtime must not also be the one serving the catalog.
+pub struct RestCatalogue {
+    agent: Agent,
+    /// `<uri>/v1/` followed by the prefix the catalog's config asks for, if any.
+    base: String,
+    token: Option<String>,
+    tables: DashMap<i64, TableIdent>,
+    next_table_id: AtomicI64,
+}
+
+impl RestCatalogue {
+    /// Connects to the catalog served at `uri`, fetching its config.
+    pub fn connect(uri: &str, token: Option<&str>) -> anyhow::Result<Self> {
+        let agent: Agent = Agent::config_builder().http_status_as_error(false).build().into();
+
+        let mut catalogue = RestCatalogue {
+            agent,
+            base: format!("{}/v1/", uri.trim_end_matches('/')),
+            token: token.map(str::to_string),
+            tables: DashMap::new(),
+            next_table_id: AtomicI64::new(1),
+        };
+
+        let config: CatalogConfig = catalogue.get("config")?;
+        let prefix = config.overrides.get("prefix").or_else(|| config.defaults.get("prefix"));
+
+        if let Some(prefix) = prefix {
+            catalogue.base.push_str(&format!("{}/", prefix.trim_matches('/')));
+        }
+
+        Ok(catalogue)
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str) -> anyhow::Result<T> {
+        blocking(|| {
+            let response = self.authorized(self.agent.get(self.url(path))).call()?;
+
+            read(response)
+        })
+    }
+
+    fn post<B: Serialize, T: DeserializeOwned>(&self, path: &str, body: &B) -> anyhow::Result<T> {
+        blocking(|| {
+            let response = self.authorized(self.agent.post(self.url(path))).send_json(body)?;
+
+            read(response)
+        })
+    }
+
+    /// Posts a request answered with `204 No Content`.
+    fn post_empty<B: Serialize>(&self, path: &str, body: &B) -> anyhow::Result<()> {
+        blocking(|| {
+            let response = self.authorized(self.agent.post(self.url(path))).send_json(body)?;
+
+            succeeded(response).map(|_| ())
+        })
+    }
+
+    fn delete(&self, path: &str) -> anyhow::Result<()> {
+        blocking(|| {
+            let response = self.authorized(self.agent.delete(self.url(path))).call()?;
+
+            succeeded(response).map(|_| ())
+        })
+    }
+
+    /// Whether a `HEAD` request finds the resource.
+    fn exists(&self, path: &str) -> anyhow::Result<bool> {
+        blocking(|| {
+            let response = self.authorized(self.agent.head(self.url(path))).call()?;
+
+            match response.status().as_u16() {
+                404 => Ok(false),
+                _ => succeeded(response).map(|_| true),
+            }
+        })
+    }
+
+    fn authorized<B>(&self, request: ureq::RequestBuilder<B>) -> ureq::RequestBuilder<B> {
+        match &self.token {
+            Some(token) => request.header("Authorization", format!("Bearer {}", token)),
+            None => request,
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base, path)
+    }
+
+    fn namespace_path(namespace: &str) -> String {
+        format!(
+            "namespaces/{}",
+            namespace_levels(namespace)
+                .iter()
+                .map(|level| encode(level))
+                .collect::<Vec<_>>()
+                .join("%1F")
+        )
+    }
+
+    fn table_path(ident: &TableIdent) -> String {
+        let namespace = RestCatalogue::namespace_path(&ident.namespace);
+
+        format!("{}/tables/{}", namespace, encode(&ident.name))
+    }
+
+    /// The id handed out for a table, assigning one on first sight.
+    fn id_for(&self, ident: &TableIdent) -> i64 {
+        let known = self.tables
+            .iter()
+            .find(|entry| entry.value() == ident)
+            .map(|entry| *entry.key());
+
+        known.unwrap_or_else(|| {
+            let table_id = self.next_table_id.fetch_add(1, Ordering::SeqCst);
+            self.tables.insert(table_id, ident.clone());
+            table_id
+        })
+    }
+
+    fn ident(&self, table_id: &i64) -> anyhow::Result<TableIdent> {
+        self.tables
+            .get(table_id)
+            .map(|entry| entry.value().clone())
+            .ok_or_else(|| anyhow::anyhow!("Table {} not found", table_id))
+    }
+
+    fn metadata(&self, table_id: &i64) -> anyhow::Result<IcebergTableMetadata> {
+        let ident = self.ident(table_id)?;
+        let loaded: LoadTableResult = self.get(&RestCatalogue::table_path(&ident))?;
+
+        Ok(loaded.metadata)
+    }
+
+    fn snapshots(&self, table_id: &i64) -> anyhow::Result<Vec<Snapshot>> {
+        let mut snapshots = self
+            .metadata(table_id)?
+            .snapshots
+            .iter()
+            .map(|snapshot| to_snapshot(*table_id, snapshot))
+            .collect::<Vec<_>>();
+
+        snapshots.sort_by_key(|snapshot| snapshot.sequence_number);
+
+        Ok(snapshots)
+    }
+
+    fn commit(
+        &self,
+        table_id: &i64,
+        requirements: Vec<TableRequirement>,
+        updates: Vec<TableUpdate>
+    ) -> anyhow::Result<IcebergTableMetadata> {
+        let ident = self.ident(table_id)?;
+        let (pointer, expected_id) = requirements
+            .iter()
+            .find_map(|requirement| {
+                match requirement {
+                    TableRequirement::AssertCurrentSchemaId { current_schema_id } => {
+                        Some(("schema", Some(*current_schema_id)))
+                    }
+                    TableRequirement::AssertDefaultSpecId { default_spec_id } => {
+                        Some(("partition spec", Some(*default_spec_id as i64)))
+                    }
+                    _ => None,
+                }
+            })
+            .unwrap_or(("schema", None));
+
+        let request = CommitTableRequest {
+            identifier: Some(TableIdentifier::from(&ident)),
+            requirements,
+            updates,
+        };
+
+        let url = self.url(&RestCatalogue::table_path(&ident));
+        let response = self.authorized(self.agent.post(url)).send_json(&request)?;
+
+        // A failed requirement means another writer committed first
+        if response.status().as_u16() == 409 {
+            return Err(
+                (CommitConflict {
+                    table_id: *table_id,
+                    pointer,
+                    expected_id,
+                }).into()
+            );
+        }
+
+        let committed: CommitTableResponse = read(response)?;
+
+        Ok(committed.metadata)
+    }
+}
+
+impl Catalog for RestCatalogue {
+    fn create_namespace(
+        &self,
+        namespace: &str,
+        properties: &BTreeMap<String, String>
+    ) -> anyhow::Result<()> {
+        let request = NamespaceModel {
+            namespace: namespace_levels(namespace),
+            properties: properties.clone(),
+        };
+
+        self.post::<_, NamespaceModel>("namespaces", &request).map(|_| ())
+    }
+
+    fn drop_namespace(&self, namespace: &str) -> anyhow::Result<()> {
+        self.delete(&RestCatalogue::namespace_path(namespace))
+    }
+
+    fn list_namespaces(&self, parent: Option<&str>) -> anyhow::Result<Vec<String>> {
+        let path = match parent {
+            Some(parent) => {
+                let levels = namespace_levels(parent)
+                    .iter()
+                    .map(|level| encode(level))
+                    .collect::<Vec<_>>();
+
+                format!("namespaces?parent={}", levels.join("%1F"))
+            }
+            None => "namespaces".to_string(),
+        };
+
+        let listed: ListNamespacesResponse = self.get(&path)?;
+
+        Ok(
+            listed.namespaces
+                .iter()
+                .map(|levels| levels.join("."))
+                .collect()
+        )
+    }
+
+    fn namespace_exists(&self, namespace: &str) -> anyhow::Result<bool> {
+        self.exists(&RestCatalogue::namespace_path(namespace))
+    }
+
+    fn get_namespace_properties(
+        &self,
+        namespace: &str
+    ) -> anyhow::Result<BTreeMap<String, String>> {
+        let loaded: NamespaceModel = self.get(&RestCatalogue::namespace_path(namespace))?;
+
+        Ok(loaded.properties)
+    }
+
+    fn update_namespace_properties(
+        &self,
+        namespace: &str,
+        updates: &BTreeMap<String, String>,
+        removals: &[String]
+    ) -> anyhow::Result<BTreeMap<String, String>> {
+        let request = UpdateNamespacePropertiesRequest {
+            removals: removals.to_vec(),
+            updates: updates.clone(),
+        };
+
+        self.post::<_, serde_json::Value>(
+            &format!("{}/properties", RestCatalogue::namespace_path(namespace)),
+            &request
+        )?;
+
+        self.get_namespace_properties(namespace)
+    }
+
+    fn create_sys_table(&self, table: &Table) -> anyhow::Result<i64> {
+        let ident = TableIdent::new(&table.namespace, &table.table_name);
+        let schema = SchemaVec::de_serialize_schema(table.schema_bin.clone())?;
+
+        let request = CreateTableRequest {
+            name: table.table_name.clone(),
+            location: Some(table.url.clone()),
+            // The catalog assigns schema ids
+            schema: IcebergSchema { schema_id: None, ..IcebergSchema::from_schema(0, &schema)? },
+            partition_spec: Some(PartitionSpec::from_terms(&table.partition_spec, &schema)?),
+            stage_create: false,
+            properties: table.properties.clone(),
+        };
+
+        let path = format!("{}/tables", RestCatalogue::namespace_path(&table.namespace));
+
+        blocking(|| {
+            let response = self.authorized(self.agent.post(self.url(&path))).send_json(&request)?;
+
+            // The catalog refuses names that are taken
+            if response.status().as_u16() == 409 {
+                return Err(TableExists(ident.clone()).into());
+            }
+
+            read::<LoadTableResult>(response)
+        })?;
+
+        Ok(self.id_for(&ident))
+    }
+
+    fn create_table_with_snapshot(
+        &self,
+        table: &Table,
+        _schema_id: i64,
+        _snapshot: &Snapshot
+    ) -> anyhow::Result<i64> {
+        Err(
+            anyhow::anyhow!(
+                "Creating table '{}' with data in an Iceberg REST catalog is not supported",
+                table.table_name
+            )
+        )
+    }
+
+    fn next_schema_id(&self) -> anyhow::Result<i64> {
+        Err(anyhow::anyhow!("An Iceberg REST catalog assigns the ids of its tables' schemas"))
+    }
+
+    fn del_sys_table(&self, table_id: i64) -> anyhow::Result<()> {
+        let ident = self.ident(&table_id)?;
+
+        self.delete(&RestCatalogue::table_path(&ident))?;
+        self.tables.remove(&table_id);
+
+        Ok(())
+    }
+
+    fn get_table_id(&self, namespace: &str, table_name: &str) -> anyhow::Result<Option<i64>> {
+        let ident = TableIdent::new(namespace, table_name);
+
+        match self.exists(&RestCatalogue::table_path(&ident))? {
+            true => Ok(Some(self.id_for(&ident))),
+            false => Ok(None),
+        }
+    }
+
+    fn table_exists(&self, ident: &TableIdent) -> anyhow::Result<bool> {
+        self.exists(&RestCatalogue::table_path(ident))
+    }
+
+    fn load_table(&self, ident: &TableIdent) -> anyhow::Result<TableMetadata> {
+        let loaded: LoadTableResult = self.get(&RestCatalogue::table_path(ident))?;
+        let metadata = loaded.metadata;
+        let table_id = self.id_for(ident);
+
+        let schema = metadata.current_schema()?;
+        let partition_spec = metadata.partition_specs
+            .iter()
+            .find(|spec| spec.spec_id == metadata.default_spec_id)
+            .map(|spec| spec.describe(&schema))
+            .unwrap_or_default();
+
+        let current_snapshot = metadata
+            .current_snapshot()
+            .map(|snapshot| to_snapshot(table_id, snapshot));
+
+        Ok(TableMetadata {
+            table_id,
+            ident: ident.clone(),
+            table_uuid: metadata.table_uuid,
+            location: metadata.location,
+            schema_id: metadata.current_schema_id,
+            schema,
+            partition_spec,
+            partition_specs: metadata.partition_specs,
+            default_spec_id: metadata.default_spec_id,
+            properties: metadata.properties,
+            metadata_location: loaded.metadata_location,
+            current_snapshot,
+        })
+    }
+
+    fn list_tables(&self, namespace: &str) -> anyhow::Result<Vec<TableIdent>> {
+        let listed: ListTablesResponse = self.get(
+            &format!("{}/tables", RestCatalogue::namespace_path(namespace))
+        )?;
+
+        Ok(listed.identifiers.iter().map(TableIdentifier::to_ident).collect())
+    }
+
+    fn rename_table(&self, from: &TableIdent, to: &TableIdent) -> anyhow::Result<()> {
+        let request = RenameTableRequest {
+            source: TableIdentifier::from(from),
+            destination: TableIdentifier::from(to),
+        };
+
+        self.post_empty("tables/rename", &request)?;
+
+        for mut entry in self.tables.iter_mut().filter(|entry| entry.value() == from) {
+            *entry.value_mut() = to.clone();
+        }
+
+        Ok(())
+    }
+
+    fn update_table_properties(
+        &self,
+        table_id: &i64,
+        updates: &BTreeMap<String, String>,
+        removals: &[String]
+    ) -> anyhow::Result<BTreeMap<String, String>> {
+        let metadata = self.commit(
+            table_id,
+            Vec::new(),
+            vec![
+                TableUpdate::RemoveProperties { removals: removals.to_vec() },
+                TableUpdate::SetProperties { updates: updates.clone() }
+            ]
+        )?;
+
+        Ok(metadata.properties)
+    }
+
+    fn get_table_schema(&self, table_id: &i64) -> anyhow::Result<SchemaVec> {
+        self.metadata(table_id)?.current_schema()
+    }
+
+    fn get_table_schema_id(&self, table_id: &i64) -> anyhow::Result<i64> {
+        Ok(self.metadata(table_id)?.current_schema_id)
+    }
+
+    fn list_schema_versions(&self, table_id: &i64) -> anyhow::Result<Vec<SchemaVersion>> {
+        self.metadata(table_id)?.schemas
+            .iter()
+            .enumerate()
+            .map(|(index, schema)| {
+                Ok(SchemaVersion {
+                    schema_id: schema.schema_id.unwrap_or_default(),
+                    version: index as i64 + 1,
+                    schema: schema.to_schema()?,
+                })
+            })
+            .collect()
+    }
+
+    fn commit_schema(
+        &self,
+        table_id: &i64,
+        expected_schema_id: i64,
+        schema: &SchemaVec
+    ) -> anyhow::Result<SchemaVersion> {
+        let metadata = self.commit(
+            table_id,
+            vec![TableRequirement::AssertCurrentSchemaId { current_schema_id: expected_schema_id }],
+            vec![
+                TableUpdate::AddSchema {
+                    schema: IcebergSchema {
+                        schema_id: None,
+                        ..IcebergSchema::from_schema(expected_schema_id, schema)?
+                    },
+                    last_column_id: Some(schema.last_field_id()),
+                },
+                TableUpdate::SetCurrentSchema { schema_id: -1 }
+            ]
+        )?;
+
+        Ok(SchemaVersion {
+            schema_id: metadata.current_schema_id,
+            version: metadata.schemas.len() as i64,
+            schema: metadata.current_schema()?,
+        })
+    }
+
+    fn commit_partition_spec(
+        &self,
+        table_id: &i64,
+        expected_spec_id: i32,
+        spec: &PartitionSpec
+    ) -> anyhow::Result<PartitionSpec> {
+        let metadata = self.commit(
+            table_id,
+            vec![TableRequirement::AssertDefaultSpecId { default_spec_id: expected_spec_id }],
+            vec![
+                TableUpdate::AddSpec { spec: spec.clone() },
+                TableUpdate::SetDefaultSpec { spec_id: -1 }
+            ]
+        )?;
+
+        metadata.partition_specs
+            .into_iter()
+            .find(|spec| spec.spec_id == metadata.default_spec_id)
+            .ok_or_else(|| anyhow::anyhow!("Partition spec {} not found", metadata.default_spec_id))
+    }
+
+    fn get_table_url(&self, table_id: &i64) -> anyhow::Result<String> {
+        Ok(self.metadata(table_id)?.location)
+    }
+
+    fn current_snapshot(&self, table_id: &i64) -> anyhow::Result<Option<Snapshot>> {
+        let metadata = self.metadata(table_id)?;
+
+        Ok(metadata.current_snapshot().map(|snapshot| to_snapshot(*table_id, snapshot)))
+    }
+
+    fn get_snapshot_by_version(
+        &self,
+        table_id: &i64,
+        version: i64
+    ) -> anyhow::Result<Option<Snapshot>> {
+        Ok(
+            self
+                .snapshots(table_id)?
+                .into_iter()
+                .find(|snapshot| snapshot.sequence_number == version)
+        )
+    }
+
+    fn get_snapshot_as_of(
+        &self,
+        table_id: &i64,
+        timestamp_ms: i64
+    ) -> anyhow::Result<Option<Snapshot>> {
+        Ok(
+            self
+                .snapshots(table_id)?
+                .into_iter()
+                .rfind(|snapshot| snapshot.timestamp_ms <= timestamp_ms)
+        )
+    }
+
+    fn list_snapshots(&self, table_id: &i64) -> anyhow::Result<Vec<Snapshot>> {
+        self.snapshots(table_id)
+    }
+
+    /// The catalog writes the metadata of its tables itself.
+    fn set_metadata_location(
+        &self,
+        table_id: &i64,
+        _expected: Option<&str>,
+        _metadata_location: &str
+    ) -> anyhow::Result<()> {
+        Err(
+            anyhow::anyhow!(
+                "Table {} of an Iceberg REST catalog has its metadata written by the catalog",
+                table_id
+            )
+        )
+    }
+
+    fn writes_table_metadata(&self) -> bool {
+        true
+    }
+
+    fn commit_snapshot(&self, snapshot: &Snapshot) -> anyhow::Result<Snapshot> {
+        Err(
+            anyhow::anyhow!(
+                "Committing data to table {} of an Iceberg REST catalog is not supported",
+                snapshot.table_id
+            )
+        )
+    }
+}
+
+/// Runs a blocking request. On a multi-threaded runtime the worker first hands its
+/// other tasks off, so they keep running while the request waits, even a catalog
+/// served by the same runtime. A current-thread runtime is blocked for the duration.
+fn blocking<T>(request: impl FnOnce() -> T) -> T {
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            block_in_place(request)
+        }
+        _ => request(),
+    }
+}
+
+/// Deserializes a successful response.
+fn read<T: DeserializeOwned>(response: Response<Body>) -> anyhow::Result<T> {
+    let mut response = succeeded(response)?;
+
+    Ok(response.body_mut().read_json::<T>()?)
+}
+
+/// Turns an error response into an error carrying the catalog's message.
+fn succeeded(mut response: Response<Body>) -> anyhow::Result<Response<Body>> {
+    let status = response.status();
+
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    match response.body_mut().read_json::<ErrorResponse>() {
+        Ok(error) => Err(anyhow::anyhow!("{}: {}", error.error.kind, error.error.message)),
+        Err(_) => Err(anyhow::anyhow!("REST catalog request failed with {}", status)),
+    }
+}
+
+/// A snapshot whose files are listed by its manifest list.
+fn to_snapshot(table_id: i64, snapshot: &IcebergSnapshot) -> Snapshot {
+    let operation = match snapshot.summary.get("operation").map(String::as_str) {
+        Some("append") => Operation::Append,
+        _ => Operation::Overwrite,
+    };
+
+    Snapshot {
+        snapshot_id: snapshot.snapshot_id,
+        table_id,
+        parent_snapshot_id: snapshot.parent_snapshot_id,
+        sequence_number: snapshot.sequence_number,
+        operation,
+        data_files: Vec::new(),
+        manifest_list: snapshot.manifest_list.clone(),
+        summary: snapshot.summary.clone(),
+        timestamp_ms: snapshot.timestamp_ms,
+    }
+}
+
+/// Percent-encodes a path segment.
+fn encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    (byte as char).to_string()
+                }
+                _ => format!("%{:02X}", byte),
+            }
+        })
+        .collect()
+}
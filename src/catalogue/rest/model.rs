@@ -0,0 +1,506 @@
+use std::collections::BTreeMap;
+
+use arrow_schema::{ DataType, TimeUnit };
+
+use serde::{ Deserialize, Serialize };
+use serde_json::{ json, Value };
+
+use crate::catalogue::{
+    partitioning::PartitionSpec,
+    snapshots::Snapshot,
+    tables::{ Column, SchemaVec, SchemaVersion, TableIdent, TableMetadata },
+};
+
+/// The ref every table's current snapshot is published under.
+pub const MAIN_BRANCH: &str = "main";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub error: ErrorModel,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorModel {
+    pub message: String,
+    /// Exception name, e.g. `NoSuchTableException`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub code: u16,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CatalogConfig {
+    pub defaults: BTreeMap<String, String>,
+    pub overrides: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListNamespacesResponse {
+    pub namespaces: Vec<Vec<String>>,
+}
+
+/// Body of namespace creation and of namespace lookups.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamespaceModel {
+    pub namespace: Vec<String>,
+    #[serde(default)]
+    pub properties: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateNamespacePropertiesRequest {
+    #[serde(default)]
+    pub removals: Vec<String>,
+    #[serde(default)]
+    pub updates: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateNamespacePropertiesResponse {
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+    pub missing: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableIdentifier {
+    pub namespace: Vec<String>,
+    pub name: String,
+}
+
+impl TableIdentifier {
+    pub fn to_ident(&self) -> TableIdent {
+        TableIdent::new(&self.namespace.join("."), &self.name)
+    }
+}
+
+impl From<&TableIdent> for TableIdentifier {
+    fn from(ident: &TableIdent) -> Self {
+        TableIdentifier {
+            namespace: namespace_levels(&ident.namespace),
+            name: ident.name.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListTablesResponse {
+    pub identifiers: Vec<TableIdentifier>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenameTableRequest {
+    pub source: TableIdentifier,
+    pub destination: TableIdentifier,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CreateTableRequest {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    pub schema: IcebergSchema,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partition_spec: Option<PartitionSpec>,
+    #[serde(default)]
+    pub stage_create: bool,
+    #[serde(default)]
+    pub properties: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LoadTableResult {
+    /// `None` while the table has no metadata file of its own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata_location: Option<String>,
+    pub metadata: IcebergTableMetadata,
+    #[serde(default)]
+    pub config: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitTableRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identifier: Option<TableIdentifier>,
+    #[serde(default)]
+    pub requirements: Vec<TableRequirement>,
+    #[serde(default)]
+    pub updates: Vec<TableUpdate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CommitTableResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata_location: Option<String>,
+    pub metadata: IcebergTableMetadata,
+}
+
+/// A condition a commit only applies under.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum TableRequirement {
+    AssertCreate,
+    AssertTableUuid {
+        uuid: String,
+    },
+    AssertRefSnapshotId {
+        #[serde(rename = "ref")]
+        reference: String,
+        #[serde(rename = "snapshot-id")]
+        snapshot_id: Option<i64>,
+    },
+    AssertCurrentSchemaId {
+        #[serde(rename = "current-schema-id")]
+        current_schema_id: i64,
+    },
+    AssertLastAssignedFieldId {
+        #[serde(rename = "last-assigned-field-id")]
+        last_assigned_field_id: i32,
+    },
+    AssertDefaultSpecId {
+        #[serde(rename = "default-spec-id")]
+        default_spec_id: i32,
+    },
+    AssertDefaultSortOrderId {
+        #[serde(rename = "default-sort-order-id")]
+        default_sort_order_id: i32,
+    },
+    /// Any requirement this catalogue does not know about.
+    #[serde(other)]
+    Unsupported,
+}
+
+/// A single change to a table's metadata.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum TableUpdate {
+    AssignUuid {
+        uuid: String,
+    },
+    UpgradeFormatVersion {
+        #[serde(rename = "format-version")]
+        format_version: i32,
+    },
+    AddSchema {
+        schema: IcebergSchema,
+        #[serde(rename = "last-column-id", default, skip_serializing_if = "Option::is_none")]
+        last_column_id: Option<i32>,
+    },
+    /// `-1` selects the schema added by this commit.
+    SetCurrentSchema {
+        #[serde(rename = "schema-id")]
+        schema_id: i64,
+    },
+    AddSpec {
+        spec: PartitionSpec,
+    },
+    /// `-1` selects the spec added by this commit.
+    SetDefaultSpec {
+        #[serde(rename = "spec-id")]
+        spec_id: i32,
+    },
+    SetLocation {
+        location: String,
+    },
+    SetProperties {
+        updates: BTreeMap<String, String>,
+    },
+    RemoveProperties {
+        removals: Vec<String>,
+    },
+    /// Any update this catalogue does not know about, snapshot changes among them.
+    #[serde(other)]
+    Unsupported,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct IcebergSchema {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_id: Option<i64>,
+    pub fields: Vec<NestedField>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NestedField {
+    pub id: i32,
+    pub name: String,
+    pub required: bool,
+    /// A primitive type name such as `long`, or a struct, list or map object.
+    #[serde(rename = "type")]
+    pub field_type: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+}
+
+impl IcebergSchema {
+    pub fn from_schema(schema_id: i64, schema: &SchemaVec) -> anyhow::Result<Self> {
+        let fields = schema.columns
+            .iter()
+            .map(|column| {
+                Ok(NestedField {
+                    id: column.field_id,
+                    name: column.name.clone(),
+                    required: !column.nullable,
+                    field_type: Value::String(iceberg_type(&column.datatype)?),
+                    doc: None,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(IcebergSchema {
+            kind: "struct".to_string(),
+            schema_id: Some(schema_id),
+            fields,
+        })
+    }
+
+    /// Converts to a catalogue schema, keeping the field ids. Only primitive columns
+    /// are supported.
+    pub fn to_schema(&self) -> anyhow::Result<SchemaVec> {
+        let mut schema = SchemaVec::new();
+
+        for field in &self.fields {
+            let datatype = match &field.field_type {
+                Value::String(name) => arrow_type(name)?,
+                _ => {
+                    return Err(anyhow::anyhow!("Column '{}' has a nested type", field.name));
+                }
+            };
+
+            schema.add(Column {
+                field_id: field.id,
+                name: field.name.clone(),
+                datatype,
+                nullable: !field.required,
+                unique: false,
+                references: None,
+            });
+        }
+
+        Ok(schema)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct IcebergSnapshot {
+    pub snapshot_id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_snapshot_id: Option<i64>,
+    /// Absent from format v1 metadata.
+    #[serde(default)]
+    pub sequence_number: i64,
+    pub timestamp_ms: i64,
+    /// `None` while the snapshot has no manifest list written for it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest_list: Option<String>,
+    pub summary: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_id: Option<i64>,
+}
+
+impl From<&Snapshot> for IcebergSnapshot {
+    fn from(snapshot: &Snapshot) -> Self {
+        let mut summary = snapshot.summary.clone();
+        summary.insert("operation".to_string(), snapshot.operation.as_str().to_string());
+
+        IcebergSnapshot {
+            snapshot_id: snapshot.snapshot_id,
+            parent_snapshot_id: snapshot.parent_snapshot_id,
+            sequence_number: snapshot.sequence_number,
+            timestamp_ms: snapshot.timestamp_ms,
+            manifest_list: snapshot.manifest_list.clone(),
+            summary,
+            // Files of one snapshot can span schema versions, the newest is the
+            // one the snapshot was written with
+            schema_id: snapshot.data_files
+                .iter()
+                .map(|file| file.schema_id)
+                .max(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SnapshotLogEntry {
+    pub snapshot_id: i64,
+    pub timestamp_ms: i64,
+}
+
+/// Iceberg v2 table metadata, as served by the REST catalogue and written to a
+/// table's `metadata/` directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct IcebergTableMetadata {
+    pub format_version: i32,
+    pub table_uuid: String,
+    pub location: String,
+    pub last_sequence_number: i64,
+    pub last_updated_ms: i64,
+    pub last_column_id: i32,
+    pub schemas: Vec<IcebergSchema>,
+    pub current_schema_id: i64,
+    pub partition_specs: Vec<PartitionSpec>,
+    pub default_spec_id: i32,
+    pub last_partition_id: i32,
+    #[serde(default)]
+    pub properties: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_snapshot_id: Option<i64>,
+    #[serde(default)]
+    pub snapshots: Vec<IcebergSnapshot>,
+    #[serde(default)]
+    pub snapshot_log: Vec<SnapshotLogEntry>,
+    #[serde(default)]
+    pub refs: BTreeMap<String, Value>,
+    pub sort_orders: Vec<Value>,
+    pub default_sort_order_id: i32,
+}
+
+impl IcebergTableMetadata {
+    /// Builds the metadata of a table from its catalogue entry, every schema version
+    /// and every committed snapshot, oldest first.
+    pub fn new(
+        table: &TableMetadata,
+        versions: &[SchemaVersion],
+        snapshots: &[Snapshot]
+    ) -> anyhow::Result<Self> {
+        let schemas = versions
+            .iter()
+            .map(|version| IcebergSchema::from_schema(version.schema_id, &version.schema))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let current_snapshot_id = table.current_snapshot
+            .as_ref()
+            .map(|snapshot| snapshot.snapshot_id);
+
+        let mut refs = BTreeMap::new();
+        if let Some(snapshot_id) = current_snapshot_id {
+            refs.insert(
+                MAIN_BRANCH.to_string(),
+                json!({ "snapshot-id": snapshot_id, "type": "branch" })
+            );
+        }
+
+        Ok(IcebergTableMetadata {
+            format_version: 2,
+            table_uuid: table.table_uuid.clone(),
+            location: table.location.clone(),
+            last_sequence_number: snapshots
+                .iter()
+                .map(|snapshot| snapshot.sequence_number)
+                .max()
+                .unwrap_or_default(),
+            last_updated_ms: snapshots
+                .iter()
+                .map(|snapshot| snapshot.timestamp_ms)
+                .max()
+                .unwrap_or_default(),
+            last_column_id: versions
+                .iter()
+                .map(|version| version.schema.last_field_id())
+                .max()
+                .unwrap_or_default(),
+            schemas,
+            current_schema_id: table.schema_id,
+            last_partition_id: PartitionSpec::last_field_id(&table.partition_specs),
+            partition_specs: table.partition_specs.clone(),
+            default_spec_id: table.default_spec_id,
+            properties: table.properties.clone(),
+            current_snapshot_id,
+            snapshots: snapshots.iter().map(IcebergSnapshot::from).collect(),
+            snapshot_log: snapshots
+                .iter()
+                .map(|snapshot| SnapshotLogEntry {
+                    snapshot_id: snapshot.snapshot_id,
+                    timestamp_ms: snapshot.timestamp_ms,
+                })
+                .collect(),
+            refs,
+            sort_orders: vec![json!({ "order-id": 0, "fields": [] })],
+            default_sort_order_id: 0,
+        })
+    }
+
+    /// The schema the table currently reads with.
+    pub fn current_schema(&self) -> anyhow::Result<SchemaVec> {
+        self.schemas
+            .iter()
+            .find(|schema| schema.schema_id == Some(self.current_schema_id))
+            .ok_or_else(|| anyhow::anyhow!("Schema {} not found", self.current_schema_id))?
+            .to_schema()
+    }
+
+    /// The snapshot the table currently points at.
+    pub fn current_snapshot(&self) -> Option<&IcebergSnapshot> {
+        self.snapshots
+            .iter()
+            .find(|snapshot| Some(snapshot.snapshot_id) == self.current_snapshot_id)
+    }
+}
+
+/// Splits a dot separated namespace into its levels.
+pub fn namespace_levels(namespace: &str) -> Vec<String> {
+    namespace.split('.').map(str::to_string).collect()
+}
+
+/// The Iceberg name of a primitive arrow type.
+pub fn iceberg_type(datatype: &DataType) -> anyhow::Result<String> {
+    let name = match datatype {
+        DataType::Boolean => "boolean".to_string(),
+        DataType::Int8 | DataType::Int16 | DataType::Int32 => "int".to_string(),
+        DataType::Int64 => "long".to_string(),
+        DataType::Float32 => "float".to_string(),
+        DataType::Float64 => "double".to_string(),
+        DataType::Decimal128(precision, scale) => format!("decimal({}, {})", precision, scale),
+        DataType::Date32 => "date".to_string(),
+        DataType::Timestamp(TimeUnit::Nanosecond, None) => "timestamp_ns".to_string(),
+        DataType::Timestamp(TimeUnit::Nanosecond, Some(_)) => "timestamptz_ns".to_string(),
+        DataType::Timestamp(_, None) => "timestamp".to_string(),
+        DataType::Timestamp(_, Some(_)) => "timestamptz".to_string(),
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => "string".to_string(),
+        DataType::Binary | DataType::LargeBinary | DataType::BinaryView => "binary".to_string(),
+        other => {
+            return Err(anyhow::anyhow!("Type {} has no Iceberg equivalent", other));
+        }
+    };
+
+    Ok(name)
+}
+
+/// The arrow type of an Iceberg primitive type name.
+pub fn arrow_type(name: &str) -> anyhow::Result<DataType> {
+    let datatype = match name {
+        "boolean" => DataType::Boolean,
+        "int" => DataType::Int32,
+        "long" => DataType::Int64,
+        "float" => DataType::Float32,
+        "double" => DataType::Float64,
+        "date" => DataType::Date32,
+        "timestamp" => DataType::Timestamp(TimeUnit::Microsecond, None),
+        "timestamptz" => DataType::Timestamp(TimeUnit::Microsecond, Some("+00:00".into())),
+        "timestamp_ns" => DataType::Timestamp(TimeUnit::Nanosecond, None),
+        "timestamptz_ns" => DataType::Timestamp(TimeUnit::Nanosecond, Some("+00:00".into())),
+        "string" => DataType::Utf8,
+        "binary" => DataType::Binary,
+        decimal if decimal.starts_with("decimal(") && decimal.ends_with(')') => {
+            let (precision, scale) = decimal["decimal(".len()..decimal.len() - 1]
+                .split_once(',')
+                .ok_or_else(|| anyhow::anyhow!("Malformed type '{}'", decimal))?;
+
+            DataType::Decimal128(precision.trim().parse()?, scale.trim().parse()?)
+        }
+        other => {
+            return Err(anyhow::anyhow!("Unsupported Iceberg type '{}'", other));
+        }
+    };
+
+    Ok(datatype)
+}
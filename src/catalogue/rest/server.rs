@@ -0,0 +1,659 @@
+use std::{ collections::{ BTreeMap, HashMap }, path::PathBuf, sync::Arc };
+
+use axum::extract::{ rejection::JsonRejection, Path, Query, State };
+use axum::http::StatusCode;
+use axum::response::{ IntoResponse, Response };
+use axum::routing::{ get, post };
+use axum::{ Json, Router };
+
+use datafusion::prelude::SessionContext;
+
+use tokio::net::TcpListener;
+
+use uuid::Uuid;
+
+use crate::catalogue::{
+    catalogue_storage::Catalog,
+    iceberg::write_table_metadata,
+    partitioning::PartitionSpec,
+    rest::model::{
+        namespace_levels,
+        CatalogConfig,
+        CommitTableRequest,
+        CommitTableResponse,
+        CreateTableRequest,
+        ErrorModel,
+        ErrorResponse,
+        IcebergTableMetadata,
+        ListNamespacesResponse,
+        ListTablesResponse,
+        LoadTableResult,
+        NamespaceModel,
+        RenameTableRequest,
+        TableIdentifier,
+        TableRequirement,
+        TableUpdate,
+        UpdateNamespacePropertiesRequest,
+        UpdateNamespacePropertiesResponse,
+        MAIN_BRANCH,
+    },
+    snapshots::CommitConflict,
+    tables::{ SchemaVec, Table, TableExists, TableFormat, TableIdent, TableMetadata },
+    RootCatalogue,
+};
+use crate::utils::storage::storage::{ BackEnd, Storage };
+
+/// Separates the levels of a multi-level namespace in request paths.
+const NAMESPACE_SEPARATOR: char = '\u{1f}';
+
+/// Serves the Iceberg REST catalog API over a [`RootCatalogue`].
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// use std::path::PathBuf;
+/// use unakite::catalogue::rest::server::RestCatalogServer;
+/// use unakite::utils::storage::storage::Storage;
+///
+/// let storage = Storage::LocalFileSystem { base_path: PathBuf::from("lake") };
+/// let server = RestCatalogServer::new(PathBuf::from("lake.db"), storage).await?;
+/// let listener = tokio::net::TcpListener::bind("127.0.0.1:8181").await?;
+///
+/// server.serve(listener).await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Namespaces, tables, renames, properties and schema changes are supported.
+/// Commits that add snapshots are refused, data is written through Unakite.
+/// Every change publishes the table's next `metadata.json` to `storage`.
+#[derive(Clone)]
+pub struct RestCatalogServer {
+    catalogue: Arc<RootCatalogue>,
+    /// Url new tables are placed under unless the request names a location.
+    warehouse: String,
+    /// Resolves table urls, `metadata.json` files are written through it.
+    ctx: SessionContext,
+}
+
+impl RestCatalogServer {
+    /// Serves the catalogue stored at `catalogue`, a path or SQLite `file:` URI like
+    /// the engine's own catalogue location, over the tables kept in `storage`.
+    pub async fn new(catalogue: PathBuf, storage: Storage) -> anyhow::Result<Self> {
+        let catalogue = RootCatalogue::start(catalogue)?;
+        let back_end = storage.get_store().await?;
+
+        Ok(RestCatalogServer::with_catalogue(Arc::new(catalogue), &back_end))
+    }
+
+    pub(crate) fn with_catalogue(catalogue: Arc<RootCatalogue>, back_end: &BackEnd) -> Self {
+        let ctx = SessionContext::new();
+        back_end.register(&ctx);
+
+        RestCatalogServer {
+            catalogue,
+            warehouse: back_end.url_for(""),
+            ctx,
+        }
+    }
+
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/v1/config", get(config))
+            .route("/v1/namespaces", get(list_namespaces).post(create_namespace))
+            .route(
+                "/v1/namespaces/{namespace}",
+                get(load_namespace).head(namespace_exists).delete(drop_namespace)
+            )
+            .route("/v1/namespaces/{namespace}/properties", post(update_namespace_properties))
+            .route("/v1/namespaces/{namespace}/tables", get(list_tables).post(create_table))
+            .route(
+                "/v1/namespaces/{namespace}/tables/{table}",
+                get(load_table).head(table_exists).delete(drop_table).post(commit_table)
+            )
+            .route("/v1/tables/rename", post(rename_table))
+            .with_state(self.clone())
+    }
+
+    /// Serves requests on `listener` until the task is dropped.
+    pub async fn serve(self, listener: TcpListener) -> anyhow::Result<()> {
+        axum::serve(listener, self.router()).await?;
+
+        Ok(())
+    }
+
+    /// A fresh directory for a new table. Like the engine's, it is suffixed with a
+    /// uuid so a table created under a renamed table's old name gets its own files.
+    fn location_for(&self, ident: &TableIdent) -> String {
+        let namespace = ident.namespace.replace('.', "/");
+
+        format!("{}{}/{}-{}/", self.warehouse, namespace, ident.name, Uuid::new_v4().simple())
+    }
+
+    /// Writes an Iceberg table's current state as its next `metadata.json`.
+    ///
+    /// Called once a change is committed, which stands when publishing fails. The
+    /// failure is logged and the table's previous file stays current until its next
+    /// change publishes again.
+    async fn publish(&self, ident: &TableIdent) {
+        let published = async {
+            if self.catalogue.load_table(ident)?.format()? == TableFormat::Iceberg {
+                write_table_metadata(&self.ctx.state(), self.catalogue.as_ref(), ident).await?;
+            }
+
+            anyhow::Ok(())
+        };
+
+        if let Err(e) = published.await {
+            log::warn!("Failed to publish metadata.json of table {}: {:#}", ident, e);
+        }
+    }
+
+    fn table_id(&self, ident: &TableIdent) -> Result<i64, RestError> {
+        self.catalogue
+            .get_table_id(&ident.namespace, &ident.name)?
+            .ok_or_else(|| RestError::no_such_table(ident))
+    }
+
+    fn existing_namespace(&self, namespace: &str) -> Result<(), RestError> {
+        match self.catalogue.namespace_exists(namespace)? {
+            true => Ok(()),
+            false => Err(RestError::no_such_namespace(namespace)),
+        }
+    }
+
+    fn load(&self, ident: &TableIdent) -> Result<LoadTableResult, RestError> {
+        self.table_id(ident)?;
+
+        let table = self.catalogue.load_table(ident)?;
+
+        Ok(LoadTableResult {
+            metadata_location: table.metadata_location.clone(),
+            metadata: self.iceberg_metadata(&table)?,
+            config: BTreeMap::new(),
+        })
+    }
+
+    fn iceberg_metadata(&self, table: &TableMetadata) -> Result<IcebergTableMetadata, RestError> {
+        let versions = self.catalogue.list_schema_versions(&table.table_id)?;
+        let snapshots = self.catalogue.list_snapshots(&table.table_id)?;
+
+        Ok(IcebergTableMetadata::new(table, &versions, &snapshots)?)
+    }
+}
+
+type Catalogue = State<RestCatalogServer>;
+
+async fn config() -> Json<CatalogConfig> {
+    Json(CatalogConfig::default())
+}
+
+async fn list_namespaces(
+    State(server): Catalogue,
+    Query(params): Query<HashMap<String, String>>
+) -> Result<Json<ListNamespacesResponse>, RestError> {
+    let parent = params.get("parent").map(|parent| namespace_name(parent));
+
+    if let Some(parent) = &parent {
+        server.existing_namespace(parent)?;
+    }
+
+    let namespaces = server.catalogue
+        .list_namespaces(parent.as_deref())?
+        .iter()
+        .map(|namespace| namespace_levels(namespace))
+        .collect();
+
+    Ok(Json(ListNamespacesResponse { namespaces }))
+}
+
+async fn create_namespace(
+    State(server): Catalogue,
+    payload: Result<Json<NamespaceModel>, JsonRejection>
+) -> Result<Json<NamespaceModel>, RestError> {
+    let request = body(payload)?;
+    let namespace = request.namespace.join(".");
+
+    if server.catalogue.namespace_exists(&namespace)? {
+        return Err(RestError::already_exists(format!("Namespace '{}' already exists", namespace)));
+    }
+
+    if let Some((parent, _)) = namespace.rsplit_once('.') {
+        server.existing_namespace(parent)?;
+    }
+
+    server.catalogue.create_namespace(&namespace, &request.properties)?;
+
+    Ok(Json(request))
+}
+
+async fn load_namespace(
+    State(server): Catalogue,
+    Path(namespace): Path<String>
+) -> Result<Json<NamespaceModel>, RestError> {
+    let namespace = namespace_name(&namespace);
+    server.existing_namespace(&namespace)?;
+
+    Ok(
+        Json(NamespaceModel {
+            namespace: namespace_levels(&namespace),
+            properties: server.catalogue.get_namespace_properties(&namespace)?,
+        })
+    )
+}
+
+async fn namespace_exists(
+    State(server): Catalogue,
+    Path(namespace): Path<String>
+) -> Result<StatusCode, RestError> {
+    server.existing_namespace(&namespace_name(&namespace))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn drop_namespace(
+    State(server): Catalogue,
+    Path(namespace): Path<String>
+) -> Result<StatusCode, RestError> {
+    let namespace = namespace_name(&namespace);
+    server.existing_namespace(&namespace)?;
+
+    // The namespace exists, so it is refused for being non-empty or the default one
+    server.catalogue.drop_namespace(&namespace).map_err(|error| {
+        RestError::new(StatusCode::CONFLICT, "NamespaceNotEmptyException", error)
+    })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn update_namespace_properties(
+    State(server): Catalogue,
+    Path(namespace): Path<String>,
+    payload: Result<Json<UpdateNamespacePropertiesRequest>, JsonRejection>
+) -> Result<Json<UpdateNamespacePropertiesResponse>, RestError> {
+    let request = body(payload)?;
+    let namespace = namespace_name(&namespace);
+    server.existing_namespace(&namespace)?;
+
+    if let Some(key) = request.removals.iter().find(|key| request.updates.contains_key(*key)) {
+        return Err(RestError::bad_request(format!("Property '{}' is updated and removed", key)));
+    }
+
+    let before = server.catalogue.get_namespace_properties(&namespace)?;
+
+    server.catalogue.update_namespace_properties(&namespace, &request.updates, &request.removals)?;
+
+    let (removed, missing) = request.removals
+        .into_iter()
+        .partition(|key| before.contains_key(key));
+
+    Ok(
+        Json(UpdateNamespacePropertiesResponse {
+            updated: request.updates.into_keys().collect(),
+            removed,
+            missing,
+        })
+    )
+}
+
+async fn list_tables(
+    State(server): Catalogue,
+    Path(namespace): Path<String>
+) -> Result<Json<ListTablesResponse>, RestError> {
+    let namespace = namespace_name(&namespace);
+    server.existing_namespace(&namespace)?;
+
+    let identifiers = server.catalogue
+        .list_tables(&namespace)?
+        .iter()
+        .map(TableIdentifier::from)
+        .collect();
+
+    Ok(Json(ListTablesResponse { identifiers }))
+}
+
+async fn create_table(
+    State(server): Catalogue,
+    Path(namespace): Path<String>,
+    payload: Result<Json<CreateTableRequest>, JsonRejection>
+) -> Result<Json<LoadTableResult>, RestError> {
+    let request = body(payload)?;
+    let ident = TableIdent::new(&namespace_name(&namespace), &request.name);
+    server.existing_namespace(&ident.namespace)?;
+
+    if request.stage_create {
+        return Err(RestError::unsupported("Staged table creation is not supported".to_string()));
+    }
+
+    let schema = request.schema.to_schema().map_err(RestError::from_bad_request)?;
+    let partition_spec = match &request.partition_spec {
+        Some(spec) => spec.describe(&schema),
+        None => Vec::new(),
+    };
+
+    // Refuse transforms the writers can't apply before the table is created
+    PartitionSpec::from_terms(&partition_spec, &schema).map_err(RestError::from_bad_request)?;
+
+    // Refused with `AlreadyExistsException` if the name is taken
+    server.catalogue.create_sys_table(
+        &(Table {
+            namespace: ident.namespace.clone(),
+            table_name: ident.name.clone(),
+            schema_bin: SchemaVec::serialize_schema(&schema),
+            url: request.location.unwrap_or_else(|| server.location_for(&ident)),
+            partition_spec,
+            properties: request.properties,
+        })
+    )?;
+    server.publish(&ident).await;
+
+    Ok(Json(server.load(&ident)?))
+}
+
+async fn load_table(
+    State(server): Catalogue,
+    Path((namespace, table)): Path<(String, String)>
+) -> Result<Json<LoadTableResult>, RestError> {
+    Ok(Json(server.load(&TableIdent::new(&namespace_name(&namespace), &table))?))
+}
+
+async fn table_exists(
+    State(server): Catalogue,
+    Path((namespace, table)): Path<(String, String)>
+) -> Result<StatusCode, RestError> {
+    server.table_id(&TableIdent::new(&namespace_name(&namespace), &table))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Drops the catalogue entry, the table's data files are left where they are.
+async fn drop_table(
+    State(server): Catalogue,
+    Path((namespace, table)): Path<(String, String)>
+) -> Result<StatusCode, RestError> {
+    let table_id = server.table_id(&TableIdent::new(&namespace_name(&namespace), &table))?;
+
+    server.catalogue.del_sys_table(table_id)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn rename_table(
+    State(server): Catalogue,
+    payload: Result<Json<RenameTableRequest>, JsonRejection>
+) -> Result<StatusCode, RestError> {
+    let request = body(payload)?;
+    let (source, destination) = (request.source.to_ident(), request.destination.to_ident());
+
+    server.table_id(&source)?;
+    server.existing_namespace(&destination.namespace)?;
+
+    if server.catalogue.table_exists(&destination)? {
+        return Err(RestError::already_exists(format!("Table '{}' already exists", destination)));
+    }
+
+    server.catalogue.rename_table(&source, &destination)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Checks every requirement against the table, then applies the schema change, the
+/// partition spec change and the property changes. The schema and spec are swapped
+/// in only if they are still the ones the requirements were checked against.
+async fn commit_table(
+    State(server): Catalogue,
+    Path((namespace, table)): Path<(String, String)>,
+    payload: Result<Json<CommitTableRequest>, JsonRejection>
+) -> Result<Json<CommitTableResponse>, RestError> {
+    let request = body(payload)?;
+    let ident = TableIdent::new(&namespace_name(&namespace), &table);
+    server.table_id(&ident)?;
+
+    let table = server.catalogue.load_table(&ident)?;
+    let current = server.iceberg_metadata(&table)?;
+
+    for requirement in &request.requirements {
+        check_requirement(requirement, &current)?;
+    }
+
+    let mut added_schema = None;
+    let mut current_schema = None;
+    let mut added_spec = None;
+    let mut default_spec = None;
+    let mut updates = BTreeMap::new();
+    let mut removals = Vec::new();
+
+    for update in request.updates {
+        match update {
+            TableUpdate::AssignUuid { uuid } if uuid == current.table_uuid => {}
+            TableUpdate::UpgradeFormatVersion { format_version: 2 } => {}
+            TableUpdate::SetLocation { location } if location == current.location => {}
+            TableUpdate::AddSchema { schema, .. } => {
+                added_schema = Some(schema.to_schema().map_err(RestError::from_bad_request)?);
+            }
+            TableUpdate::SetCurrentSchema { schema_id } => {
+                current_schema = Some(schema_id);
+            }
+            TableUpdate::AddSpec { spec } => {
+                added_spec = Some(spec);
+            }
+            TableUpdate::SetDefaultSpec { spec_id } => {
+                default_spec = Some(spec_id);
+            }
+            TableUpdate::SetProperties { updates: set } => {
+                updates.extend(set);
+            }
+            TableUpdate::RemoveProperties { removals: removed } => {
+                removals.extend(removed);
+            }
+            other => {
+                return Err(RestError::unsupported(format!("Unsupported table update {:?}", other)));
+            }
+        }
+    }
+
+    let schema = match (current_schema, added_schema) {
+        (None, None) => None,
+        (Some(schema_id), _) if schema_id == table.schema_id => None,
+        (Some(-1), Some(schema)) => Some(schema),
+        (Some(schema_id), _) => {
+            let version = server.catalogue
+                .list_schema_versions(&table.table_id)?
+                .into_iter()
+                .find(|version| version.schema_id == schema_id)
+                .ok_or_else(|| RestError::bad_request(format!("Unknown schema {}", schema_id)))?;
+
+            Some(version.schema)
+        }
+        (None, Some(_)) => {
+            return Err(
+                RestError::unsupported("Added schemas must become the current schema".to_string())
+            );
+        }
+    };
+
+    if let Some(schema) = &schema {
+        server.catalogue.commit_schema(&table.table_id, table.schema_id, schema)?;
+    }
+
+    // Added specs are renumbered, spec and field ids are assigned by the catalogue
+    let schema = schema.as_ref().unwrap_or(&table.schema);
+    let spec = match (default_spec, added_spec) {
+        (None, None) => None,
+        (Some(spec_id), _) if spec_id == table.default_spec_id => None,
+        (Some(-1), Some(spec)) => {
+            let spec = PartitionSpec::evolve(
+                &table.partition_specs,
+                &spec.describe(schema),
+                schema
+            ).map_err(RestError::from_bad_request)?;
+
+            Some(spec)
+        }
+        (Some(spec_id), _) => {
+            let spec = table.partition_specs
+                .iter()
+                .find(|spec| spec.spec_id == spec_id)
+                .cloned()
+                .ok_or_else(|| {
+                    RestError::bad_request(format!("Unknown partition spec {}", spec_id))
+                })?;
+
+            Some(spec)
+        }
+        (None, Some(_)) => {
+            return Err(
+                RestError::unsupported(
+                    "Added partition specs must become the default spec".to_string()
+                )
+            );
+        }
+    };
+
+    if let Some(spec) = spec {
+        server.catalogue.commit_partition_spec(&table.table_id, table.default_spec_id, &spec)?;
+    }
+
+    if !updates.is_empty() || !removals.is_empty() {
+        server.catalogue.update_table_properties(&table.table_id, &updates, &removals)?;
+    }
+
+    server.publish(&ident).await;
+    let table = server.catalogue.load_table(&ident)?;
+
+    Ok(
+        Json(CommitTableResponse {
+            metadata_location: table.metadata_location.clone(),
+            metadata: server.iceberg_metadata(&table)?,
+        })
+    )
+}
+
+fn check_requirement(
+    requirement: &TableRequirement,
+    metadata: &IcebergTableMetadata
+) -> Result<(), RestError> {
+    let met = match requirement {
+        TableRequirement::AssertCreate => false,
+        TableRequirement::AssertTableUuid { uuid } => uuid == &metadata.table_uuid,
+        TableRequirement::AssertRefSnapshotId { reference, snapshot_id } => {
+            match reference.as_str() {
+                MAIN_BRANCH => snapshot_id == &metadata.current_snapshot_id,
+                _ => snapshot_id.is_none(),
+            }
+        }
+        TableRequirement::AssertCurrentSchemaId { current_schema_id } => {
+            current_schema_id == &metadata.current_schema_id
+        }
+        TableRequirement::AssertLastAssignedFieldId { last_assigned_field_id } => {
+            last_assigned_field_id == &metadata.last_column_id
+        }
+        TableRequirement::AssertDefaultSpecId { default_spec_id } => {
+            default_spec_id == &metadata.default_spec_id
+        }
+        TableRequirement::AssertDefaultSortOrderId { default_sort_order_id } => {
+            default_sort_order_id == &metadata.default_sort_order_id
+        }
+        TableRequirement::Unsupported => {
+            return Err(RestError::unsupported("Unsupported table requirement".to_string()));
+        }
+    };
+
+    match met {
+        true => Ok(()),
+        false => Err(RestError::commit_failed(format!("Requirement failed: {:?}", requirement))),
+    }
+}
+
+/// Namespace levels in paths are separated by `0x1F`, the catalogue joins them by dots.
+fn namespace_name(encoded: &str) -> String {
+    encoded.split(NAMESPACE_SEPARATOR).collect::<Vec<_>>().join(".")
+}
+
+fn body<T>(payload: Result<Json<T>, JsonRejection>) -> Result<T, RestError> {
+    payload
+        .map(|Json(request)| request)
+        .map_err(|rejection| RestError::bad_request(rejection.body_text()))
+}
+
+/// An error response in the shape the Iceberg REST spec prescribes.
+#[derive(Debug)]
+pub struct RestError {
+    status: StatusCode,
+    kind: &'static str,
+    message: String,
+}
+
+impl RestError {
+    fn new(status: StatusCode, kind: &'static str, error: impl ToString) -> Self {
+        RestError { status, kind, message: error.to_string() }
+    }
+
+    fn bad_request(message: String) -> Self {
+        RestError::new(StatusCode::BAD_REQUEST, "BadRequestException", message)
+    }
+
+    fn from_bad_request(error: anyhow::Error) -> Self {
+        RestError::bad_request(error.to_string())
+    }
+
+    fn no_such_namespace(namespace: &str) -> Self {
+        RestError::new(
+            StatusCode::NOT_FOUND,
+            "NoSuchNamespaceException",
+            format!("Namespace '{}' not found", namespace)
+        )
+    }
+
+    fn no_such_table(ident: &TableIdent) -> Self {
+        RestError::new(
+            StatusCode::NOT_FOUND,
+            "NoSuchTableException",
+            format!("Table '{}' not found", ident)
+        )
+    }
+
+    fn already_exists(message: String) -> Self {
+        RestError::new(StatusCode::CONFLICT, "AlreadyExistsException", message)
+    }
+
+    fn commit_failed(message: String) -> Self {
+        RestError::new(StatusCode::CONFLICT, "CommitFailedException", message)
+    }
+
+    fn unsupported(message: String) -> Self {
+        RestError::new(StatusCode::NOT_ACCEPTABLE, "UnsupportedOperationException", message)
+    }
+}
+
+impl From<anyhow::Error> for RestError {
+    fn from(error: anyhow::Error) -> Self {
+        if let Some(exists) = error.downcast_ref::<TableExists>() {
+            return RestError::already_exists(exists.to_string());
+        }
+
+        match error.downcast_ref::<CommitConflict>() {
+            Some(conflict) => RestError::commit_failed(conflict.to_string()),
+            None => {
+                RestError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "InternalServerError",
+                    format!("{:#}", error)
+                )
+            }
+        }
+    }
+}
+
+impl IntoResponse for RestError {
+    fn into_response(self) -> Response {
+        let body = ErrorResponse {
+            error: ErrorModel {
+                message: self.message,
+                kind: self.kind.to_string(),
+                code: self.status.as_u16(),
+            },
+        };
+
+        (self.status, Json(body)).into_response()
+    }
+}
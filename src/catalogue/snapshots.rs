@@ -0,0 +1,182 @@
+use std::{ collections::BTreeMap, fmt, time::{ SystemTime, UNIX_EPOCH } };
+
+use bincode::config::standard;
+
+use serde::{ Serialize, Deserialize };
+
+/// A parquet data file tracked by a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataFile {
+    /// Full url of the file, e.g. `file:///data/lake/orders/part-<uuid>.parquet`.
+    pub file_path: String,
+    pub row_count: u64,
+    pub file_size: u64,
+    /// The `sys_schemas` entry the file was written with.
+    pub schema_id: i64,
+    /// Id of the partition spec the file was written with.
+    pub spec_id: i32,
+    /// The file's partition values in partition spec order, see
+    /// [`Transform::apply`]. Empty for files of unpartitioned tables.
+    ///
+    /// [`Transform::apply`]: crate::catalogue::partitioning::Transform::apply
+    pub partition_values: Vec<Option<String>>,
+    /// Statistics of the file's columns by field id, recorded when the file is
+    /// written. Columns without an entry have unknown statistics.
+    pub column_stats: BTreeMap<i32, ColumnStats>,
+}
+
+/// Statistics of one column of a data file.
+///
+/// Bounds are kept as text, the column's values cast to a string, and are read
+/// back by casting to the column's current type.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnStats {
+    /// `None` if the column holds only nulls or its type is not ordered.
+    pub min: Option<String>,
+    pub max: Option<String>,
+    pub null_count: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// New files were added, existing files are kept.
+    Append,
+    /// The table's files were replaced wholesale.
+    Overwrite,
+}
+
+impl Operation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operation::Append => "append",
+            Operation::Overwrite => "overwrite",
+        }
+    }
+
+    pub fn parse(operation: &str) -> anyhow::Result<Self> {
+        match operation {
+            "append" => Ok(Operation::Append),
+            "overwrite" => Ok(Operation::Overwrite),
+            other => Err(anyhow::anyhow!("Unknown snapshot operation '{}'", other)),
+        }
+    }
+}
+
+/// An immutable version of a table: every data file that is live at that point.
+///
+/// Snapshots are never updated once committed. A write produces a new snapshot
+/// whose parent is the table's current one, and the table's pointer is only
+/// advanced if that parent is still current when the commit lands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Assigned by the catalogue on commit unless the writer picked one, `0` for a
+    /// pending snapshot without one.
+    pub snapshot_id: i64,
+    pub table_id: i64,
+    pub parent_snapshot_id: Option<i64>,
+    /// Per-table version, starting at 1 for the first commit.
+    pub sequence_number: i64,
+    pub operation: Operation,
+    pub data_files: Vec<DataFile>,
+    /// Url of the Iceberg manifest list naming the snapshot's files. When set, the
+    /// files are read from it when the table is scanned. Snapshots of tables that
+    /// were not written by this engine leave `data_files` empty.
+    pub manifest_list: Option<String>,
+    pub summary: BTreeMap<String, String>,
+    pub timestamp_ms: i64,
+}
+
+impl Snapshot {
+    /// Builds the pending snapshot that follows `parent` after a write of `added` files.
+    pub fn next(
+        table_id: i64,
+        parent: Option<&Snapshot>,
+        operation: Operation,
+        added: Vec<DataFile>
+    ) -> Self {
+        let mut data_files = match (operation, parent) {
+            (Operation::Append, Some(parent)) => parent.data_files.clone(),
+            _ => Vec::new(),
+        };
+
+        let mut summary = BTreeMap::new();
+        summary.insert("added-data-files".to_string(), added.len().to_string());
+        summary.insert(
+            "added-records".to_string(),
+            added
+                .iter()
+                .map(|file| file.row_count)
+                .sum::<u64>()
+                .to_string()
+        );
+
+        data_files.extend(added);
+
+        summary.insert("total-data-files".to_string(), data_files.len().to_string());
+        summary.insert(
+            "total-records".to_string(),
+            data_files
+                .iter()
+                .map(|file| file.row_count)
+                .sum::<u64>()
+                .to_string()
+        );
+
+        Snapshot {
+            snapshot_id: 0,
+            table_id,
+            parent_snapshot_id: parent.map(|parent| parent.snapshot_id),
+            sequence_number: parent.map_or(1, |parent| parent.sequence_number + 1),
+            operation,
+            data_files,
+            manifest_list: None,
+            summary,
+            timestamp_ms: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as i64,
+        }
+    }
+
+    pub fn row_count(&self) -> u64 {
+        self.data_files
+            .iter()
+            .map(|file| file.row_count)
+            .sum()
+    }
+
+    pub fn serialize_files(files: &[DataFile]) -> anyhow::Result<Vec<u8>> {
+        Ok(bincode::serde::encode_to_vec(files, standard())?)
+    }
+
+    pub fn de_serialize_files(encoded: Vec<u8>) -> anyhow::Result<Vec<DataFile>> {
+        let (files, _): (Vec<DataFile>, _) = bincode::serde::decode_from_slice(
+            &encoded,
+            standard()
+        )?;
+
+        Ok(files)
+    }
+}
+
+/// Returned when a commit loses the race for one of a table's pointers, its current
+/// snapshot or current schema.
+#[derive(Debug)]
+pub struct CommitConflict {
+    pub table_id: i64,
+    /// Which pointer moved, `"snapshot"`, `"schema"`, `"partition spec"`, `"metadata"`,
+    /// `"Delta log"` or `"schema id"`, the id a table was to be created with.
+    pub pointer: &'static str,
+    pub expected_id: Option<i64>,
+}
+
+impl fmt::Display for CommitConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Commit conflict on table {}: current {} is no longer {:?}",
+            self.table_id,
+            self.pointer,
+            self.expected_id
+        )
+    }
+}
+
+impl std::error::Error for CommitConflict {}
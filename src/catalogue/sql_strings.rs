@@ -1,12 +1,27 @@
 pub const CREATE_SYSTEM_TABLE_SQL: &str =
     r#"
+CREATE TABLE IF NOT EXISTS sys_namespaces (
+    namespace_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    namespace_name TEXT NOT NULL UNIQUE,
+    properties TEXT NOT NULL,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP
+) STRICT;
+
+INSERT OR IGNORE INTO sys_namespaces (namespace_name, properties) VALUES ('default', '{}');
+
 CREATE TABLE IF NOT EXISTS sys_tables (
     table_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    namespace_id INTEGER NOT NULL,
     table_name TEXT NOT NULL,
+    table_uuid TEXT NULL,
     table_url_string TEXT NULL,
     schema_id INTEGER NOT NULL,
     partition_string TEXT NULL,
+    properties TEXT NULL,
+    current_snapshot_id INTEGER NULL,
+    metadata_location TEXT NULL,
     created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (namespace_id) REFERENCES sys_namespaces(namespace_id),
     FOREIGN KEY (schema_id) REFERENCES sys_schemas(schema_id)
 ) STRICT;
 
@@ -15,14 +30,101 @@ CREATE TABLE IF NOT EXISTS sys_schemas (
     schema_bin BLOB NOT NULL,
     versions TEXT NULL,
     table_name TEXT NOT NULL,
+    table_id INTEGER NULL,
     FOREIGN KEY (table_name) REFERENCES sys_tables(table_name)
 ) STRICT;
+
+CREATE TABLE IF NOT EXISTS sys_snapshots (
+    snapshot_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    table_id INTEGER NOT NULL,
+    parent_snapshot_id INTEGER NULL,
+    sequence_number INTEGER NOT NULL,
+    operation TEXT NOT NULL,
+    data_files BLOB NOT NULL,
+    summary TEXT NOT NULL,
+    timestamp_ms INTEGER NOT NULL,
+    manifest_list TEXT NULL,
+    FOREIGN KEY (table_id) REFERENCES sys_tables(table_id)
+) STRICT;
+"#;
+
+/// Brings a catalogue created before namespaces and table metadata existed up to
+/// version 1. Runs after `CREATE_SYSTEM_TABLE_SQL`, which has already added the
+/// tables that were missing, and files every existing table under `default`.
+pub const MIGRATE_SYSTEM_TABLES_V1_SQL: &str =
+    r#"
+CREATE TABLE sys_tables_v1 (
+    table_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    namespace_id INTEGER NOT NULL,
+    table_name TEXT NOT NULL,
+    table_uuid TEXT NULL,
+    table_url_string TEXT NULL,
+    schema_id INTEGER NOT NULL,
+    partition_string TEXT NULL,
+    properties TEXT NULL,
+    current_snapshot_id INTEGER NULL,
+    metadata_location TEXT NULL,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (namespace_id) REFERENCES sys_namespaces(namespace_id),
+    FOREIGN KEY (schema_id) REFERENCES sys_schemas(schema_id)
+) STRICT;
+
+INSERT INTO sys_tables_v1 (
+    table_id, namespace_id, table_name, table_url_string, schema_id, partition_string,
+    created_at
+)
+SELECT table_id,
+    (SELECT namespace_id FROM sys_namespaces WHERE namespace_name = 'default'),
+    table_name, table_url_string, schema_id, partition_string, created_at
+FROM sys_tables;
+
+DROP TABLE sys_tables;
+
+ALTER TABLE sys_tables_v1 RENAME TO sys_tables;
+
+ALTER TABLE sys_schemas ADD COLUMN table_id INTEGER NULL;
+
+UPDATE sys_schemas SET table_id = (
+    SELECT table_id FROM sys_tables WHERE sys_tables.schema_id = sys_schemas.schema_id
+);
+"#;
+
+/// Brings a version 1 catalogue to version 2, where a table's name is unique within
+/// its namespace. Tables hidden by a newer table created under the same name are
+/// renamed to `<name>_<table_id>`.
+pub const MIGRATE_SYSTEM_TABLES_V2_SQL: &str =
+    r#"
+UPDATE sys_schemas SET table_name = table_name || '_' || table_id
+WHERE table_id IN (
+    SELECT hidden.table_id FROM sys_tables AS hidden
+    JOIN sys_tables AS newer ON newer.namespace_id = hidden.namespace_id
+        AND newer.table_name = hidden.table_name AND newer.table_id > hidden.table_id
+);
+
+UPDATE sys_tables SET table_name = table_name || '_' || table_id
+WHERE EXISTS (
+    SELECT 1 FROM sys_tables AS newer
+    WHERE newer.namespace_id = sys_tables.namespace_id
+        AND newer.table_name = sys_tables.table_name AND newer.table_id > sys_tables.table_id
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS sys_tables_namespace_name
+ON sys_tables (namespace_id, table_name);
+"#;
+
+/// Whether `sys_tables` predates namespaces, i.e. the catalogue needs migrating.
+pub const SELECT_LEGACY_SYS_TABLES: &str =
+    r#"
+SELECT COUNT(*) = 0 FROM pragma_table_info('sys_tables') WHERE name = 'namespace_id';
 "#;
 
 pub const INSERT_SYS_TABLES: &str =
     r#"
-INSERT INTO sys_tables (table_name, schema_id)
-VALUES ( ?, ?);
+INSERT INTO sys_tables (
+    namespace_id, table_name, table_uuid, table_url_string, schema_id,
+    partition_string, properties
+)
+VALUES (?, ?, ?, ?, ?, ?, ?);
 "#;
 
 pub const DELETE_SYS_TABLES: &str = r#"
@@ -34,26 +136,226 @@ pub const SELECT_SYS_TABLES: &str = r#"
 SELECT * FROM sys_tables WHERE table_id = ?;
 "#;
 
-pub const SELECT_NAME_SYS_TABLES: &str = r#"
-SELECT table_name FROM sys_tables ;
+pub const SELECT_TABLE_NAME_SYS_TABLES: &str = r#"
+SELECT table_name FROM sys_tables WHERE table_id = ?;
 "#;
 
-pub const INSERT_SYS_SCHEMAS: &str =
+pub const SELECT_URL_SYS_TABLES: &str = r#"
+SELECT table_url_string FROM sys_tables WHERE table_id = ?;
+"#;
+
+pub const SELECT_ID_SYS_TABLES: &str =
+    r#"
+SELECT sys_tables.table_id FROM sys_tables
+JOIN sys_namespaces ON sys_namespaces.namespace_id = sys_tables.namespace_id
+WHERE sys_namespaces.namespace_name = ? AND sys_tables.table_name = ?;
+"#;
+
+pub const SELECT_NAME_SYS_TABLES: &str =
+    r#"
+SELECT sys_tables.table_id, sys_namespaces.namespace_name, sys_tables.table_name
+FROM sys_tables
+JOIN sys_namespaces ON sys_namespaces.namespace_id = sys_tables.namespace_id;
+"#;
+
+pub const SELECT_NAMESPACE_SYS_TABLES: &str =
+    r#"
+SELECT sys_tables.table_name FROM sys_tables
+JOIN sys_namespaces ON sys_namespaces.namespace_id = sys_tables.namespace_id
+WHERE sys_namespaces.namespace_name = ?
+ORDER BY sys_tables.table_name;
+"#;
+
+pub const SELECT_METADATA_SYS_TABLES: &str =
+    r#"
+SELECT table_uuid, table_url_string, schema_id, partition_string, properties, metadata_location
+FROM sys_tables WHERE table_id = ?;
+"#;
+
+/// Compare-and-swap of the Iceberg `metadata.json` a table points at.
+pub const ADVANCE_SYS_TABLES_METADATA: &str =
+    r#"
+UPDATE sys_tables SET metadata_location = ?
+WHERE table_id = ? AND metadata_location IS ?;
+"#;
+
+pub const RENAME_SYS_TABLES: &str =
+    r#"
+UPDATE sys_tables SET namespace_id = ?, table_name = ?
+WHERE table_id = ?;
+"#;
+
+pub const SELECT_PARTITION_SYS_TABLES: &str =
+    r#"
+SELECT partition_string FROM sys_tables WHERE table_id = ?;
+"#;
+
+pub const UPDATE_PARTITION_SYS_TABLES: &str =
+    r#"
+UPDATE sys_tables SET partition_string = ?
+WHERE table_id = ?;
+"#;
+
+pub const SELECT_PROPERTIES_SYS_TABLES: &str =
+    r#"
+SELECT properties FROM sys_tables WHERE table_id = ?;
+"#;
+
+pub const UPDATE_PROPERTIES_SYS_TABLES: &str =
+    r#"
+UPDATE sys_tables SET properties = ?
+WHERE table_id = ?;
+"#;
+
+pub const COUNT_NAMESPACE_SYS_TABLES: &str = r#"
+SELECT COUNT(*) FROM sys_tables WHERE namespace_id = ?;
+"#;
+
+pub const INSERT_SYS_NAMESPACES: &str =
     r#"
-INSERT INTO sys_schemas (table_name, schema_bin)
+INSERT INTO sys_namespaces (namespace_name, properties)
 VALUES (?, ?);
 "#;
 
+pub const DELETE_SYS_NAMESPACES: &str = r#"
+DELETE FROM sys_namespaces
+WHERE namespace_id = ?;
+"#;
+
+pub const SELECT_ID_SYS_NAMESPACES: &str =
+    r#"
+SELECT namespace_id FROM sys_namespaces WHERE namespace_name = ?;
+"#;
+
+pub const SELECT_NAME_SYS_NAMESPACES: &str =
+    r#"
+SELECT namespace_name FROM sys_namespaces ORDER BY namespace_name;
+"#;
+
+pub const SELECT_PROPERTIES_SYS_NAMESPACES: &str =
+    r#"
+SELECT properties FROM sys_namespaces WHERE namespace_name = ?;
+"#;
+
+pub const UPDATE_PROPERTIES_SYS_NAMESPACES: &str =
+    r#"
+UPDATE sys_namespaces SET properties = ?
+WHERE namespace_name = ?;
+"#;
+
+pub const INSERT_SYS_SCHEMAS: &str =
+    r#"
+INSERT INTO sys_schemas (schema_id, table_name, schema_bin, versions, table_id)
+VALUES (?, ?, ?, ?, ?);
+"#;
+
+pub const RENAME_SYS_SCHEMAS: &str = r#"
+UPDATE sys_schemas SET table_name = ?
+WHERE table_id = ?;
+"#;
+
+pub const LINK_SYS_SCHEMAS: &str = r#"
+UPDATE sys_schemas SET table_id = ?
+WHERE schema_id = ?;
+"#;
+
+/// Compare-and-swap of a table's current schema, see `ADVANCE_SYS_TABLES_SNAPSHOT`.
+pub const ADVANCE_SYS_TABLES_SCHEMA: &str =
+    r#"
+UPDATE sys_tables SET schema_id = ?
+WHERE table_id = ? AND schema_id = ?;
+"#;
+
 pub const DELETE_SYS_SCHEMAS: &str = r#"
 DELETE FROM sys_schemas
 WHERE table_id = ?;
 "#;
 
+pub const SELECT_SCHEMA_ID_SYS_TABLES: &str =
+    r#"
+SELECT schema_id FROM sys_tables WHERE table_id = ?;
+"#;
+
+pub const SELECT_TABLE_SYS_SCHEMAS: &str =
+    r#"
+SELECT schema_id, versions, schema_bin FROM sys_schemas
+WHERE table_id = ? ORDER BY schema_id;
+"#;
+
 pub const SELECT_SYS_SCHEMAS: &str = r#"
 SELECT * FROM sys_schemas WHERE schema_id = ?;
 "#;
 
 pub const SELECT_SCHEMA_FROM_SYS_SCHEMA: &str =
     r#"
-SELECT schema_bin FROM sys_schemas WHERE table_id = ?;
+SELECT sys_schemas.schema_bin FROM sys_schemas
+JOIN sys_tables ON sys_tables.schema_id = sys_schemas.schema_id
+WHERE sys_tables.table_id = ?;
+"#;
+
+pub const SELECT_NEXT_ID_SYS_SCHEMAS: &str =
+    r#"
+SELECT MAX(
+    COALESCE((SELECT seq FROM sqlite_sequence WHERE name = 'sys_schemas'), 0),
+    COALESCE((SELECT MAX(schema_id) FROM sys_schemas), 0)
+) + 1;
+"#;
+
+pub const SELECT_EXISTS_SYS_SCHEMAS: &str =
+    r#"
+SELECT EXISTS(SELECT 1 FROM sys_schemas WHERE schema_id = ?);
+"#;
+
+pub const INSERT_SYS_SNAPSHOTS: &str =
+    r#"
+INSERT INTO sys_snapshots (
+    snapshot_id, table_id, parent_snapshot_id, sequence_number, operation, data_files, summary,
+    timestamp_ms, manifest_list
+)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?);
+"#;
+
+/// Compare-and-swap of a table's current snapshot, only succeeds if the pointer
+/// still refers to the snapshot the commit was based on.
+pub const ADVANCE_SYS_TABLES_SNAPSHOT: &str =
+    r#"
+UPDATE sys_tables SET current_snapshot_id = ?
+WHERE table_id = ? AND current_snapshot_id IS ?;
+"#;
+
+pub const SELECT_CURRENT_SYS_SNAPSHOTS: &str =
+    r#"
+SELECT sys_snapshots.snapshot_id, sys_snapshots.table_id, sys_snapshots.parent_snapshot_id,
+    sys_snapshots.sequence_number, sys_snapshots.operation, sys_snapshots.data_files,
+    sys_snapshots.summary, sys_snapshots.timestamp_ms, sys_snapshots.manifest_list
+FROM sys_snapshots
+JOIN sys_tables ON sys_tables.current_snapshot_id = sys_snapshots.snapshot_id
+WHERE sys_tables.table_id = ?;
+"#;
+
+pub const SELECT_TABLE_SYS_SNAPSHOTS: &str =
+    r#"
+SELECT snapshot_id, table_id, parent_snapshot_id, sequence_number, operation, data_files,
+    summary, timestamp_ms, manifest_list
+FROM sys_snapshots WHERE table_id = ? ORDER BY sequence_number;
+"#;
+
+pub const SELECT_VERSION_SYS_SNAPSHOTS: &str =
+    r#"
+SELECT snapshot_id, table_id, parent_snapshot_id, sequence_number, operation, data_files,
+    summary, timestamp_ms, manifest_list
+FROM sys_snapshots WHERE table_id = ? AND sequence_number = ?;
+"#;
+
+pub const SELECT_AS_OF_SYS_SNAPSHOTS: &str =
+    r#"
+SELECT snapshot_id, table_id, parent_snapshot_id, sequence_number, operation, data_files,
+    summary, timestamp_ms, manifest_list
+FROM sys_snapshots WHERE table_id = ? AND timestamp_ms <= ?
+ORDER BY sequence_number DESC LIMIT 1;
+"#;
+
+pub const DELETE_SYS_SNAPSHOTS: &str = r#"
+DELETE FROM sys_snapshots
+WHERE table_id = ?;
 "#;
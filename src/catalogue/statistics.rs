@@ -0,0 +1,321 @@
+use std::{ collections::{ BTreeMap, HashSet }, sync::Arc };
+
+use arrow_array::{ Array, ArrayRef, BooleanArray, RecordBatch, UInt64Array };
+use arrow_schema::{ DataType, Schema, SchemaRef };
+
+use datafusion::arrow::compute::cast;
+use datafusion::catalog::Session;
+use datafusion::common::{ Column, DFSchema, ScalarValue };
+use datafusion::functions_aggregate::min_max::{ MaxAccumulator, MinAccumulator };
+use datafusion::logical_expr::{ utils::conjunction, Accumulator, Expr };
+use datafusion::physical_optimizer::pruning::{ PruningPredicate, PruningStatistics };
+
+use crate::catalogue::{
+    partitioning::{ PartitionSpec, Transform },
+    snapshots::{ ColumnStats, DataFile },
+    tables::SchemaVec,
+};
+
+/// Collects the statistics of every column of one data file as it is written.
+pub(crate) struct StatsCollector {
+    columns: Vec<ColumnCollector>,
+}
+
+struct ColumnCollector {
+    field_id: Option<i32>,
+    /// `None` for types without an order bounds are kept for.
+    bounds: Option<(MinAccumulator, MaxAccumulator)>,
+    null_count: u64,
+}
+
+impl StatsCollector {
+    pub(crate) fn new(schema: &Schema) -> Self {
+        let columns = schema
+            .fields()
+            .iter()
+            .map(|field| {
+                let bounds = has_bounds(field.data_type())
+                    .then(|| {
+                        Some((
+                            MinAccumulator::try_new(field.data_type()).ok()?,
+                            MaxAccumulator::try_new(field.data_type()).ok()?,
+                        ))
+                    })
+                    .flatten();
+
+                ColumnCollector {
+                    field_id: SchemaVec::field_id(field),
+                    bounds,
+                    null_count: 0,
+                }
+            })
+            .collect();
+
+        StatsCollector { columns }
+    }
+
+    pub(crate) fn update(&mut self, batch: &RecordBatch) -> anyhow::Result<()> {
+        for (collector, column) in self.columns.iter_mut().zip(batch.columns()) {
+            collector.null_count += column.null_count() as u64;
+
+            if let Some((min, max)) = collector.bounds.as_mut() {
+                min.update_batch(std::slice::from_ref(column))?;
+                max.update_batch(std::slice::from_ref(column))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The collected statistics by field id, columns without one are left out.
+    pub(crate) fn finish(self) -> anyhow::Result<BTreeMap<i32, ColumnStats>> {
+        let mut stats = BTreeMap::new();
+
+        for collector in self.columns {
+            let Some(field_id) = collector.field_id else {
+                continue;
+            };
+
+            let (min, max) = match collector.bounds {
+                Some((mut min, mut max)) => (to_text(min.evaluate()?)?, to_text(max.evaluate()?)?),
+                None => (None, None),
+            };
+
+            stats.insert(field_id, ColumnStats { min, max, null_count: collector.null_count });
+        }
+
+        Ok(stats)
+    }
+}
+
+/// Whether min and max bounds are kept for columns of a type.
+fn has_bounds(datatype: &DataType) -> bool {
+    datatype.is_integer() ||
+        datatype.is_floating() ||
+        matches!(
+            datatype,
+            | DataType::Boolean
+            | DataType::Utf8
+            | DataType::LargeUtf8
+            | DataType::Utf8View
+            | DataType::Date32
+            | DataType::Date64
+            | DataType::Timestamp(_, _)
+            | DataType::Decimal128(_, _)
+            | DataType::Decimal256(_, _)
+        )
+}
+
+fn to_text(value: ScalarValue) -> anyhow::Result<Option<String>> {
+    if value.is_null() {
+        return Ok(None);
+    }
+
+    let text = cast(&value.to_array()?, &DataType::Utf8)?;
+
+    Ok(ScalarValue::try_from_array(&text, 0)?.try_as_str().flatten().map(str::to_string))
+}
+
+/// Drops the files `filters` can't match, judged by their recorded column
+/// statistics and partition values.
+///
+/// `specs` are every partition spec of the table, each file's partition values are
+/// read with the spec it was written with.
+pub(crate) fn prune_files(
+    state: &dyn Session,
+    schema: &SchemaRef,
+    specs: &[PartitionSpec],
+    filters: &[Expr],
+    files: Vec<DataFile>
+) -> anyhow::Result<Vec<DataFile>> {
+    let Some(filter) = conjunction(filters.to_vec()) else {
+        return Ok(files);
+    };
+
+    let df_schema = DFSchema::try_from(schema.as_ref().clone())?;
+    let predicate = PruningPredicate::try_new(
+        state.create_physical_expr(filter, &df_schema)?,
+        schema.clone()
+    )?;
+
+    if predicate.always_true() {
+        return Ok(files);
+    }
+
+    let statistics = FileStatistics { schema, specs, files: &files };
+    let keep = predicate.prune(&statistics)?;
+
+    Ok(
+        files
+            .into_iter()
+            .zip(keep)
+            .filter_map(|(file, keep)| keep.then_some(file))
+            .collect()
+    )
+}
+
+/// What is known about the columns of a table's data files.
+///
+/// Bounds recorded when a file was written are narrowed by the file's partition
+/// values, so a `day(ts)` partition bounds `ts` even for files without statistics.
+struct FileStatistics<'a> {
+    schema: &'a SchemaRef,
+    specs: &'a [PartitionSpec],
+    files: &'a [DataFile],
+}
+
+impl FileStatistics<'_> {
+    /// Field id and type of a table column.
+    fn field(&self, column: &Column) -> Option<(i32, &DataType)> {
+        let field = self.schema.field_with_name(&column.name).ok()?;
+
+        Some((SchemaVec::field_id(field)?, field.data_type()))
+    }
+
+    /// The partition fields of a file's spec sourced from a column, with their
+    /// position in the file's partition values.
+    fn partitions(&self, file: &DataFile, field_id: i32) -> Vec<(usize, Transform)> {
+        let Some(spec) = self.specs.iter().find(|spec| spec.spec_id == file.spec_id) else {
+            return Vec::new();
+        };
+
+        spec.fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| field.source_id == field_id)
+            .filter_map(|(index, field)| Some((index, Transform::parse(&field.transform).ok()?)))
+            .collect()
+    }
+
+    fn bounds(&self, column: &Column, max: bool) -> Option<ArrayRef> {
+        let (field_id, datatype) = self.field(column)?;
+
+        let mut bounds = Vec::with_capacity(self.files.len());
+
+        for file in self.files {
+            let recorded = file.column_stats
+                .get(&field_id)
+                .and_then(|stats| if max { stats.max.clone() } else { stats.min.clone() })
+                .and_then(|text| ScalarValue::try_from_string(text, datatype).ok());
+
+            let partitions = self.partitions(file, field_id);
+            let derived = partitions.iter().filter_map(|(index, transform)| {
+                let value = file.partition_values.get(*index)?.as_deref()?;
+                let (min_bound, max_bound) = transform.source_bounds(datatype, value);
+
+                if max { max_bound } else { min_bound }
+            });
+
+            // The tightest of the known bounds
+            let bound = recorded
+                .into_iter()
+                .chain(derived)
+                .reduce(|bound, other| {
+                    let tighter = match max {
+                        true => other < bound,
+                        false => other > bound,
+                    };
+
+                    if tighter { other } else { bound }
+                });
+
+            bounds.push(match bound {
+                Some(bound) => bound,
+                None => ScalarValue::try_from(datatype).ok()?,
+            });
+        }
+
+        ScalarValue::iter_to_array(bounds).ok()
+    }
+}
+
+impl PruningStatistics for FileStatistics<'_> {
+    fn min_values(&self, column: &Column) -> Option<ArrayRef> {
+        self.bounds(column, false)
+    }
+
+    fn max_values(&self, column: &Column) -> Option<ArrayRef> {
+        self.bounds(column, true)
+    }
+
+    fn num_containers(&self) -> usize {
+        self.files.len()
+    }
+
+    fn null_counts(&self, column: &Column) -> Option<ArrayRef> {
+        let (field_id, _) = self.field(column)?;
+
+        let null_counts = self.files.iter().map(|file| {
+            // A null partition value only comes from a null source value
+            let all_null = self
+                .partitions(file, field_id)
+                .iter()
+                .any(|(index, _)| matches!(file.partition_values.get(*index), Some(None)));
+
+            match all_null {
+                true => Some(file.row_count),
+                false => file.column_stats.get(&field_id).map(|stats| stats.null_count),
+            }
+        });
+
+        Some(Arc::new(null_counts.collect::<UInt64Array>()))
+    }
+
+    fn row_counts(&self, _column: &Column) -> Option<ArrayRef> {
+        let row_counts = self.files.iter().map(|file| Some(file.row_count));
+
+        Some(Arc::new(row_counts.collect::<UInt64Array>()))
+    }
+
+    /// Identity partitions hold a single value, bucket partitions only values that
+    /// hash to them.
+    fn contained(&self, column: &Column, values: &HashSet<ScalarValue>) -> Option<BooleanArray> {
+        let (field_id, datatype) = self.field(column)?;
+
+        let partitioned = self.specs
+            .iter()
+            .flat_map(|spec| &spec.fields)
+            .any(|field| field.source_id == field_id);
+
+        if !partitioned || values.iter().any(|value| value.data_type() != *datatype) {
+            return None;
+        }
+
+        let values = ScalarValue::iter_to_array(values.iter().cloned()).ok()?;
+
+        // Partition values of the literals by transform, shared across specs
+        let mut literals: Vec<(Transform, Vec<Option<String>>)> = Vec::new();
+        let mut contained = Vec::with_capacity(self.files.len());
+
+        for file in self.files {
+            let mut file_contained = None;
+
+            for (index, transform) in self.partitions(file, field_id) {
+                let Some(value) = file.partition_values.get(index) else {
+                    continue;
+                };
+
+                let position = match literals.iter().position(|(known, _)| *known == transform) {
+                    Some(position) => position,
+                    None => {
+                        literals.push((transform, transform.apply(&values).ok()?));
+                        literals.len() - 1
+                    }
+                };
+
+                if !literals[position].1.contains(value) {
+                    file_contained = Some(false);
+                    break;
+                }
+
+                if transform == Transform::Identity && value.is_some() {
+                    file_contained = Some(true);
+                }
+            }
+
+            contained.push(file_contained);
+        }
+
+        Some(contained.into_iter().collect())
+    }
+}
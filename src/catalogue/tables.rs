@@ -1,13 +1,23 @@
 use bytes::{ Buf, BufMut, Bytes, BytesMut };
 
-use arrow_schema::DataType;
+use std::{ collections::{ BTreeMap, HashMap }, fmt };
+
+use arrow_schema::{ DataType, Field, Schema };
 
 use bincode::config::standard;
 
+use parquet::arrow::PARQUET_FIELD_ID_META_KEY;
+
 use serde::{ Serialize, Deserialize };
 
+use crate::catalogue::{ partitioning::PartitionSpec, snapshots::Snapshot };
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Column {
+    /// Stable id of the column. Survives renames and is never reused within a table,
+    /// so data files are matched to the current schema by id rather than by name.
+    pub field_id: i32,
+
     /// Column name. Can't be empty.
     pub name: String,
 
@@ -27,6 +37,21 @@ pub struct Column {
     pub references: Option<String>,
 }
 
+/// Column layout written before columns carried field ids.
+#[derive(Deserialize)]
+struct LegacyColumn {
+    name: String,
+    datatype: DataType,
+    nullable: bool,
+    unique: bool,
+    references: Option<String>,
+}
+
+/// Leads every schema blob written with field ids. Legacy blobs start with the length
+/// of their first column, which can never be this large.
+const SCHEMA_FORMAT_MARKER: u32 = u32::MAX;
+const SCHEMA_FORMAT_VERSION: u32 = 1;
+
 impl Column {
     pub fn serialize_column(column: Column) -> Vec<u8> {
         let config = standard();
@@ -36,18 +61,34 @@ impl Column {
         encoded
     }
 
-    pub fn de_serialize_column(encoded: Vec<u8>) -> Column {
-        let config = standard();
+    pub fn de_serialize_column(encoded: Vec<u8>) -> anyhow::Result<Column> {
+        let (decoded_column, _): (Column, _) = bincode::serde::decode_from_slice(
+            &encoded,
+            standard()
+        )?;
+
+        Ok(decoded_column)
+    }
 
-        let (decoded_column, _): (Column, _) = bincode::serde
-            ::decode_from_slice(&encoded, standard())
-            .unwrap();
+    /// Decodes a column stored before field ids existed, giving it `field_id`.
+    fn de_serialize_legacy_column(encoded: Vec<u8>, field_id: i32) -> anyhow::Result<Column> {
+        let (legacy, _): (LegacyColumn, _) = bincode::serde::decode_from_slice(
+            &encoded,
+            standard()
+        )?;
 
-        decoded_column
+        Ok(Column {
+            field_id,
+            name: legacy.name,
+            datatype: legacy.datatype,
+            nullable: legacy.nullable,
+            unique: legacy.unique,
+            references: legacy.references,
+        })
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct SchemaVec {
     pub columns: Vec<Column>,
 }
@@ -61,9 +102,99 @@ impl SchemaVec {
         self.columns.push(column);
     }
 
+    /// Builds a catalogue schema from an Arrow schema, e.g. one inferred during ingestion.
+    pub fn from_arrow_schema(schema: &Schema) -> Self {
+        let columns = schema
+            .fields()
+            .iter()
+            .enumerate()
+            .map(|(index, field)| Column {
+                field_id: (index as i32) + 1,
+                name: field.name().clone(),
+                datatype: field.data_type().clone(),
+                nullable: field.is_nullable(),
+                unique: false,
+                references: None,
+            })
+            .collect();
+
+        SchemaVec { columns }
+    }
+
+    /// Builds a catalogue schema from an Arrow schema whose fields carry their field
+    /// ids, as [`SchemaVec::to_arrow_schema`] writes them, keeping the ids.
+    pub fn from_numbered_arrow_schema(schema: &Schema) -> anyhow::Result<Self> {
+        let mut columns: Vec<Column> = Vec::with_capacity(schema.fields().len());
+
+        for field in schema.fields() {
+            let field_id = SchemaVec::field_id(field)
+                .filter(|field_id| *field_id > 0)
+                .ok_or_else(|| anyhow::anyhow!("Column '{}' has no valid field id", field.name()))?;
+
+            if columns.iter().any(|column| column.field_id == field_id) {
+                return Err(anyhow::anyhow!("Field id {} is used twice", field_id));
+            }
+
+            columns.push(Column {
+                field_id,
+                name: field.name().clone(),
+                datatype: field.data_type().clone(),
+                nullable: field.is_nullable(),
+                unique: false,
+                references: None,
+            });
+        }
+
+        Ok(SchemaVec { columns })
+    }
+
+    pub fn column_by_id(&self, field_id: i32) -> Option<&Column> {
+        self.columns.iter().find(|column| column.field_id == field_id)
+    }
+
+    pub fn column_by_name(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    /// Highest field id in use, `0` for an empty schema.
+    pub fn last_field_id(&self) -> i32 {
+        self.columns
+            .iter()
+            .map(|column| column.field_id)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// The Arrow schema readers should use when scanning the table's data files.
+    ///
+    /// Each field carries its id as `PARQUET:field_id` metadata, which the parquet
+    /// writer stores in the file schema.
+    pub fn to_arrow_schema(&self) -> Schema {
+        let fields: Vec<Field> = self.columns
+            .iter()
+            .map(|column| {
+                Field::new(&column.name, column.datatype.clone(), column.nullable).with_metadata(
+                    HashMap::from([
+                        (PARQUET_FIELD_ID_META_KEY.to_string(), column.field_id.to_string()),
+                    ])
+                )
+            })
+            .collect();
+
+        Schema::new(fields)
+    }
+
+    /// The field id stored on an Arrow field, if it was read from a file that has one.
+    pub fn field_id(field: &Field) -> Option<i32> {
+        field.metadata().get(PARQUET_FIELD_ID_META_KEY)?.parse().ok()
+    }
+
     pub fn serialize_schema(schema: &SchemaVec) -> Vec<u8> {
         let mut vec = BytesMut::with_capacity(64);
 
+        vec.put_u32_le(SCHEMA_FORMAT_MARKER);
+        vec.put_u32_le(SCHEMA_FORMAT_VERSION);
+
         for column in schema.columns.clone().into_iter() {
             let encoded = Bytes::from(Column::serialize_column(column));
             let encoded_length = encoded.len() as u32;
@@ -75,26 +206,171 @@ impl SchemaVec {
         vec.to_vec()
     }
 
-    pub fn de_serialize_schema(encoded_buf: Vec<u8>) -> SchemaVec {
+    /// Decodes a schema blob. Blobs written before columns had field ids get
+    /// positional ids, matching the order the columns were created in.
+    pub fn de_serialize_schema(encoded_buf: Vec<u8>) -> anyhow::Result<SchemaVec> {
         let mut encoded = Bytes::from(encoded_buf);
 
+        let legacy = !(encoded.len() >= 8 && encoded[..4] == SCHEMA_FORMAT_MARKER.to_le_bytes());
+        if !legacy {
+            encoded.advance(4);
+            let version = encoded.get_u32_le();
+            if version != SCHEMA_FORMAT_VERSION {
+                return Err(anyhow::anyhow!("Unsupported schema format version {}", version));
+            }
+        }
+
         let mut columns: Vec<Column> = Vec::new();
 
         while encoded.has_remaining() {
-            let column_len = encoded.get_u32_le();
-            let mut data_buf = vec![0u8; column_len as usize];
+            if encoded.remaining() < 4 {
+                return Err(anyhow::anyhow!("Truncated schema column length"));
+            }
+            let column_len = encoded.get_u32_le() as usize;
+            if encoded.remaining() < column_len {
+                return Err(anyhow::anyhow!("Truncated schema column"));
+            }
+            let mut data_buf = vec![0u8; column_len];
 
             encoded.copy_to_slice(&mut data_buf);
 
-            columns.push(Column::de_serialize_column(data_buf));
+            let column = match legacy {
+                true => Column::de_serialize_legacy_column(data_buf, (columns.len() as i32) + 1)?,
+                false => Column::de_serialize_column(data_buf)?,
+            };
+            columns.push(column);
         }
 
-        SchemaVec { columns }
+        Ok(SchemaVec { columns })
     }
 }
 
+/// One entry of a table's schema history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaVersion {
+    pub schema_id: i64,
+    /// Per-table version, starting at 1 when the table is created.
+    pub version: i64,
+    pub schema: SchemaVec,
+}
+
+/// A table's name within its catalogue.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TableIdent {
+    /// Dot separated namespace, e.g. `default` or `team.analytics`.
+    pub namespace: String,
+    pub name: String,
+}
+
+impl TableIdent {
+    pub fn new(namespace: &str, name: &str) -> Self {
+        TableIdent {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for TableIdent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.namespace, self.name)
+    }
+}
+
+/// Returned when a table is created or renamed under a name already in use.
+#[derive(Debug)]
+pub struct TableExists(pub TableIdent);
+
+impl fmt::Display for TableExists {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Table '{}' already exists", self.0)
+    }
+}
+
+impl std::error::Error for TableExists {}
+
 pub struct Table {
+    /// Namespace the table is created in, which must already exist.
+    pub namespace: String,
     pub table_name: String,
     pub schema_bin: Vec<u8>,
     pub url: String,
+    /// Columns the table's files are partitioned by, empty if unpartitioned.
+    pub partition_spec: Vec<String>,
+    pub properties: BTreeMap<String, String>,
+}
+
+/// Table property naming the format a table's snapshots are published in,
+/// `iceberg` unless set.
+pub const TABLE_FORMAT_PROPERTY: &str = "table-format";
+
+/// The table format other engines read a table's files through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableFormat {
+    /// Iceberg v2 `metadata.json`, manifest lists and manifests.
+    Iceberg,
+    /// A Delta Lake `_delta_log` of JSON commits.
+    Delta,
+}
+
+impl TableFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TableFormat::Iceberg => "iceberg",
+            TableFormat::Delta => "delta",
+        }
+    }
+
+    pub fn parse(format: &str) -> anyhow::Result<Self> {
+        match format.to_ascii_lowercase().as_str() {
+            "iceberg" => Ok(TableFormat::Iceberg),
+            "delta" => Ok(TableFormat::Delta),
+            other => Err(anyhow::anyhow!("Unknown table format '{}'", other)),
+        }
+    }
+}
+
+/// Everything the catalogue knows about a table, see [`Catalog::load_table`].
+///
+/// [`Catalog::load_table`]: crate::catalogue::catalogue_storage::Catalog::load_table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableMetadata {
+    pub table_id: i64,
+    pub ident: TableIdent,
+    /// Assigned when the table is created, survives renames.
+    pub table_uuid: String,
+    /// Url of the directory holding the table's data files.
+    pub location: String,
+    /// The `sys_schemas` entry the table currently reads with.
+    pub schema_id: i64,
+    pub schema: SchemaVec,
+    /// Terms of the partition spec new files are written with.
+    pub partition_spec: Vec<String>,
+    /// Every partition spec the table has had, files keep the id of theirs.
+    pub partition_specs: Vec<PartitionSpec>,
+    /// Id of the partition spec new files are written with.
+    pub default_spec_id: i32,
+    pub properties: BTreeMap<String, String>,
+    /// Url of the Iceberg `metadata.json` last written for the table.
+    pub metadata_location: Option<String>,
+    /// `None` until the first write is committed.
+    pub current_snapshot: Option<Snapshot>,
+}
+
+impl TableMetadata {
+    /// The format set by the table's [`TABLE_FORMAT_PROPERTY`].
+    pub fn format(&self) -> anyhow::Result<TableFormat> {
+        self.properties
+            .get(TABLE_FORMAT_PROPERTY)
+            .map_or(Ok(TableFormat::Iceberg), |format| TableFormat::parse(format))
+    }
+
+    /// The partition spec new files are written with.
+    pub fn current_spec(&self) -> PartitionSpec {
+        self.partition_specs
+            .iter()
+            .find(|spec| spec.spec_id == self.default_spec_id)
+            .cloned()
+            .unwrap_or_else(PartitionSpec::unpartitioned)
+    }
 }
@@ -0,0 +1,345 @@
+use std::{ any::TypeId, ops::ControlFlow, sync::LazyLock };
+
+use datafusion::arrow::compute::kernels::cast_utils::string_to_timestamp_nanos;
+use datafusion::common::TableReference;
+use datafusion::sql::parser::{ DFParserBuilder, Statement };
+use datafusion::sql::sqlparser::ast::{
+    Expr as SqlExpr,
+    FunctionArg,
+    FunctionArgExpr,
+    FunctionArguments,
+    Ident,
+    ObjectNamePart,
+    TableFactor,
+    TableVersion as SqlTableVersion,
+    Value,
+    VisitMut,
+    VisitorMut,
+};
+use datafusion::sql::sqlparser::dialect::{ Dialect, GenericDialect };
+use datafusion::sql::sqlparser::parser::Parser;
+use datafusion::sql::sqlparser::tokenizer::{ Token, TokenWithSpan, Tokenizer };
+use regex::Regex;
+
+/// Which snapshot of a table a query reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableVersion {
+    /// The table's current snapshot.
+    Current,
+    /// The snapshot with the given per-table sequence number.
+    Version(i64),
+    /// The last snapshot committed at or before the given unix time in milliseconds.
+    Timestamp(i64),
+}
+
+static VERSIONED_NAME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^(.+)@(v|t)(\d+)$"#).unwrap()
+});
+
+impl TableVersion {
+    /// Splits a table name produced by [`parse_time_travel`], e.g. `orders@v3`,
+    /// into the catalogued name and the version to read.
+    pub fn split_name(name: &str) -> (&str, TableVersion) {
+        match VERSIONED_NAME.captures(name) {
+            Some(captures) => {
+                let table = captures.get(1).unwrap().as_str();
+                let value = captures[3].parse::<i64>().unwrap_or_default();
+
+                match &captures[2] {
+                    "v" => (table, TableVersion::Version(value)),
+                    _ => (table, TableVersion::Timestamp(value)),
+                }
+            }
+            None => (name, TableVersion::Current),
+        }
+    }
+
+    /// The table name that reads `table` at this version.
+    pub fn qualify(&self, table: &str) -> String {
+        match self {
+            TableVersion::Current => table.to_string(),
+            TableVersion::Version(version) => format!("{}@v{}", table, version),
+            TableVersion::Timestamp(timestamp_ms) => format!("{}@t{}", table, timestamp_ms),
+        }
+    }
+
+    /// The reference that reads the table `reference` points to at this version,
+    /// keeping its catalogue and namespace.
+    pub fn qualify_reference(&self, reference: &TableReference) -> TableReference {
+        let table = self.qualify(reference.table());
+
+        match reference {
+            TableReference::Bare { .. } => TableReference::bare(table),
+            TableReference::Partial { schema, .. } => {
+                TableReference::partial(schema.clone(), table)
+            }
+            TableReference::Full { catalog, schema, .. } => {
+                TableReference::full(catalog.clone(), schema.clone(), table)
+            }
+        }
+    }
+}
+
+/// DataFusion's generic dialect, also accepting `FOR SYSTEM_TIME AS OF` after
+/// table names.
+#[derive(Debug, Default)]
+pub struct LakeDialect;
+
+/// Delegates dialect flags to [`GenericDialect`].
+macro_rules! generic_flags {
+    ($($flag:ident),* $(,)?) => {
+        $(
+            fn $flag(&self) -> bool {
+                GenericDialect.$flag()
+            }
+        )*
+    };
+}
+
+impl Dialect for LakeDialect {
+    /// Parsed as the generic dialect wherever the parser special-cases dialects.
+    fn dialect(&self) -> TypeId {
+        TypeId::of::<GenericDialect>()
+    }
+
+    fn is_delimited_identifier_start(&self, ch: char) -> bool {
+        GenericDialect.is_delimited_identifier_start(ch)
+    }
+
+    fn is_identifier_start(&self, ch: char) -> bool {
+        GenericDialect.is_identifier_start(ch)
+    }
+
+    fn is_identifier_part(&self, ch: char) -> bool {
+        GenericDialect.is_identifier_part(ch)
+    }
+
+    fn supports_timestamp_versioning(&self) -> bool {
+        true
+    }
+
+    generic_flags!(
+        supports_unicode_string_literal,
+        supports_group_by_expr,
+        supports_group_by_with_modifier,
+        supports_connect_by,
+        supports_match_recognize,
+        supports_start_transaction_modifier,
+        supports_window_function_null_treatment_arg,
+        supports_dictionary_syntax,
+        supports_window_clause_named_window_reference,
+        supports_parenthesized_set_variables,
+        supports_select_wildcard_except,
+        support_map_literal_syntax,
+        allow_extract_custom,
+        allow_extract_single_quotes,
+        supports_create_index_with_clause,
+        supports_explain_with_utility_options,
+        supports_limit_comma,
+        supports_asc_desc_in_column_definition,
+        supports_try_convert,
+        supports_comment_on,
+        supports_load_extension,
+        supports_named_fn_args_with_assignment_operator,
+        supports_struct_literal,
+        supports_empty_projections,
+        supports_nested_comments,
+        supports_user_host_grantee,
+        supports_string_escape_constant,
+        supports_array_typedef_with_brackets,
+        supports_match_against,
+    );
+}
+
+/// Parses a single statement, rewriting `FOR VERSION AS OF` / `FOR TIMESTAMP AS OF`
+/// clauses, which DataFusion cannot plan, into quoted table names the lake schema
+/// provider understands.
+///
+/// `orders FOR VERSION AS OF 3` reads `"orders@v3"` and
+/// `lake.default.orders FOR TIMESTAMP AS OF '2025-06-01 12:00:00'` reads
+/// `lake.default."orders@t1748779200000"`. Timestamps may also be given as unix
+/// milliseconds, `FOR SYSTEM_TIME AS OF` is read like `FOR TIMESTAMP AS OF`.
+///
+/// The clauses are found by the parser, so string literals and comments are left
+/// alone. Quoted table names keep their case, unquoted ones are lowercased as the
+/// planner does.
+pub fn parse_time_travel(sql: &str, recursion_limit: usize) -> anyhow::Result<Statement> {
+    let tokens = Tokenizer::new(&LakeDialect, sql).tokenize_with_location()?;
+
+    let mut parser = DFParserBuilder::new("").with_dialect(&LakeDialect).build()?;
+    parser.parser = Parser::new(&LakeDialect)
+        .with_tokens_with_locations(version_clauses(tokens))
+        .with_recursion_limit(recursion_limit);
+
+    let mut statements = parser.parse_statements()?;
+
+    if statements.len() > 1 {
+        return Err(anyhow::anyhow!("Only a single SQL statement is supported"));
+    }
+
+    let mut statement = statements
+        .pop_front()
+        .ok_or_else(|| anyhow::anyhow!("No SQL statement was provided"))?;
+
+    rewrite_statement(&mut statement)?;
+
+    Ok(statement)
+}
+
+/// Turns `FOR VERSION AS OF 3` into `FOR SYSTEM_TIME AS OF VERSION(3)` and
+/// `FOR TIMESTAMP AS OF ..` into `FOR SYSTEM_TIME AS OF ..`, which the parser
+/// reads into the table's version.
+fn version_clauses(tokens: Vec<TokenWithSpan>) -> Vec<TokenWithSpan> {
+    let is_word = |token: &TokenWithSpan, word: &str| {
+        matches!(
+            &token.token,
+            Token::Word(w) if w.quote_style.is_none() && w.value.eq_ignore_ascii_case(word)
+        )
+    };
+
+    let significant: Vec<usize> = tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, token)| !matches!(token.token, Token::Whitespace(_)))
+        .map(|(index, _)| index)
+        .collect();
+
+    let mut tokens = tokens;
+    let mut versions = Vec::new();
+
+    for window in significant.windows(5) {
+        let &[keyword_for, kind, keyword_as, keyword_of, value] = window else {
+            continue;
+        };
+
+        let clause =
+            is_word(&tokens[keyword_for], "FOR") &&
+            is_word(&tokens[keyword_as], "AS") &&
+            is_word(&tokens[keyword_of], "OF");
+
+        if clause && is_word(&tokens[kind], "VERSION") {
+            versions.push(value);
+        } else if !(clause && is_word(&tokens[kind], "TIMESTAMP")) {
+            continue;
+        }
+
+        tokens[kind].token = Token::make_keyword("SYSTEM_TIME");
+    }
+
+    let mut rewritten = Vec::with_capacity(tokens.len() + versions.len() * 3);
+
+    for (index, token) in tokens.into_iter().enumerate() {
+        if !versions.contains(&index) {
+            rewritten.push(token);
+            continue;
+        }
+
+        let span = token.span;
+        rewritten.push(TokenWithSpan::new(Token::make_word(VERSION_FUNCTION, None), span));
+        rewritten.push(TokenWithSpan::new(Token::LParen, span));
+        rewritten.push(token);
+        rewritten.push(TokenWithSpan::new(Token::RParen, span));
+    }
+
+    rewritten
+}
+
+/// Name of the function `FOR VERSION AS OF` clauses are parsed into.
+const VERSION_FUNCTION: &str = "VERSION";
+
+fn rewrite_statement(statement: &mut Statement) -> anyhow::Result<()> {
+    match statement {
+        Statement::Statement(statement) => {
+            match statement.visit(&mut AsOfRewriter) {
+                ControlFlow::Break(e) => Err(e),
+                ControlFlow::Continue(()) => Ok(()),
+            }
+        }
+        Statement::Explain(explain) => rewrite_statement(&mut explain.statement),
+        _ => Ok(()),
+    }
+}
+
+/// Replaces the name of every table read at a version with its versioned name.
+struct AsOfRewriter;
+
+impl VisitorMut for AsOfRewriter {
+    type Break = anyhow::Error;
+
+    fn pre_visit_table_factor(&mut self, factor: &mut TableFactor) -> ControlFlow<Self::Break> {
+        let TableFactor::Table { name, version, .. } = factor else {
+            return ControlFlow::Continue(());
+        };
+
+        let Some(SqlTableVersion::ForSystemTimeAsOf(as_of)) = version else {
+            return ControlFlow::Continue(());
+        };
+
+        let table_version = match TableVersion::from_as_of(as_of) {
+            Ok(table_version) => table_version,
+            Err(e) => {
+                return ControlFlow::Break(e);
+            }
+        };
+
+        if let Some(ObjectNamePart::Identifier(table)) = name.0.last_mut() {
+            // Unquoted identifiers are normalised to lowercase by the planner, mirror
+            // that since the versioned name is quoted
+            let value = match table.quote_style {
+                Some(_) => table.value.clone(),
+                None => table.value.to_lowercase(),
+            };
+
+            *table = Ident::with_quote('"', table_version.qualify(&value));
+        }
+
+        *version = None;
+
+        ControlFlow::Continue(())
+    }
+}
+
+impl TableVersion {
+    /// The version an `AS OF` expression reads, see [`version_clauses`].
+    fn from_as_of(as_of: &SqlExpr) -> anyhow::Result<Self> {
+        let number = |value: &Value| match value {
+            Value::Number(number, _) => number.parse::<i64>().ok(),
+            _ => None,
+        };
+
+        match as_of {
+            SqlExpr::Function(function) if function.name.to_string() == VERSION_FUNCTION => {
+                let argument = match &function.args {
+                    FunctionArguments::List(list) if list.args.len() == 1 => list.args.first(),
+                    _ => None,
+                };
+                let version = match argument {
+                    Some(FunctionArg::Unnamed(FunctionArgExpr::Expr(SqlExpr::Value(value)))) => {
+                        number(&value.value)
+                    }
+                    _ => None,
+                };
+
+                version
+                    .map(TableVersion::Version)
+                    .ok_or_else(|| anyhow::anyhow!("Invalid version in AS OF clause: {}", as_of))
+            }
+            SqlExpr::Value(value) => {
+                if let Some(timestamp_ms) = number(&value.value) {
+                    return Ok(TableVersion::Timestamp(timestamp_ms));
+                }
+
+                let Value::SingleQuotedString(timestamp) = &value.value else {
+                    return Err(anyhow::anyhow!("Invalid timestamp in AS OF clause: {}", as_of));
+                };
+
+                let nanos = string_to_timestamp_nanos(timestamp).map_err(|e| {
+                    anyhow::anyhow!("Invalid timestamp in AS OF clause: {}", e)
+                })?;
+
+                Ok(TableVersion::Timestamp(nanos / 1_000_000))
+            }
+            _ => Err(anyhow::anyhow!("Unsupported AS OF clause: {}", as_of)),
+        }
+    }
+}
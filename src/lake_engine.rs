@@ -1,26 +1,1228 @@
-#[allow(unused_variables)]
-#[allow(dead_code)]
-use crate::catalogue::RootCatalogue;
-use crate::utils::{ csv_tools::reader::BlobWriter, storage::storage::BackEnd };
+use std::{ collections::{ BTreeMap, HashMap }, path::PathBuf, sync::Arc };
 
+use arrow_array::{ RecordBatch, UInt64Array };
+use arrow_schema::{ DataType, Field, Schema, SchemaRef };
+
+use datafusion::common::TableReference;
+use datafusion::dataframe::DataFrame;
+use datafusion::logical_expr::{
+    dml::InsertOp,
+    CreateMemoryTable,
+    DdlStatement,
+    DmlStatement,
+    LogicalPlan,
+    WriteOp,
+};
+use datafusion::prelude::{ SessionConfig, SessionContext };
+use datafusion::sql::parser::Statement;
+use datafusion::sql::planner::object_name_to_table_reference;
+use datafusion::sql::sqlparser::ast::{
+    AlterTableOperation,
+    CreateTable,
+    Expr as SqlExpr,
+    FunctionArg,
+    FunctionArgExpr,
+    FunctionArguments,
+    ObjectName,
+    SqlOption,
+    Statement as SqlStatement,
+    Value as SqlValue,
+};
+
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+use crate::blob_writer::WriteOptions;
+use crate::catalogue::{
+    catalogue_storage::Catalog,
+    delta::{
+        check_delta_write,
+        delta_table_properties,
+        read_delta_log,
+        remove_delta_commit,
+        write_delta_commit,
+    },
+    iceberg::{
+        new_snapshot_id,
+        read_table_metadata,
+        snapshot_files,
+        write_manifests,
+        write_table_metadata,
+    },
+    partitioning::PartitionSpec,
+    provider::{ LakeCatalogProvider, DEFAULT_CATALOGUE_NAME, DEFAULT_NAMESPACE },
+    snapshots::{ CommitConflict, DataFile, Operation, Snapshot },
+    time_travel::{ parse_time_travel, TableVersion },
+    evolution::{ normalize, PartitionChange, SchemaChange },
+    tables::{
+        SchemaVec,
+        SchemaVersion,
+        Table,
+        TableExists,
+        TableFormat,
+        TableIdent,
+        TableMetadata,
+        TABLE_FORMAT_PROPERTY,
+    },
+    CatalogueLocation,
+};
+use crate::utils::{
+    csv_tools::reader::{ BlobWriter, BlobWriterOps },
+    storage::storage::{ BackEnd, Storage },
+};
+
+pub const DEFAULT_CATALOGUE_PATH: &str = "db.db";
+/// How many times a write is rebased onto a newer snapshot before giving up.
+pub const MAX_COMMIT_ATTEMPTS: usize = 8;
+
+/// Builder for a [`LakeEngine`].
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// use std::path::PathBuf;
+/// use unakite::lake_engine::EngineOptions;
+/// use unakite::utils::storage::storage::Storage;
+///
+/// let engine = EngineOptions::new()
+///     .provider(Storage::LocalFileSystem { base_path: PathBuf::from("lake") })
+///     .catalogue(PathBuf::from("lake.db"))
+///     .attach_catalogue("scratch", PathBuf::from("scratch.db"))
+///     .build().await?;
+///
+/// engine.ingest(PathBuf::from("orders.csv")).await?;
+/// engine.sql("CREATE TABLE scratch.default.totals AS SELECT 1 AS total").await?;
+/// # Ok(())
+/// # }
+/// ```
 pub struct EngineOptions {
-    // Object Store Client
+    /// Object store the lake's data files are written to, required to build.
+    storage: Option<Storage>,
+
+    catalogue: CatalogueLocation,
+    /// Further catalogues by the name they are attached under.
+    attached: Vec<(String, CatalogueLocation)>,
+    ingest_defaults: BlobWriterOps,
 }
 
 impl EngineOptions {
-    pub fn provider(mut self) -> Self {
-        unimplemented!()
+    pub fn new() -> Self {
+        EngineOptions::default()
+    }
+
+    /// Object store the lake's data files are written to.
+    pub fn provider(mut self, storage: Storage) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// The catalogue attached as `lake`. Defaults to the SQLite catalogue `db.db`.
+    ///
+    /// A path is a SQLite file path or `file:` URI,
+    /// [`IN_MEMORY_CATALOGUE`](crate::catalogue::IN_MEMORY_CATALOGUE) keeps it in
+    /// memory for the engine's lifetime. [`CatalogueLocation::Rest`] uses an Iceberg
+    /// REST catalog instead.
+    pub fn catalogue(mut self, location: impl Into<CatalogueLocation>) -> Self {
+        self.catalogue = location.into();
+        self
+    }
+
+    /// Attaches another catalogue next to the default one, its tables resolve in SQL
+    /// as `<name>.<namespace>.<table>`.
+    ///
+    /// Tables created through an attached catalogue are written under `<name>/` in the
+    /// storage provider.
+    pub fn attach_catalogue(mut self, name: &str, location: impl Into<CatalogueLocation>) -> Self {
+        self.attached.push((name.to_string(), location.into()));
+        self
+    }
+
+    /// Options applied to every CSV ingested through the engine, the input path and
+    /// table name are ignored.
+    pub fn ingest_defaults(mut self, ops: BlobWriterOps) -> Self {
+        self.ingest_defaults = ops;
+        self
     }
 
-    pub fn build(mut self) -> anyhow::Result<LakeEngine> {
+    pub async fn build(self) -> anyhow::Result<LakeEngine> {
         // Instantiate object store client
+        let storage = self.storage.ok_or_else(||
+            anyhow::anyhow!("No storage provider configured")
+        )?;
 
-        unimplemented!()
+        let engine_state = storage.get_store().await?;
+        let blob_writer = self.ingest_defaults.buiild();
+
+        let mut catalogues = HashMap::new();
+        catalogues.insert(DEFAULT_CATALOGUE_NAME.to_string(), self.catalogue.open()?);
+
+        for (name, location) in self.attached {
+            if catalogues.contains_key(&name) {
+                return Err(anyhow::anyhow!("Catalogue '{}' is attached twice", name));
+            }
+
+            catalogues.insert(name, location.open()?);
+        }
+
+        let config = SessionConfig::new()
+            .with_create_default_catalog_and_schema(false)
+            .with_default_catalog_and_schema(DEFAULT_CATALOGUE_NAME, DEFAULT_NAMESPACE);
+
+        let ctx = SessionContext::new_with_config(config);
+        engine_state.register(&ctx);
+        for (name, catalogue) in &catalogues {
+            ctx.register_catalog(name, Arc::new(LakeCatalogProvider::new(catalogue.clone())));
+        }
+
+        Ok(LakeEngine {
+            blob_writer,
+            catalogues,
+            engine_state,
+            ctx,
+        })
     }
 }
 
+impl Default for EngineOptions {
+    fn default() -> Self {
+        EngineOptions {
+            storage: None,
+            catalogue: CatalogueLocation::Sqlite(PathBuf::from(DEFAULT_CATALOGUE_PATH)),
+            attached: Vec::new(),
+            ingest_defaults: BlobWriterOps::default(),
+        }
+    }
+}
+
+/// A table name resolved against the engine's attached catalogues.
+struct ResolvedTable<'a> {
+    catalogue_name: &'a str,
+    catalogue: &'a Arc<dyn Catalog + Send + Sync>,
+    namespace: String,
+    name: String,
+}
+
 pub struct LakeEngine {
     blob_writer: BlobWriter,
-    catalogue: RootCatalogue,
+    /// Attached catalogues by name, always including `lake`.
+    pub(crate) catalogues: HashMap<String, Arc<dyn Catalog + Send + Sync>>,
     engine_state: BackEnd,
+    ctx: SessionContext,
+}
+
+impl LakeEngine {
+    /// Ingests a CSV file into the lake using the engine's default ingest options.
+    pub async fn ingest(&self, input: PathBuf) -> anyhow::Result<TableMetadata> {
+        let writer = BlobWriter {
+            input,
+            glob: None,
+            table_name: None,
+            ..self.blob_writer.clone()
+        };
+
+        self.ingest_with(&writer).await
+    }
+
+    /// Ingests a CSV file, or every file matching the writer's glob pattern, described
+    /// by a fully configured writer as a new table.
+    ///
+    /// The table is named after the file's stem unless the writer names it, and is
+    /// partitioned by the writer's partition terms. Its files are written under a
+    /// new location first, then the table is catalogued with the inferred schema and
+    /// partition spec and the files as its first snapshot, in one go. If anything
+    /// fails the written files are deleted again, ingestion either yields a
+    /// queryable table or nothing.
+    ///
+    /// The files matching a glob are written in parallel, at most the writer's
+    /// concurrency at a time, and committed together in one snapshot.
+    pub async fn ingest_with(&self, writer: &BlobWriter) -> anyhow::Result<TableMetadata> {
+        let name = writer.table_name()?;
+        let table = self.resolve(&TableReference::from(name.as_str()))?;
+        let ident = TableIdent::new(&table.namespace, &table.name);
+        let catalogue = table.catalogue;
+
+        if catalogue.get_table_id(&table.namespace, &table.name)?.is_some() {
+            return Err(anyhow::anyhow!("Table '{}' already exists", name));
+        }
+
+        let inputs = writer.inputs()?;
+        let schema = (BlobWriter {
+            input: inputs[0].clone(),
+            ..writer.clone()
+        }).infer_schema()?;
+        let location = self.table_location(&table);
+
+        let created = self.create_ingested(&table, writer, inputs, &schema, &location).await;
+
+        if let Err(e) = created {
+            // The location is new, the only files there are the ones just written
+            let _ = self.engine_state.delete_all(&location).await;
+
+            return Err(e);
+        }
+
+        self.publish_committed(&table).await;
+
+        catalogue.load_table(&ident)
+    }
+
+    /// Writes the rows of every input file under `location`, at most the writer's
+    /// concurrency at a time. Files are read and encoded on blocking threads, the
+    /// tasks here only upload them.
+    ///
+    /// The first failure stops the writes still running, none of them uploads
+    /// anything once it is returned.
+    async fn write_inputs(
+        &self,
+        writer: &BlobWriter,
+        inputs: Vec<PathBuf>,
+        schema: &SchemaRef,
+        spec: &PartitionSpec,
+        location: &str,
+        schema_id: i64
+    ) -> anyhow::Result<Vec<DataFile>> {
+        let mut inputs = inputs.into_iter();
+        let mut writes = JoinSet::new();
+        let mut written = Vec::new();
+
+        loop {
+            while writes.len() < writer.concurrency.max(1) {
+                let Some(input) = inputs.next() else {
+                    break;
+                };
+
+                let writer = BlobWriter { input, ..writer.clone() };
+                let (schema, spec) = (schema.clone(), spec.clone());
+                let (store, location) = (self.engine_state.clone(), location.to_string());
+
+                writes.spawn(async move {
+                    writer.write_data_files(schema, &spec, &store, &location, schema_id).await
+                });
+            }
+
+            let Some(joined) = writes.join_next().await else {
+                return Ok(written);
+            };
+
+            match joined.map_err(anyhow::Error::from).and_then(|files| files) {
+                Ok(files) => written.extend(files),
+                Err(e) => {
+                    writes.shutdown().await;
+
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    /// Writes the files of an ingested table under `location` and creates the table
+    /// with them as its first snapshot.
+    ///
+    /// The files and manifests record the schema id the table is created with, the
+    /// catalogue's next one. If another table takes it first the manifests are
+    /// rewritten with the following id and the creation retried.
+    async fn create_ingested(
+        &self,
+        table: &ResolvedTable<'_>,
+        writer: &BlobWriter,
+        inputs: Vec<PathBuf>,
+        schema: &SchemaRef,
+        location: &str
+    ) -> anyhow::Result<i64> {
+        let catalogue = table.catalogue.as_ref();
+        let terms = writer.make_partiotion_on.clone().unwrap_or_default();
+        // Explicit schemas keep the caller's field ids
+        let columns = SchemaVec::from_numbered_arrow_schema(schema)?;
+        let new_table = LakeEngine::new_table(table, location, &columns, terms, BTreeMap::new());
+
+        let spec = PartitionSpec::from_terms(&new_table.partition_spec, &columns)?;
+        let mut schema_id = catalogue.next_schema_id()?;
+
+        let mut written = self
+            .write_inputs(writer, inputs, schema, &spec, location, schema_id).await?;
+
+        let state = self.ctx.state();
+
+        for _ in 0..MAX_COMMIT_ATTEMPTS {
+            for file in &mut written {
+                file.schema_id = schema_id;
+            }
+
+            let metadata = TableMetadata {
+                table_id: 0,
+                ident: TableIdent::new(&table.namespace, &table.name),
+                table_uuid: String::new(),
+                location: location.to_string(),
+                schema_id,
+                schema: columns.clone(),
+                partition_spec: new_table.partition_spec.clone(),
+                partition_specs: vec![spec.clone()],
+                default_spec_id: spec.spec_id,
+                properties: new_table.properties.clone(),
+                metadata_location: None,
+                current_snapshot: None,
+            };
+
+            let mut snapshot = Snapshot::next(0, None, Operation::Append, written.clone());
+            snapshot.snapshot_id = new_snapshot_id();
+            snapshot.manifest_list = Some(
+                write_manifests(&state, &metadata, &snapshot, None, &written).await?
+            );
+
+            match catalogue.create_table_with_snapshot(&new_table, schema_id, &snapshot) {
+                Ok(table_id) => {
+                    return Ok(table_id);
+                }
+                Err(e) if e.is::<CommitConflict>() => {
+                    schema_id = catalogue.next_schema_id()?;
+                }
+                Err(e) => {
+                    return Err(e);
+                }
+            }
+        }
+
+        Err(
+            anyhow::anyhow!(
+                "Gave up creating table '{}' after {} conflicting attempts",
+                table.name,
+                MAX_COMMIT_ATTEMPTS
+            )
+        )
+    }
+
+    /// Registers parquet files already present in the store as a catalogued table.
+    ///
+    /// `location` is the url of the directory holding the table's files, see [`BackEnd::url_for`].
+    /// The files found there are committed as the table's first snapshot. `name` may be
+    /// qualified with the catalogue to register the table in, e.g. `scratch.default.orders`.
+    pub async fn create_table(
+        &self,
+        name: &str,
+        location: &str,
+        schema: &Schema
+    ) -> anyhow::Result<Snapshot> {
+        let table = self.resolve(&TableReference::from(name))?;
+
+        let table_id = LakeEngine::register_table(
+            &table,
+            location,
+            schema,
+            Vec::new(),
+            BTreeMap::new()
+        )?;
+        let schema_id = table.catalogue.get_table_schema_id(&table_id)?;
+
+        let data_files = BlobWriter::scan_data_files(
+            &self.engine_state,
+            location,
+            schema_id
+        ).await?;
+
+        self.commit_files(&table, table_id, Operation::Append, data_files).await
+    }
+
+    /// Registers an existing Iceberg table, e.g. one written by Spark, as `name`.
+    ///
+    /// `location` is the table's location or the url of one of its `metadata.json`
+    /// files. The table's current schema, partition spec and properties are
+    /// catalogued and its current snapshot becomes the first one, its files are read
+    /// from its manifests. Earlier snapshots are not imported. If registering fails
+    /// nothing is left catalogued.
+    pub async fn register_iceberg_table(
+        &self,
+        name: &str,
+        location: &str
+    ) -> anyhow::Result<TableMetadata> {
+        let table = self.resolve(&TableReference::from(name))?;
+        let ident = TableIdent::new(&table.namespace, &table.name);
+
+        let (metadata_location, metadata) = read_table_metadata(&self.ctx.state(), location).await?;
+        let schema = metadata.current_schema()?;
+        let partition_spec = metadata.partition_specs
+            .iter()
+            .find(|spec| spec.spec_id == metadata.default_spec_id)
+            .map(|spec| spec.describe(&schema))
+            .unwrap_or_default();
+
+        let new_table = Table {
+            namespace: table.namespace.clone(),
+            table_name: table.name.clone(),
+            schema_bin: SchemaVec::serialize_schema(&schema),
+            url: metadata.location.clone(),
+            partition_spec,
+            properties: metadata.properties.clone(),
+        };
+
+        let table_id = match metadata.current_snapshot() {
+            Some(current) => {
+                let manifest_list = current.manifest_list.clone().ok_or_else(|| {
+                    anyhow::anyhow!("Snapshot {} has no manifest list", current.snapshot_id)
+                })?;
+
+                let operation = match current.summary.get("operation").map(String::as_str) {
+                    Some("append") => Operation::Append,
+                    _ => Operation::Overwrite,
+                };
+
+                let snapshot = Snapshot {
+                    snapshot_id: current.snapshot_id,
+                    table_id: 0,
+                    parent_snapshot_id: None,
+                    // Kept so later commits carry on from the table's own sequence
+                    sequence_number: current.sequence_number.max(1),
+                    operation,
+                    data_files: Vec::new(),
+                    manifest_list: Some(manifest_list),
+                    summary: current.summary.clone(),
+                    timestamp_ms: current.timestamp_ms,
+                };
+
+                LakeEngine::create_with_snapshot(&table, &new_table, snapshot)?
+            }
+            None => table.catalogue.create_sys_table(&new_table)?,
+        };
+
+        if let Err(e) = table.catalogue.set_metadata_location(&table_id, None, &metadata_location) {
+            table.catalogue.del_sys_table(table_id)?;
+
+            return Err(e);
+        }
+
+        table.catalogue.load_table(&ident)
+    }
+
+    /// Registers an existing Delta Lake table as `name`.
+    ///
+    /// `location` is the table's location, holding its `_delta_log/`. The log is
+    /// replayed from its last checkpoint, the table's current schema and
+    /// configuration are catalogued and its live files become the first snapshot,
+    /// numbered after the table's latest version so later writes continue its log.
+    /// Both are catalogued in one go, a table that fails to register leaves nothing
+    /// behind. Partitioned tables and files with deletion vectors are not supported.
+    pub async fn register_delta_table(
+        &self,
+        name: &str,
+        location: &str
+    ) -> anyhow::Result<TableMetadata> {
+        let table = self.resolve(&TableReference::from(name))?;
+        let ident = TableIdent::new(&table.namespace, &table.name);
+        let state = self.ctx.state();
+
+        let log = read_delta_log(&state, location).await?;
+
+        let new_table = Table {
+            namespace: table.namespace.clone(),
+            table_name: table.name.clone(),
+            schema_bin: SchemaVec::serialize_schema(&log.schema()?),
+            url: location.to_string(),
+            partition_spec: Vec::new(),
+            properties: log.properties(),
+        };
+
+        // The files get their schema id once the table is created
+        let data_files = log.data_files(&state, location, 0).await?;
+
+        let mut snapshot = Snapshot::next(0, None, Operation::Append, data_files);
+        snapshot.snapshot_id = new_snapshot_id();
+        snapshot.sequence_number = log.version + 1;
+        snapshot.timestamp_ms = log.timestamp_ms;
+
+        LakeEngine::create_with_snapshot(&table, &new_table, snapshot)?;
+
+        table.catalogue.load_table(&ident)
+    }
+
+    /// Reads a whole table as it was after its `version`-th write.
+    pub async fn sql_at(&self, table_name: &str, version: i64) -> anyhow::Result<Vec<RecordBatch>> {
+        let versioned = TableVersion::Version(version).qualify_reference(
+            &TableReference::from(table_name)
+        );
+
+        self.sql(&format!("SELECT * FROM {}", versioned.to_quoted_string())).await
+    }
+
+    /// Every snapshot of a table, oldest first.
+    pub fn snapshots(&self, table_name: &str) -> anyhow::Result<Vec<Snapshot>> {
+        let (table, table_id) = self.table_id(&TableReference::from(table_name))?;
+
+        table.catalogue.list_snapshots(&table_id)
+    }
+
+    /// Every schema version of a table, oldest first.
+    pub fn schema_versions(&self, table_name: &str) -> anyhow::Result<Vec<SchemaVersion>> {
+        let (table, table_id) = self.table_id(&TableReference::from(table_name))?;
+
+        table.catalogue.list_schema_versions(&table_id)
+    }
+
+    /// The catalogue attached under `name`, e.g. to manage its namespaces.
+    pub fn catalog(&self, name: &str) -> anyhow::Result<Arc<dyn Catalog + Send + Sync>> {
+        let catalogue = self.catalogues
+            .get(name)
+            .ok_or_else(|| anyhow::anyhow!("Catalogue '{}' not found", name))?;
+
+        Ok(catalogue.clone())
+    }
+
+    /// Looks up the catalogue a table reference points into, unqualified names
+    /// resolve in `lake.default`.
+    fn resolve(&self, reference: &TableReference) -> anyhow::Result<ResolvedTable<'_>> {
+        let (catalogue_name, catalogue) = self.catalogues
+            .get_key_value(reference.catalog().unwrap_or(DEFAULT_CATALOGUE_NAME))
+            .ok_or_else(|| anyhow::anyhow!("Catalogue for table '{}' not found", reference))?;
+
+        Ok(ResolvedTable {
+            catalogue_name,
+            catalogue,
+            namespace: reference.schema().unwrap_or(DEFAULT_NAMESPACE).to_string(),
+            name: reference.table().to_string(),
+        })
+    }
+
+    /// Where a new table's files are written: `<table>-<uuid>/` for `lake.default`,
+    /// other catalogues and namespaces add directories, e.g.
+    /// `scratch/team/analytics/<table>-<uuid>/`.
+    ///
+    /// The suffix keeps a table's files apart from a namespace of the same name and
+    /// from earlier tables that had its name.
+    fn table_location(&self, table: &ResolvedTable) -> String {
+        let mut path = String::new();
+
+        if table.catalogue_name != DEFAULT_CATALOGUE_NAME {
+            path.push_str(&format!("{}/", table.catalogue_name));
+        }
+
+        if table.namespace != DEFAULT_NAMESPACE {
+            path.push_str(&format!("{}/", table.namespace.replace('.', "/")));
+        }
+
+        self.engine_state.url_for(&format!("{}{}-{}/", path, table.name, Uuid::new_v4().simple()))
+    }
+
+    /// Creates a table with `snapshot` as its first commit, its files get the schema
+    /// id the table is created with. If another schema takes the id first the
+    /// creation is retried with the next one.
+    fn create_with_snapshot(
+        table: &ResolvedTable,
+        new_table: &Table,
+        mut snapshot: Snapshot
+    ) -> anyhow::Result<i64> {
+        let catalogue = table.catalogue.as_ref();
+
+        for _ in 0..MAX_COMMIT_ATTEMPTS {
+            let schema_id = catalogue.next_schema_id()?;
+
+            for file in &mut snapshot.data_files {
+                file.schema_id = schema_id;
+            }
+
+            match catalogue.create_table_with_snapshot(new_table, schema_id, &snapshot) {
+                Ok(table_id) => {
+                    return Ok(table_id);
+                }
+                Err(e) if e.is::<CommitConflict>() => {
+                    continue;
+                }
+                Err(e) => {
+                    return Err(e);
+                }
+            }
+        }
+
+        Err(
+            anyhow::anyhow!(
+                "Gave up creating table '{}' after {} conflicting attempts",
+                table.name,
+                MAX_COMMIT_ATTEMPTS
+            )
+        )
+    }
+
+    fn register_table(
+        table: &ResolvedTable,
+        location: &str,
+        schema: &Schema,
+        partition_spec: Vec<String>,
+        properties: BTreeMap<String, String>
+    ) -> anyhow::Result<i64> {
+        let columns = SchemaVec::from_arrow_schema(schema);
+
+        table.catalogue.create_sys_table(
+            &LakeEngine::new_table(table, location, &columns, partition_spec, properties)
+        )
+    }
+
+    fn new_table(
+        table: &ResolvedTable,
+        location: &str,
+        columns: &SchemaVec,
+        partition_spec: Vec<String>,
+        properties: BTreeMap<String, String>
+    ) -> Table {
+        Table {
+            namespace: table.namespace.clone(),
+            table_name: table.name.clone(),
+            schema_bin: SchemaVec::serialize_schema(columns),
+            url: location.to_string(),
+            partition_spec,
+            properties,
+        }
+    }
+
+    fn table_id(&self, reference: &TableReference) -> anyhow::Result<(ResolvedTable<'_>, i64)> {
+        let table = self.resolve(reference)?;
+
+        let table_id = table.catalogue
+            .get_table_id(&table.namespace, &table.name)?
+            .ok_or_else(|| anyhow::anyhow!("Table '{}' not found", reference))?;
+
+        Ok((table, table_id))
+    }
+
+    /// Commits newly written files as the table's next snapshot and publishes it in
+    /// the table's format: a new Iceberg `metadata.json` or a Delta log commit.
+    ///
+    /// Iceberg manifests and the Delta log commit are written before the snapshot is
+    /// committed, a log commit the catalogue refuses is removed again. Losing the
+    /// compare-and-swap on the table's current snapshot, or the log's version, means
+    /// another writer committed first, the snapshot is rebuilt on top of theirs and
+    /// retried.
+    async fn commit_files(
+        &self,
+        table: &ResolvedTable<'_>,
+        table_id: i64,
+        operation: Operation,
+        added: Vec<DataFile>
+    ) -> anyhow::Result<Snapshot> {
+        let catalogue = table.catalogue.as_ref();
+        let ident = TableIdent::new(&table.namespace, &table.name);
+        let state = self.ctx.state();
+
+        let metadata = catalogue.load_table(&ident)?;
+        let format = metadata.format()?;
+        let snapshot_id = new_snapshot_id();
+
+        if format == TableFormat::Delta {
+            check_delta_write(&metadata, operation)?;
+        }
+
+        for _ in 0..MAX_COMMIT_ATTEMPTS {
+            let mut current = catalogue.current_snapshot(&table_id)?;
+
+            // Registered tables list their files in manifests only, appends list them all
+            if let Some(parent) = current.as_mut() {
+                parent.data_files = snapshot_files(&state, parent, metadata.schema_id).await?;
+            }
+
+            let mut snapshot = Snapshot::next(table_id, current.as_ref(), operation, added.clone());
+            snapshot.snapshot_id = snapshot_id;
+
+            let log_commit = match format {
+                TableFormat::Iceberg => {
+                    snapshot.manifest_list = Some(
+                        write_manifests(&state, &metadata, &snapshot, current.as_ref(), &added)
+                            .await?
+                    );
+
+                    None
+                }
+                TableFormat::Delta => {
+                    match
+                        write_delta_commit(
+                            &state,
+                            &metadata,
+                            &snapshot,
+                            current.as_ref(),
+                            &added
+                        ).await
+                    {
+                        Ok(url) => Some(url),
+                        Err(e) if e.is::<CommitConflict>() => {
+                            continue;
+                        }
+                        Err(e) => {
+                            return Err(e);
+                        }
+                    }
+                }
+            };
+
+            let committed = catalogue.commit_snapshot(&snapshot);
+
+            if let (Err(_), Some(url)) = (&committed, &log_commit) {
+                remove_delta_commit(&state, url).await?;
+            }
+
+            match committed {
+                Ok(committed) => {
+                    if format == TableFormat::Iceberg {
+                        self.publish_committed(table).await;
+                    }
+
+                    return Ok(committed);
+                }
+                Err(e) if e.is::<CommitConflict>() => {
+                    continue;
+                }
+                Err(e) => {
+                    return Err(e);
+                }
+            }
+        }
+
+        Err(
+            anyhow::anyhow!(
+                "Gave up committing to table {} after {} conflicting attempts",
+                table_id,
+                MAX_COMMIT_ATTEMPTS
+            )
+        )
+    }
+
+    /// Runs a SQL query against the catalogue and collects the results.
+    ///
+    /// Tables resolve as `<catalogue>.<namespace>.<table>`, unqualified parts default
+    /// to `lake` and `default`. `CREATE SCHEMA` and `DROP SCHEMA` manage namespaces.
+    ///
+    /// `CREATE TABLE .. AS SELECT` and `INSERT INTO .. SELECT` are written through
+    /// the lake write path into the backend store and recorded in the catalogue.
+    ///
+    /// Tables can be read as of an earlier write with `FOR VERSION AS OF <n>` or
+    /// `FOR TIMESTAMP AS OF '<timestamp>'` after the table name.
+    ///
+    /// `ALTER TABLE` adds, drops, renames and widens columns by appending a new
+    /// schema version, existing data files are left untouched.
+    ///
+    /// `CREATE TABLE` accepts `TBLPROPERTIES (..)` or `WITH (..)`, which are stored as
+    /// the table's properties. `'table-format' = 'delta'` publishes the table as a
+    /// Delta Lake table instead of an Iceberg one. `'write.target-file-size-bytes'`
+    /// sets the size written data files are rolled over at.
+    ///
+    /// `CREATE TABLE .. PARTITION BY (day(ts), bucket(16, user_id)) AS SELECT` sets
+    /// the table's partition spec, every write splits its files by it. Transforms are
+    /// `year`, `month`, `day`, `hour`, `bucket(n, ..)` and `truncate(width, ..)`.
+    ///
+    /// `ALTER TABLE .. ADD PARTITION FIELD hour(ts)`, `.. DROP PARTITION FIELD day(ts)`
+    /// and `.. REPLACE PARTITION FIELD day(ts) WITH hour(ts)` change the spec later
+    /// writes use, files already written keep theirs.
+    pub async fn sql(&self, sql: &str) -> anyhow::Result<Vec<RecordBatch>> {
+        if let Some(change) = PartitionChange::from_sql(sql) {
+            let (table_name, change) = change?;
+            self.alter_partitioning(&table_name, &[change]).await?;
+
+            return Ok(vec![]);
+        }
+
+        let state = self.ctx.state();
+        let recursion_limit = state.config().options().sql_parser.recursion_limit;
+        let statement = parse_time_travel(sql, recursion_limit)?;
+
+        if let Statement::Statement(statement) = &statement {
+            match statement.as_ref() {
+                SqlStatement::AlterTable { name, operations, .. } => {
+                    return self.alter_table(name, operations).await;
+                }
+                SqlStatement::CreateTable(create) if
+                    !create.table_properties.is_empty() ||
+                    !create.with_options.is_empty() ||
+                    create.partition_by.is_some()
+                => {
+                    return self.create_table_with_options(create.clone()).await;
+                }
+                _ => {}
+            }
+        }
+
+        let plan = state.statement_to_plan(statement).await?;
+
+        match plan {
+            LogicalPlan::Ddl(DdlStatement::CreateMemoryTable(create)) => {
+                self.create_table_as(create, Vec::new(), BTreeMap::new()).await
+            }
+            LogicalPlan::Dml(insert @ DmlStatement { op: WriteOp::Insert(_), .. }) => {
+                self.insert_into(insert).await
+            }
+            plan => {
+                let batches = self.ctx.execute_logical_plan(plan).await?.collect().await?;
+
+                Ok(batches)
+            }
+        }
+    }
+
+    /// Plans a `CREATE TABLE` without the properties and partitioning DataFusion does
+    /// not plan, then creates the table with them.
+    async fn create_table_with_options(
+        &self,
+        mut create: CreateTable
+    ) -> anyhow::Result<Vec<RecordBatch>> {
+        let options = create.table_properties.drain(..).chain(create.with_options.drain(..));
+        let properties = LakeEngine::table_properties(options)?;
+
+        let partition_spec = match create.partition_by.take() {
+            Some(partition_by) => LakeEngine::partition_terms(&partition_by)?,
+            None => Vec::new(),
+        };
+
+        let statement = Statement::Statement(Box::new(SqlStatement::CreateTable(create)));
+        let plan = self.ctx.state().statement_to_plan(statement).await?;
+
+        match plan {
+            LogicalPlan::Ddl(DdlStatement::CreateMemoryTable(create)) => {
+                self.create_table_as(create, partition_spec, properties).await
+            }
+            _ => {
+                Err(
+                    anyhow::anyhow!(
+                        "Table properties and partitioning are only supported by CREATE TABLE"
+                    )
+                )
+            }
+        }
+    }
+
+    /// The partition terms of `PARTITION BY (day(ts), bucket(16, user_id))`.
+    fn partition_terms(partition_by: &SqlExpr) -> anyhow::Result<Vec<String>> {
+        let exprs = match partition_by {
+            SqlExpr::Tuple(exprs) => exprs.iter().collect(),
+            SqlExpr::Nested(expr) => vec![expr.as_ref()],
+            expr => vec![expr],
+        };
+
+        exprs
+            .into_iter()
+            .map(|expr| {
+                match expr {
+                    SqlExpr::Identifier(ident) => Ok(normalize(ident)),
+                    SqlExpr::Function(function) => {
+                        let FunctionArguments::List(list) = &function.args else {
+                            return Err(anyhow::anyhow!("Unsupported partition term '{}'", expr));
+                        };
+
+                        let arguments = list.args
+                            .iter()
+                            .map(|argument| {
+                                match argument {
+                                    FunctionArg::Unnamed(FunctionArgExpr::Expr(argument)) => {
+                                        match argument {
+                                            SqlExpr::Identifier(ident) => Some(normalize(ident)),
+                                            SqlExpr::Value(value) => Some(value.to_string()),
+                                            _ => None,
+                                        }
+                                    }
+                                    _ => None,
+                                }
+                            })
+                            .collect::<Option<Vec<_>>>()
+                            .ok_or_else(|| {
+                                anyhow::anyhow!("Unsupported partition term '{}'", expr)
+                            })?;
+
+                        Ok(format!("{}({})", function.name, arguments.join(", ")))
+                    }
+                    other => Err(anyhow::anyhow!("Unsupported partition term '{}'", other)),
+                }
+            })
+            .collect()
+    }
+
+    /// The properties set by `TBLPROPERTIES ('key' = 'value', ..)`.
+    fn table_properties(
+        options: impl Iterator<Item = SqlOption>
+    ) -> anyhow::Result<BTreeMap<String, String>> {
+        options
+            .map(|option| {
+                let SqlOption::KeyValue { key, value } = option else {
+                    return Err(anyhow::anyhow!("Unsupported table option '{}'", option));
+                };
+
+                let value = match value {
+                    SqlExpr::Value(value) => {
+                        match value.value {
+                            | SqlValue::SingleQuotedString(value)
+                            | SqlValue::DoubleQuotedString(value) => value,
+                            other => other.to_string(),
+                        }
+                    }
+                    SqlExpr::Identifier(ident) => ident.value,
+                    other => {
+                        return Err(anyhow::anyhow!("Unsupported value for '{}': {}", key, other));
+                    }
+                };
+
+                Ok((key.value, value))
+            })
+            .collect()
+    }
+
+    async fn create_table_as(
+        &self,
+        create: CreateMemoryTable,
+        partition_spec: Vec<String>,
+        mut properties: BTreeMap<String, String>
+    ) -> anyhow::Result<Vec<RecordBatch>> {
+        let table = self.resolve(&create.name)?;
+        let catalogue = table.catalogue;
+
+        if create.or_replace {
+            return Err(anyhow::anyhow!("CREATE OR REPLACE TABLE is not supported"));
+        }
+
+        let format = properties
+            .get(TABLE_FORMAT_PROPERTY)
+            .map_or(Ok(TableFormat::Iceberg), |format| TableFormat::parse(format))?;
+
+        if format == TableFormat::Delta {
+            if !partition_spec.is_empty() {
+                return Err(anyhow::anyhow!("Delta tables can't be partitioned"));
+            }
+
+            properties = delta_table_properties(properties);
+        }
+
+        let location = self.table_location(&table);
+
+        let df = DataFrame::new(self.ctx.state(), create.input.as_ref().clone());
+        let schema = Arc::new(df.schema().as_arrow().clone());
+
+        // The name is only claimed here, by the same transaction that creates the table
+        let table_id = match
+            LakeEngine::register_table(&table, &location, &schema, partition_spec, properties)
+        {
+            Ok(table_id) => table_id,
+            Err(e) if create.if_not_exists && e.is::<TableExists>() => {
+                return Ok(vec![]);
+            }
+            Err(e) => {
+                return Err(e);
+            }
+        };
+
+        let created = async {
+            let metadata = catalogue.load_table(&TableIdent::new(&table.namespace, &table.name))?;
+            let spec = metadata.current_spec();
+
+            // Written with the catalogued schema so the files carry its field ids
+            let schema = Arc::new(metadata.schema.to_arrow_schema());
+            let stream = df.execute_stream().await?;
+
+            let written = BlobWriter::write_stream(
+                &self.engine_state,
+                &location,
+                metadata.schema_id,
+                schema,
+                &spec,
+                WriteOptions::from_properties(&metadata.properties)?,
+                stream
+            ).await?;
+
+            self.commit_files(&table, table_id, Operation::Append, written).await
+        }.await;
+
+        if let Err(e) = created {
+            // The table never became visible with data, don't leave an empty shell behind.
+            // The location is new, the only files there are the ones just written
+            catalogue.del_sys_table(table_id)?;
+            let _ = self.engine_state.delete_all(&location).await;
+
+            return Err(e);
+        }
+
+        Ok(vec![])
+    }
+
+    async fn insert_into(&self, insert: DmlStatement) -> anyhow::Result<Vec<RecordBatch>> {
+        let operation = match insert.op {
+            WriteOp::Insert(InsertOp::Append) => Operation::Append,
+            WriteOp::Insert(InsertOp::Overwrite) => Operation::Overwrite,
+            op => {
+                return Err(anyhow::anyhow!("{} is not supported", op.name()));
+            }
+        };
+
+        let (table, table_id) = self.table_id(&insert.table_name)?;
+        let metadata = table.catalogue.load_table(&TableIdent::new(&table.namespace, &table.name))?;
+        let spec = metadata.current_spec();
+
+        let df = DataFrame::new(self.ctx.state(), insert.input.as_ref().clone());
+
+        let written = BlobWriter::write_stream(
+            &self.engine_state,
+            &metadata.location,
+            metadata.schema_id,
+            Arc::new(metadata.schema.to_arrow_schema()),
+            &spec,
+            WriteOptions::from_properties(&metadata.properties)?,
+            df.execute_stream().await?
+        ).await?;
+        let row_count = written
+            .iter()
+            .map(|file| file.row_count)
+            .sum();
+        let paths = written
+            .iter()
+            .map(|file| file.file_path.clone())
+            .collect::<Vec<_>>();
+
+        if let Err(e) = self.commit_files(&table, table_id, operation, written).await {
+            // The files were written for this commit alone, nothing else refers to them
+            let _ = self.engine_state.delete_each(&paths).await;
+
+            return Err(e);
+        }
+
+        Ok(vec![LakeEngine::count_batch(row_count)?])
+    }
+
+    async fn alter_table(
+        &self,
+        name: &ObjectName,
+        operations: &[AlterTableOperation]
+    ) -> anyhow::Result<Vec<RecordBatch>> {
+        let reference = object_name_to_table_reference(name.clone(), true)?;
+        let (table, table_id) = self.table_id(&reference)?;
+
+        let changes = operations
+            .iter()
+            .map(SchemaChange::from_sql)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        LakeEngine::evolve_schema(table.catalogue.as_ref(), table_id, &changes)?;
+        self.publish_committed(&table).await;
+
+        Ok(vec![])
+    }
+
+    /// Applies schema changes to a table as a single new schema version.
+    pub async fn alter_schema(
+        &self,
+        table_name: &str,
+        changes: &[SchemaChange]
+    ) -> anyhow::Result<SchemaVersion> {
+        let (table, table_id) = self.table_id(&TableReference::from(table_name))?;
+
+        let version = LakeEngine::evolve_schema(table.catalogue.as_ref(), table_id, changes)?;
+        self.publish_committed(&table).await;
+
+        Ok(version)
+    }
+
+    fn evolve_schema(
+        catalogue: &dyn Catalog,
+        table_id: i64,
+        changes: &[SchemaChange]
+    ) -> anyhow::Result<SchemaVersion> {
+        let versions = catalogue.list_schema_versions(&table_id)?;
+        let last_field_id = versions
+            .iter()
+            .map(|version| version.schema.last_field_id())
+            .max()
+            .unwrap_or_default();
+
+        let expected_schema_id = catalogue.get_table_schema_id(&table_id)?;
+        let current = catalogue.get_table_schema(&table_id)?;
+
+        let mut next = current;
+        for change in changes {
+            next = next.evolve(change, last_field_id)?;
+        }
+
+        catalogue.commit_schema(&table_id, expected_schema_id, &next)
+    }
+
+    /// Applies partition spec changes to a table as a single new spec.
+    ///
+    /// Data files already written keep the spec they were written with and are
+    /// read and pruned through it, only later writes use the new spec.
+    pub async fn alter_partitioning(
+        &self,
+        table_name: &str,
+        changes: &[PartitionChange]
+    ) -> anyhow::Result<PartitionSpec> {
+        let table = self.resolve(&TableReference::from(table_name))?;
+        let catalogue = table.catalogue.as_ref();
+
+        let metadata = catalogue.load_table(&TableIdent::new(&table.namespace, &table.name))?;
+
+        if metadata.format()? == TableFormat::Delta {
+            return Err(anyhow::anyhow!("Delta tables can't be partitioned"));
+        }
+
+        let mut terms = metadata.partition_spec.clone();
+        for change in changes {
+            terms = change.apply(&terms, &metadata.schema)?;
+        }
+
+        let spec = PartitionSpec::evolve(&metadata.partition_specs, &terms, &metadata.schema)?;
+
+        let spec = catalogue.commit_partition_spec(
+            &metadata.table_id,
+            metadata.default_spec_id,
+            &spec
+        )?;
+        self.publish_committed(&table).await;
+
+        Ok(spec)
+    }
+
+    /// Sets `updates` and removes `removals` from a table's properties, returning the
+    /// resulting properties.
+    pub async fn update_table_properties(
+        &self,
+        table_name: &str,
+        updates: &BTreeMap<String, String>,
+        removals: &[String]
+    ) -> anyhow::Result<BTreeMap<String, String>> {
+        let (table, table_id) = self.table_id(&TableReference::from(table_name))?;
+
+        let properties = table.catalogue.update_table_properties(&table_id, updates, removals)?;
+        self.publish_committed(&table).await;
+
+        Ok(properties)
+    }
+
+    /// Writes the current state of an Iceberg table as its next `metadata.json` and
+    /// returns its url.
+    ///
+    /// Every change to a table publishes it, this retries a publish that failed after
+    /// its change was committed. Delta tables publish through their log and REST
+    /// catalogs write their tables' metadata themselves, `None` is returned for both.
+    pub async fn publish_metadata(&self, table_name: &str) -> anyhow::Result<Option<String>> {
+        let table = self.resolve(&TableReference::from(table_name))?;
+
+        self.publish(&table).await
+    }
+
+    async fn publish(&self, table: &ResolvedTable<'_>) -> anyhow::Result<Option<String>> {
+        let catalogue = table.catalogue.as_ref();
+        let ident = TableIdent::new(&table.namespace, &table.name);
+
+        if catalogue.writes_table_metadata() {
+            return Ok(None);
+        }
+
+        if catalogue.load_table(&ident)?.format()? != TableFormat::Iceberg {
+            return Ok(None);
+        }
+
+        Ok(Some(write_table_metadata(&self.ctx.state(), catalogue, &ident).await?))
+    }
+
+    /// Publishes a table after a change to it was committed. The change stands when
+    /// publishing fails, the table's previous `metadata.json` stays current until the
+    /// next change or [`LakeEngine::publish_metadata`] publishes it again.
+    async fn publish_committed(&self, table: &ResolvedTable<'_>) {
+        let _ = self.publish(table).await;
+    }
+
+    /// The single `count` row DataFusion returns for DML statements.
+    fn count_batch(count: u64) -> anyhow::Result<RecordBatch> {
+        let schema = Schema::new(vec![Field::new("count", DataType::UInt64, false)]);
+
+        Ok(RecordBatch::try_new(Arc::new(schema), vec![Arc::new(UInt64Array::from(vec![count]))])?)
+    }
+
+    pub fn back_end(&self) -> &BackEnd {
+        &self.engine_state
+    }
 }
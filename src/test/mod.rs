@@ -1,16 +1,99 @@
 #[cfg(test)]
 mod tests {
+    use std::{ collections::{ BTreeMap, HashMap }, fs, path::PathBuf, sync::Arc };
+
+    use apache_avro::{ types::Value as AvroValue, Schema as AvroSchema, Writer as AvroWriter };
+    use arrow_array::{
+        ArrayRef,
+        Date32Array,
+        Float64Array,
+        Int64Array,
+        RecordBatch,
+        StringArray,
+        TimestampMicrosecondArray,
+        UInt64Array,
+    };
+    use arrow_schema::{ DataType, Field, Schema, TimeUnit };
+    use axum::{ http::{ HeaderMap, StatusCode }, routing::get, Json, Router };
+    use datafusion::common::ScalarValue;
+    use object_store::path::Path;
+    use parquet::arrow::{ arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter };
+    use serde_json::{ json, Value };
+
     use crate::catalogue::{
         catalogue_storage::Catalog,
-        tables::{ Column, SchemaVec, Table },
+        delta::DELTA_TABLE_ID_PROPERTY,
+        evolution::PartitionChange,
+        partitioning::Transform,
+        rest::server::RestCatalogServer,
+        provider::DEFAULT_CATALOGUE_NAME,
+        snapshots::{ CommitConflict, DataFile, Operation, Snapshot },
+        time_travel::{ parse_time_travel, TableVersion },
+        tables::{ Column, SchemaVec, Table, TableExists, TableFormat, TableIdent, TableMetadata },
+        CatalogueLocation,
         RootCatalogue,
+        IN_MEMORY_CATALOGUE,
+    };
+    use crate::lake_engine::EngineOptions;
+    use crate::utils::{
+        csv_tools::reader::{ BlobWriter, BlobWriterOps },
+        storage::storage::Storage,
     };
 
+    /// The directory of the one table named `name` directly under `parent`.
+    fn table_dir(parent: &std::path::Path, name: &str) -> PathBuf {
+        let prefix = format!("{}-", name);
+        let mut dirs: Vec<PathBuf> = fs::read_dir(parent)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.file_name().unwrap().to_string_lossy().starts_with(&prefix))
+            .collect();
+        assert_eq!(dirs.len(), 1, "expected one directory for {} in {:?}", name, parent);
+        dirs.pop().unwrap()
+    }
+
+    /// Fresh scratch directory under the system temp dir, unique per test.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("unakite-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Every file below `dir`, at any depth.
+    fn files_under(dir: &std::path::Path) -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .map(|entry| entry.unwrap().path())
+            .flat_map(|path| if path.is_dir() { files_under(&path) } else { vec![path] })
+            .collect()
+    }
+
+    fn write_avro(path: &std::path::Path, schema: &str, records: Vec<AvroValue>) {
+        let schema = AvroSchema::parse_str(schema).unwrap();
+        let mut writer = AvroWriter::new(&schema, Vec::new()).unwrap();
+        for record in records {
+            writer.append_value(record).unwrap();
+        }
+        fs::write(path, writer.into_inner().unwrap()).unwrap();
+    }
+
+    fn write_orders_csv(dir: &std::path::Path) -> PathBuf {
+        let input = dir.join("orders.csv");
+        fs::write(
+            &input,
+            "id,region,amount\n1,east,10\n2,west,20\n3,east,30\n4,north,40\n5,west,50\n6,east,60\n"
+        ).unwrap();
+        input
+    }
+
     #[test]
     fn schema_serde_works() {
         let mut schema = SchemaVec::new();
 
         schema.add(Column {
+            field_id: 1,
             datatype: arrow_schema::DataType::Int32,
             name: String::from("Test"),
             nullable: false,
@@ -19,6 +102,7 @@ mod tests {
         });
 
         schema.add(Column {
+            field_id: 2,
             datatype: arrow_schema::DataType::Int32,
             name: String::from("Test"),
             nullable: true,
@@ -27,19 +111,119 @@ mod tests {
         });
 
         let schema_bin = SchemaVec::serialize_schema(&schema);
-        let schema_copy = SchemaVec::de_serialize_schema(schema_bin);
+        let schema_copy = SchemaVec::de_serialize_schema(schema_bin).unwrap();
 
         assert_eq!(schema_copy, schema)
     }
 
+    /// A schema blob as written before columns had field ids, one Int64 column per name.
+    fn legacy_schema_blob(names: &[&str]) -> Vec<u8> {
+        #[derive(serde::Serialize)]
+        struct LegacyColumn {
+            name: String,
+            datatype: DataType,
+            nullable: bool,
+            unique: bool,
+            references: Option<String>,
+        }
+
+        let mut blob = Vec::new();
+        for name in names {
+            let column = LegacyColumn {
+                name: name.to_string(),
+                datatype: DataType::Int64,
+                nullable: true,
+                unique: false,
+                references: None,
+            };
+            let encoded = bincode::serde
+                ::encode_to_vec(&column, bincode::config::standard())
+                .unwrap();
+            blob.extend_from_slice(&(encoded.len() as u32).to_le_bytes());
+            blob.extend_from_slice(&encoded);
+        }
+        blob
+    }
+
+    #[test]
+    fn schema_serde_reads_blobs_without_field_ids() {
+        let schema = SchemaVec::de_serialize_schema(legacy_schema_blob(&["id", "region"])).unwrap();
+        let ids: Vec<(i32, &str)> = schema.columns
+            .iter()
+            .map(|column| (column.field_id, column.name.as_str()))
+            .collect();
+        assert_eq!(ids, vec![(1, "id"), (2, "region")]);
+
+        assert!(SchemaVec::de_serialize_schema(vec![1, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn catalogue_migrates_tables_written_before_namespaces() {
+        let dir = scratch_dir("catalogue_migrates_tables_written_before_namespaces");
+        let db_path = dir.join("db.db");
+        {
+            let conn = rusqlite::Connection::open(&db_path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE sys_tables (
+                    table_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+                    table_name TEXT NOT NULL,
+                    table_url_string TEXT NULL,
+                    schema_id INTEGER NOT NULL,
+                    partition_string TEXT NULL,
+                    created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (schema_id) REFERENCES sys_schemas(schema_id)
+                ) STRICT;
+                CREATE TABLE sys_schemas (
+                    schema_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+                    schema_bin BLOB NOT NULL,
+                    versions TEXT NULL,
+                    table_name TEXT NOT NULL,
+                    FOREIGN KEY (table_name) REFERENCES sys_tables(table_name)
+                ) STRICT;"
+            ).unwrap();
+            // An older table hidden by the one created after it under the same name
+            for (schema_id, columns) in [(1, vec!["id"]), (2, vec!["id", "total"])] {
+                conn.execute(
+                    "INSERT INTO sys_schemas (table_name, schema_bin) VALUES ('orders', ?)",
+                    [legacy_schema_blob(&columns)]
+                ).unwrap();
+                conn.execute(
+                    "INSERT INTO sys_tables (table_name, schema_id) VALUES ('orders', ?)",
+                    [schema_id]
+                ).unwrap();
+            }
+        }
+
+        for _ in 0..2 {
+            let catalogue = RootCatalogue::start(db_path.clone()).unwrap();
+            let table_id = catalogue.get_table_id("default", "orders").unwrap().unwrap();
+            let schema = catalogue.get_table_schema(&table_id).unwrap();
+            assert_eq!(schema.column_by_name("total").unwrap().field_id, 2);
+
+            let versions = catalogue.list_schema_versions(&table_id).unwrap();
+            assert_eq!(versions.len(), 1);
+
+            let hidden = catalogue.get_table_id("default", "orders_1").unwrap().unwrap();
+            assert_ne!(hidden, table_id);
+            assert!(catalogue.get_table_schema(&hidden).unwrap().column_by_name("total").is_none());
+        }
+
+        let conn = rusqlite::Connection::open(&db_path).unwrap();
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(version, 2);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
     #[test]
     fn catalogue_run_start() {
-        let catalogue = RootCatalogue::start().unwrap();
+        let catalogue = RootCatalogue::in_memory().unwrap();
         let conn = catalogue.db.get();
         assert!(conn.is_ok());
 
         let mut schema_one = SchemaVec::new();
         schema_one.add(Column {
+            field_id: 1,
             datatype: arrow_schema::DataType::Int32,
             name: String::from("Test"),
             nullable: false,
@@ -48,6 +232,7 @@ mod tests {
         });
 
         schema_one.add(Column {
+            field_id: 2,
             datatype: arrow_schema::DataType::Int16,
             name: String::from("West"),
             nullable: true,
@@ -58,6 +243,7 @@ mod tests {
         let mut schema_two = SchemaVec::new();
 
         schema_two.add(Column {
+            field_id: 1,
             datatype: arrow_schema::DataType::Int32,
             name: String::from("Test"),
             nullable: true,
@@ -68,10 +254,13 @@ mod tests {
         catalogue
             .create_sys_table(
                 &(Table {
+                    namespace: String::from("default"),
                     table_name: String::from("Table"),
                     schema_bin: SchemaVec::serialize_schema(&schema_one),
 
                     url: String::from("db://Table"),
+                    partition_spec: Vec::new(),
+                    properties: BTreeMap::new(),
                 })
             )
             .unwrap();
@@ -79,15 +268,2249 @@ mod tests {
         catalogue
             .create_sys_table(
                 &(Table {
+                    namespace: String::from("default"),
                     table_name: String::from("Table_two"),
                     schema_bin: SchemaVec::serialize_schema(&schema_two),
                     url: String::from("db://Table_two"),
+                    partition_spec: Vec::new(),
+                    properties: BTreeMap::new(),
                 })
             )
             .unwrap();
 
-        catalogue.list_tables_schemas().unwrap();
+        let duplicate = catalogue
+            .create_sys_table(
+                &(Table {
+                    namespace: String::from("default"),
+                    table_name: String::from("Table"),
+                    schema_bin: SchemaVec::serialize_schema(&schema_two),
+                    url: String::from("db://Table_three"),
+                    partition_spec: Vec::new(),
+                    properties: BTreeMap::new(),
+                })
+            )
+            .unwrap_err();
+        assert!(duplicate.is::<TableExists>());
+
+        assert_eq!(
+            catalogue.list_tables("default").unwrap(),
+            vec![TableIdent::new("default", "Table"), TableIdent::new("default", "Table_two")]
+        );
 
         let _ = catalogue.destroy();
     }
+
+    #[tokio::test]
+    async fn engine_build_and_ingest() {
+        let dir = scratch_dir("engine_build_and_ingest");
+        let input = write_orders_csv(&dir);
+
+        let engine = EngineOptions::new()
+            .provider(Storage::LocalFileSystem { base_path: dir.join("lake") })
+            .catalogue(dir.join("catalogue.db"))
+            .build().await
+            .unwrap();
+
+        let orders = engine.ingest(input.clone()).await.unwrap();
+        assert_eq!(orders.schema.columns.len(), 3);
+        assert!(orders.location.starts_with(&engine.back_end().url_for("orders-")));
+
+        let written = engine.snapshots("orders").unwrap().pop().unwrap();
+        assert_eq!(written.data_files.len(), 1);
+        let file_path = engine.back_end().path_for(&written.data_files[0].file_path).unwrap();
+        let store = engine.back_end().store();
+        assert!(store.head(&file_path).await.is_ok());
+
+        // The table already exists, nothing is written
+        assert!(engine.ingest(input.clone()).await.is_err());
+        assert_eq!(engine.snapshots("orders").unwrap().len(), 1);
+
+        let partitioned = BlobWriterOps::make()
+            .path(input)
+            .make_paritions(vec![String::from("region")])
+            .table(String::from("orders_by_region"))
+            .buiild();
+        let by_region = engine.ingest_with(&partitioned).await.unwrap();
+        assert_eq!(by_region.partition_spec, vec!["region"]);
+
+        assert!(table_dir(&dir.join("lake"), "orders_by_region").join("region=east").is_dir());
+
+        // Ingested tables are queryable straight away
+        let batches = engine
+            .sql("SELECT COUNT(*) FROM orders_by_region WHERE region = 'east'").await
+            .unwrap();
+        let count = batches[0].column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(count.value(0), 3);
+
+        // A file that can't be read leaves no table and no files behind, even those
+        // written from the rows before the unreadable one
+        let broken = dir.join("broken.csv");
+        let rows = (1..=2048).map(|id| format!("{},{}\n", id, id * 10)).collect::<String>();
+        fs::write(&broken, format!("id,amount\n{}2049,x\n", rows)).unwrap();
+        let broken = BlobWriterOps::make()
+            .path(broken)
+            .set_delimiter(',')
+            .sample_size(10)
+            .row_group_size(100)
+            .target_file_size(1)
+            .buiild();
+        assert!(engine.ingest_with(&broken).await.is_err());
+        assert!(engine.sql("SELECT * FROM broken").await.is_err());
+        let leftovers = fs::read_dir(dir.join("lake"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.file_name().unwrap().to_string_lossy().starts_with("broken-"))
+            .flat_map(|path| files_under(&path))
+            .collect::<Vec<_>>();
+        assert_eq!(leftovers, Vec::<PathBuf>::new());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn ingest_streams_row_groups_and_rolls_files() {
+        let dir = scratch_dir("ingest_streams_row_groups_and_rolls_files");
+        let input = write_orders_csv(&dir);
+        let lake = dir.join("lake");
+
+        let engine = EngineOptions::new()
+            .provider(Storage::LocalFileSystem { base_path: lake.clone() })
+            .catalogue(PathBuf::from(IN_MEMORY_CATALOGUE))
+            .build().await
+            .unwrap();
+        let row_groups = |url: &str| {
+            let file = fs::File::open(PathBuf::from(url.trim_start_matches("file://"))).unwrap();
+            let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+            reader
+                .metadata()
+                .row_groups()
+                .iter()
+                .map(|group| group.num_rows())
+                .collect::<Vec<_>>()
+        };
+
+        let grouped = BlobWriterOps::make().path(input.clone()).row_group_size(2).buiild();
+        engine.ingest_with(&grouped).await.unwrap();
+
+        let files = engine.snapshots("orders").unwrap().pop().unwrap().data_files;
+        assert_eq!(files.len(), 1);
+        assert_eq!(row_groups(&files[0].file_path), vec![2, 2, 2]);
+        assert!(files[0].file_size > 0);
+
+        // Every row group fills the target size, each starts a new file
+        let rolled = BlobWriterOps::make()
+            .path(input.clone())
+            .table(String::from("rolled"))
+            .row_group_size(2)
+            .target_file_size(1)
+            .buiild();
+        engine.ingest_with(&rolled).await.unwrap();
+
+        let files = engine.snapshots("rolled").unwrap().pop().unwrap().data_files;
+        assert_eq!(files.len(), 3);
+        for file in &files {
+            assert_eq!(file.row_count, 2);
+            assert_eq!(row_groups(&file.file_path), vec![2]);
+        }
+
+        let batches = engine.sql("SELECT SUM(amount) FROM rolled").await.unwrap();
+        let total = batches[0].column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(total.value(0), 210);
+
+        // Files buffering more than the budget together are completed early
+        let budgeted = |table: &str, bytes: usize| {
+            BlobWriterOps::make()
+                .path(input.clone())
+                .table(String::from(table))
+                .make_paritions(vec![String::from("region")])
+                .row_group_size(1)
+                .max_buffered_bytes(bytes)
+                .buiild()
+        };
+        engine.ingest_with(&budgeted("unbounded", usize::MAX)).await.unwrap();
+        let files = engine.snapshots("unbounded").unwrap().pop().unwrap().data_files;
+        assert_eq!(files.len(), 3);
+
+        engine.ingest_with(&budgeted("bounded", 1)).await.unwrap();
+        let files = engine.snapshots("bounded").unwrap().pop().unwrap().data_files;
+        assert_eq!(files.len(), 6);
+        assert!(files.iter().all(|file| file.row_count == 1));
+
+        // Tables take the size from their properties
+        engine
+            .sql(
+                "CREATE TABLE by_region TBLPROPERTIES ('write.target-file-size-bytes' = '1') \
+                 PARTITION BY (region) AS SELECT * FROM orders"
+            ).await
+            .unwrap();
+        let files = engine.snapshots("by_region").unwrap().pop().unwrap().data_files;
+        // Each region of each of the three source files' batches went to its own file
+        assert_eq!(files.len(), 6);
+        assert!(files.iter().all(|file| file.row_count == 1));
+
+        // Files also roll over at a row count
+        let by_rows = BlobWriterOps::make()
+            .path(input.clone())
+            .table(String::from("by_rows"))
+            .target_file_rows(4)
+            .buiild();
+        engine.ingest_with(&by_rows).await.unwrap();
+
+        let files = engine.snapshots("by_rows").unwrap().pop().unwrap().data_files;
+        let mut row_counts: Vec<u64> = files
+            .iter()
+            .map(|file| file.row_count)
+            .collect();
+        row_counts.sort();
+        assert_eq!(row_counts, vec![2, 4]);
+
+        // Converting the same file twice keeps both outputs side by side
+        let landing = dir.join("landing.csv");
+        fs::copy(&input, &landing).unwrap();
+        let converted = BlobWriterOps::make().path(landing).target_file_rows(4).buiild();
+        converted.to_parquet(engine.back_end()).await.unwrap();
+        converted.to_parquet(engine.back_end()).await.unwrap();
+
+        let written = fs::read_dir(lake.join("landing")).unwrap().count();
+        assert_eq!(written, 4);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn glob_ingest_commits_every_file_at_once() {
+        let dir = scratch_dir("glob_ingest_commits_every_file_at_once");
+        let landing = dir.join("landing");
+        for (file, rows) in [
+            ("2024-12/old.csv", "1,east,10\n"),
+            ("2025-01/a.csv", "2,west,20\n3,east,30\n"),
+            ("2025-02/b.csv", "4,north,40\n"),
+            ("2025-02/c.CSV", "5,west,50\n6,east,60\n"),
+            ("2025-02/notes.txt", "not,a,csv\n"),
+        ] {
+            let path = landing.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, format!("id,region,amount\n{}", rows)).unwrap();
+        }
+        let pattern = landing.join("2025-*/*.csv").to_string_lossy().to_string();
+
+        let files = BlobWriter::find_files(&pattern).unwrap();
+        assert_eq!(files, vec![
+            landing.join("2025-01/a.csv"),
+            landing.join("2025-02/b.csv"),
+            landing.join("2025-02/c.CSV")
+        ]);
+
+        let engine = EngineOptions::new()
+            .provider(Storage::LocalFileSystem { base_path: dir.join("lake") })
+            .catalogue(PathBuf::from(IN_MEMORY_CATALOGUE))
+            .build().await
+            .unwrap();
+
+        let unnamed = BlobWriterOps::make().glob(&pattern).buiild();
+        assert!(engine.ingest_with(&unnamed).await.is_err());
+
+        let bulk = BlobWriterOps::make()
+            .glob(&pattern)
+            .table(String::from("landed"))
+            .make_paritions(vec![String::from("region")])
+            .concurrency(2)
+            .buiild();
+        let landed = engine.ingest_with(&bulk).await.unwrap();
+        assert_eq!(landed.partition_spec, vec!["region"]);
+
+        let snapshots = engine.snapshots("landed").unwrap();
+        assert_eq!(snapshots.len(), 1);
+        // One file per region of each input file
+        assert_eq!(snapshots[0].data_files.len(), 5);
+
+        let batches = engine.sql("SELECT COUNT(*), SUM(amount) FROM landed").await.unwrap();
+        let count = batches[0].column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+        let total = batches[0].column(1).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!((count.value(0), total.value(0)), (5, 200));
+
+        // A single unreadable file fails the whole ingest, without leaving files behind
+        fs::write(landing.join("2025-02/d.csv"), "id,region,amount\n7,east,x\n").unwrap();
+        let failing = BlobWriterOps::make().glob(&pattern).table(String::from("failed")).buiild();
+        assert!(engine.ingest_with(&failing).await.is_err());
+        assert!(engine.sql("SELECT * FROM failed").await.is_err());
+        let leftovers = fs::read_dir(dir.join("lake"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.file_name().unwrap().to_string_lossy().starts_with("failed-"))
+            .flat_map(|path| files_under(&path))
+            .collect::<Vec<_>>();
+        assert_eq!(leftovers, Vec::<PathBuf>::new());
+
+        // So does a file whose columns differ from the first file's
+        let swapped = landing.join("2025-03/swapped.csv");
+        fs::create_dir_all(swapped.parent().unwrap()).unwrap();
+        fs::write(&swapped, "id,amount,region\n8,80,west\n").unwrap();
+        let mixed = landing.join("2025-0[13]/*.csv").to_string_lossy().to_string();
+        let mixed = BlobWriterOps::make().glob(&mixed).table(String::from("mixed")).buiild();
+        let error = engine.ingest_with(&mixed).await.unwrap_err();
+        assert!(error.to_string().contains("swapped.csv"));
+
+        let missing = landing.join("2030-*/*.csv").to_string_lossy().to_string();
+        let nothing = BlobWriterOps::make().glob(&missing).table(String::from("none")).buiild();
+        assert!(engine.ingest_with(&nothing).await.is_err());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn csv_schema_hints_override_inference() {
+        let dir = scratch_dir("csv_schema_hints_override_inference");
+        let input = dir.join("readings.csv");
+        fs::write(
+            &input,
+            "id,value,day,taken_at\n1,10,05/03/2024,05/03/2024 10:30\n2,20,06/03/2024,\n\
+             3,30,07/03/2024,07/03/2024 23:59\n4,40,,07/03/2024 00:00\n\
+             5,50,08/03/2024,08/03/2024 12:00\n6,N/A,09/03/2024,09/03/2024 12:00\n"
+        ).unwrap();
+
+        let engine = EngineOptions::new()
+            .provider(Storage::LocalFileSystem { base_path: dir.join("lake") })
+            .catalogue(PathBuf::from(IN_MEMORY_CATALOGUE))
+            .build().await
+            .unwrap();
+        let engine = &engine;
+        let ingest = |table: &str, ops: BlobWriterOps| {
+            let writer = ops.path(input.clone()).table(table.to_string()).buiild();
+            async move { engine.ingest_with(&writer).await }
+        };
+        let value_type = |table: &TableMetadata| table.schema.columns[1].datatype.clone();
+
+        // Five sampled rows say integer, the sixth doesn't parse as one
+        assert!(ingest("sampled", BlobWriterOps::make()).await.is_err());
+
+        let full = ingest("full", BlobWriterOps::make().infer_full_file()).await.unwrap();
+        assert_eq!(value_type(&full), DataType::Utf8);
+        let larger = ingest("larger", BlobWriterOps::make().sample_size(6)).await.unwrap();
+        assert_eq!(value_type(&larger), DataType::Utf8);
+
+        let hinted = ingest(
+            "hinted",
+            BlobWriterOps::make()
+                .column_type("value", DataType::Utf8)
+                .column_type("day", DataType::Date32)
+                .column_type("taken_at", DataType::Timestamp(TimeUnit::Microsecond, None))
+                .date_format("%d/%m/%Y")
+                .timestamp_format("%d/%m/%Y %H:%M")
+        ).await.unwrap();
+        assert_eq!(hinted.schema.columns[2].datatype, DataType::Date32);
+
+        let batches = engine
+            .sql(
+                "SELECT CAST(day AS VARCHAR), CAST(taken_at AS VARCHAR) FROM hinted \
+                 WHERE id IN (3, 4) ORDER BY id"
+            ).await
+            .unwrap();
+        let days = batches[0].column(0).as_any().downcast_ref::<StringArray>().unwrap();
+        let taken = batches[0].column(1).as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(days.iter().collect::<Vec<_>>(), vec![Some("2024-03-07"), None]);
+        assert_eq!(
+            taken.iter().collect::<Vec<_>>(),
+            vec![Some("2024-03-07T23:59:00"), Some("2024-03-07T00:00:00")]
+        );
+
+        assert!(
+            ingest("unknown", BlobWriterOps::make().column_type("missing", DataType::Utf8)).await
+                .is_err()
+        );
+        let misformatted = BlobWriterOps::make()
+            .column_type("value", DataType::Utf8)
+            .column_type("day", DataType::Date32)
+            .date_format("%Y-%m-%d");
+        assert!(ingest("misformatted", misformatted).await.is_err());
+
+        // Timestamps past what nanoseconds can hold aren't reported as misformatted
+        let future = dir.join("future.csv");
+        fs::write(&future, "id,taken_at\n1,05/03/2300 10:30\n").unwrap();
+        let future = BlobWriterOps::make()
+            .path(future)
+            .column_type("taken_at", DataType::Timestamp(TimeUnit::Microsecond, None))
+            .timestamp_format("%d/%m/%Y %H:%M")
+            .buiild();
+        let error = engine.ingest_with(&future).await.unwrap_err().to_string();
+        assert!(error.contains("1677 to 2262"), "{}", error);
+
+        // An explicit schema replaces inference, its columns keep their field ids
+        let column = |field_id: i32, name: &str, datatype: DataType| Column {
+            field_id,
+            name: name.to_string(),
+            datatype,
+            nullable: true,
+            unique: false,
+            references: None,
+        };
+        let explicit = SchemaVec {
+            columns: vec![
+                column(7, "reading_id", DataType::Int32),
+                column(8, "raw", DataType::Utf8),
+                column(9, "day", DataType::Utf8),
+                column(10, "taken_at", DataType::Utf8)
+            ],
+        };
+        let typed = ingest("typed", BlobWriterOps::make().schema(explicit.clone())).await.unwrap();
+        let ids: Vec<i32> = typed.schema.columns
+            .iter()
+            .map(|column| column.field_id)
+            .collect();
+        assert_eq!(ids, vec![7, 8, 9, 10]);
+
+        // Type hints would be ignored next to an explicit schema, ids must be unique
+        let hinted = BlobWriterOps::make()
+            .schema(explicit.clone())
+            .column_type("raw", DataType::Int64);
+        assert!(ingest("hinted_schema", hinted).await.is_err());
+        let mut duplicated = explicit;
+        duplicated.columns[1].field_id = 7;
+        assert!(ingest("duplicated", BlobWriterOps::make().schema(duplicated)).await.is_err());
+
+        let batches = engine.sql("SELECT raw FROM typed WHERE reading_id = 6").await.unwrap();
+        let raw = batches[0].column(0).as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(raw.value(0), "N/A");
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn engine_sql_over_catalogued_tables() {
+        let dir = scratch_dir("engine_sql_over_catalogued_tables");
+        let input = write_orders_csv(&dir);
+
+        let engine = EngineOptions::new()
+            .provider(Storage::LocalFileSystem { base_path: dir.join("lake") })
+            .catalogue(dir.join("catalogue.db"))
+            .build().await
+            .unwrap();
+
+        let orders = engine.ingest(input).await.unwrap();
+        let schema = orders.schema.to_arrow_schema();
+        let location = orders.location;
+
+        let batches = engine
+            .sql("SELECT region, SUM(amount) AS total FROM orders GROUP BY region ORDER BY region")
+            .await
+            .unwrap();
+
+        let rows: usize = batches
+            .iter()
+            .map(|batch| batch.num_rows())
+            .sum();
+        assert_eq!(rows, 3);
+
+        let totals = batches[0]
+            .column(1)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(totals.value(0), 100);
+
+        // Tables created after the session started resolve through the catalogue
+        engine.create_table("orders_copy", &location, &schema).await.unwrap();
+
+        let batches = engine.sql("SELECT * FROM lake.default.orders_copy").await.unwrap();
+        let rows: usize = batches
+            .iter()
+            .map(|batch| batch.num_rows())
+            .sum();
+        assert_eq!(rows, 6);
+
+        assert!(engine.sql("SELECT * FROM lake.default.missing").await.is_err());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn engine_create_table_as_and_insert_into() {
+        let dir = scratch_dir("engine_create_table_as_and_insert_into");
+        let input = write_orders_csv(&dir);
+
+        let engine = EngineOptions::new()
+            .provider(Storage::LocalFileSystem { base_path: dir.join("lake") })
+            .catalogue(dir.join("catalogue.db"))
+            .build().await
+            .unwrap();
+
+        engine.ingest(input).await.unwrap();
+
+        engine
+            .sql("CREATE TABLE east AS SELECT id, amount FROM orders WHERE region = 'east'")
+            .await
+            .unwrap();
+
+        let inserted = engine
+            .sql("INSERT INTO east SELECT id, amount FROM orders WHERE region = 'west'")
+            .await
+            .unwrap();
+        let count = inserted[0].column(0).as_any().downcast_ref::<UInt64Array>().unwrap();
+        assert_eq!(count.value(0), 2);
+
+        engine.sql("INSERT INTO east VALUES (7, 70)").await.unwrap();
+
+        let batches = engine.sql("SELECT SUM(amount) FROM lake.default.east").await.unwrap();
+        let total = batches[0].column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(total.value(0), 240);
+
+        assert!(engine.sql("CREATE TABLE east AS SELECT 1").await.is_err());
+        engine.sql("CREATE TABLE IF NOT EXISTS east AS SELECT 1").await.unwrap();
+
+        let snapshots = engine.snapshots("east").unwrap();
+        assert_eq!(snapshots.len(), 3);
+        assert_eq!(snapshots[2].sequence_number, 3);
+        assert_eq!(snapshots[2].parent_snapshot_id, Some(snapshots[1].snapshot_id));
+        assert_eq!(snapshots[2].data_files.len(), 3);
+        assert_eq!(snapshots[2].summary["total-records"], "6");
+
+        engine.sql("INSERT OVERWRITE east VALUES (8, 80)").await.unwrap();
+
+        let batches = engine.sql("SELECT SUM(amount) FROM east").await.unwrap();
+        let total = batches[0].column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(total.value(0), 80);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn snapshot_commit_is_compare_and_swap() {
+        let catalogue = RootCatalogue::in_memory().unwrap();
+
+        let table_id = catalogue
+            .create_sys_table(
+                &(Table {
+                    namespace: String::from("default"),
+                    table_name: String::from("events"),
+                    schema_bin: SchemaVec::serialize_schema(&SchemaVec::new()),
+                    url: String::from("db://local/events/"),
+                    partition_spec: Vec::new(),
+                    properties: BTreeMap::new(),
+                })
+            )
+            .unwrap();
+
+        let file = |name: &str| DataFile {
+            file_path: format!("db://local/events/{}.parquet", name),
+            row_count: 10,
+            file_size: 100,
+            schema_id: 1,
+            spec_id: 0,
+            partition_values: Vec::new(),
+            column_stats: BTreeMap::new(),
+        };
+
+        let first = catalogue
+            .commit_snapshot(&Snapshot::next(table_id, None, Operation::Append, vec![file("a")]))
+            .unwrap();
+
+        // Two writers race from the same parent, only the first one lands
+        let winner = Snapshot::next(table_id, Some(&first), Operation::Append, vec![file("b")]);
+        let loser = Snapshot::next(table_id, Some(&first), Operation::Append, vec![file("c")]);
+
+        let second = catalogue.commit_snapshot(&winner).unwrap();
+        let conflict = catalogue.commit_snapshot(&loser).unwrap_err();
+        assert!(conflict.is::<CommitConflict>());
+
+        let current = catalogue.current_snapshot(&table_id).unwrap().unwrap();
+        assert_eq!(current, second);
+        assert_eq!(current.row_count(), 20);
+        assert_eq!(catalogue.list_snapshots(&table_id).unwrap().len(), 2);
+
+        // A data file list that can't be decoded is an error, not a panic
+        catalogue.db
+            .get()
+            .unwrap()
+            .execute(
+                "UPDATE sys_snapshots SET data_files = x'ff' WHERE snapshot_id = ?1",
+                [second.snapshot_id]
+            )
+            .unwrap();
+        assert!(catalogue.current_snapshot(&table_id).is_err());
+
+        // In-memory catalogues are private to whoever opened them
+        let other = RootCatalogue::in_memory().unwrap();
+        assert_eq!(other.get_table_id("default", "events").unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn engine_time_travel() {
+        let engine = EngineOptions::new()
+            .provider(Storage::InMemory)
+            .catalogue(PathBuf::from(IN_MEMORY_CATALOGUE))
+            .build().await
+            .unwrap();
+
+        let count = |batches: Vec<arrow_array::RecordBatch>| {
+            batches[0].column(0).as_any().downcast_ref::<Int64Array>().unwrap().value(0)
+        };
+
+        engine.sql("CREATE TABLE audit AS SELECT 1 AS id").await.unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        engine.sql("INSERT INTO audit VALUES (2), (3)").await.unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        engine.sql("INSERT OVERWRITE audit VALUES (4)").await.unwrap();
+
+        let first = engine.sql("SELECT COUNT(*) FROM audit FOR VERSION AS OF 1").await.unwrap();
+        assert_eq!(count(first), 1);
+
+        let second = engine.sql_at("audit", 2).await.unwrap();
+        assert_eq!(
+            second
+                .iter()
+                .map(|batch| batch.num_rows())
+                .sum::<usize>(),
+            3
+        );
+
+        let snapshots = engine.snapshots("audit").unwrap();
+        let as_of = engine
+            .sql(
+                &format!(
+                    "SELECT COUNT(*) FROM lake.default.audit FOR TIMESTAMP AS OF {}",
+                    snapshots[1].timestamp_ms
+                )
+            ).await
+            .unwrap();
+        assert_eq!(count(as_of), 3);
+
+        let current = engine.sql("SELECT COUNT(*) FROM audit").await.unwrap();
+        assert_eq!(count(current), 1);
+
+        assert!(engine.sql("SELECT * FROM audit FOR VERSION AS OF 9").await.is_err());
+
+        // Quoted, mixed-case names are read at a version too
+        engine.sql("CREATE TABLE \"Audit\" AS SELECT 1 AS id").await.unwrap();
+        engine.sql("INSERT INTO \"Audit\" VALUES (2)").await.unwrap();
+        let quoted = engine
+            .sql("SELECT COUNT(*) FROM \"Audit\" FOR VERSION AS OF 1").await
+            .unwrap();
+        assert_eq!(count(quoted), 1);
+
+        let snapshots = engine.snapshots("audit").unwrap();
+        assert!(snapshots[0].data_files[0].file_path.starts_with("memory://local/audit-"));
+    }
+
+    #[test]
+    fn time_travel_rewrite() {
+        let parse = |sql: &str| parse_time_travel(sql, 50).map(|statement| statement.to_string());
+
+        assert_eq!(
+            parse(
+                "SELECT * FROM lake.default.Orders FOR TIMESTAMP AS OF '2025-06-01T12:00:00Z' o \
+                 JOIN items FOR VERSION AS OF 2 i ON o.id = i.id"
+            ).unwrap(),
+            "SELECT * FROM lake.default.\"orders@t1748779200000\" AS o \
+             JOIN \"items@v2\" AS i ON o.id = i.id"
+        );
+
+        // Quoted names keep their case and may contain dots
+        assert_eq!(
+            parse("SELECT * FROM lake.\"team.analytics\".\"Events\" FOR VERSION AS OF 2").unwrap(),
+            "SELECT * FROM lake.\"team.analytics\".\"Events@v2\""
+        );
+
+        // Literals and comments are not clauses
+        assert_eq!(
+            parse("SELECT 'items FOR VERSION AS OF 2' FROM items -- items FOR VERSION AS OF 3")
+                .unwrap(),
+            "SELECT 'items FOR VERSION AS OF 2' FROM items"
+        );
+
+        assert_eq!(TableVersion::split_name("items@v2"), ("items", TableVersion::Version(2)));
+        assert_eq!(TableVersion::split_name("items"), ("items", TableVersion::Current));
+        assert!(parse("SELECT * FROM t FOR TIMESTAMP AS OF 'yesterday'").is_err());
+        assert!(parse("SELECT * FROM t FOR VERSION AS OF 'first'").is_err());
+    }
+
+    #[tokio::test]
+    async fn engine_schema_evolution() {
+        let dir = scratch_dir("engine_schema_evolution");
+
+        let engine = EngineOptions::new()
+            .provider(Storage::LocalFileSystem { base_path: dir.join("lake") })
+            .catalogue(dir.join("catalogue.db"))
+            .build().await
+            .unwrap();
+
+        engine
+            .sql(
+                "CREATE TABLE feed AS SELECT CAST(1 AS INT) AS id, 'a' AS name, CAST(1.5 AS FLOAT) AS score"
+            ).await
+            .unwrap();
+
+        engine.sql("ALTER TABLE feed ADD COLUMN email VARCHAR").await.unwrap();
+        engine.sql("ALTER TABLE feed RENAME COLUMN name TO label").await.unwrap();
+        engine.sql("ALTER TABLE feed ALTER COLUMN id SET DATA TYPE BIGINT").await.unwrap();
+        engine.sql("ALTER TABLE feed ALTER COLUMN score SET DATA TYPE DOUBLE").await.unwrap();
+        engine.sql("ALTER TABLE feed DROP COLUMN email").await.unwrap();
+        engine.sql("ALTER TABLE feed ADD COLUMN email VARCHAR").await.unwrap();
+
+        engine.sql("INSERT INTO feed VALUES (5000000000, 'b', 2.5, 'b@example.com')").await.unwrap();
+
+        let batches = engine
+            .sql("SELECT id, label, score, email FROM feed ORDER BY id").await
+            .unwrap();
+        let batch = &batches[0];
+        assert_eq!(batch.num_rows(), 2);
+
+        let ids = batch.column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(ids.values(), &[1, 5000000000]);
+
+        let labels = batch.column(1).as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(labels.value(0), "a");
+
+        let scores = batch.column(2).as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(scores.value(0), 1.5);
+
+        // The re-added column gets a fresh id, so the old file doesn't resurrect values
+        assert!(batch.column(3).is_null(0));
+        assert!(!batch.column(3).is_null(1));
+
+        let versions = engine.schema_versions("feed").unwrap();
+        assert_eq!(versions.len(), 7);
+
+        let current = &versions[6].schema;
+        assert_eq!(current.column_by_name("email").unwrap().field_id, 5);
+        assert_eq!(current.column_by_name("label").unwrap().field_id, 2);
+
+        assert!(engine.sql("ALTER TABLE feed ALTER COLUMN label SET DATA TYPE INT").await.is_err());
+        assert!(engine.sql("ALTER TABLE feed ADD COLUMN id INT").await.is_err());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn parquet_files_carry_field_ids() {
+        let dir = scratch_dir("parquet_files_carry_field_ids");
+        let input = write_orders_csv(&dir);
+
+        let engine = EngineOptions::new()
+            .provider(Storage::LocalFileSystem { base_path: dir.join("lake") })
+            .catalogue(dir.join("catalogue.db"))
+            .build().await
+            .unwrap();
+
+        let orders = engine.ingest(input).await.unwrap();
+
+        let written = engine.snapshots("orders").unwrap().pop().unwrap();
+        let file_path = written.data_files[0].file_path.trim_start_matches("file://");
+        let file = fs::File::open(file_path).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+        let ids: Vec<Option<i32>> = reader
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| SchemaVec::field_id(field))
+            .collect();
+        assert_eq!(ids, vec![Some(1), Some(2), Some(3)]);
+
+        // Catalogued under different names and order, the file is still read by id
+        let schema = SchemaVec {
+            columns: vec![
+                Column {
+                    field_id: 3,
+                    name: "total".to_string(),
+                    datatype: DataType::Float64,
+                    nullable: true,
+                    unique: false,
+                    references: None,
+                },
+                Column {
+                    field_id: 1,
+                    name: "order_id".to_string(),
+                    datatype: DataType::Int64,
+                    nullable: true,
+                    unique: false,
+                    references: None,
+                }
+            ],
+        };
+
+        let location = orders.location;
+        let catalogue = &engine.catalogues[DEFAULT_CATALOGUE_NAME];
+        let table_id = catalogue
+            .create_sys_table(
+                &(Table {
+                    namespace: String::from("default"),
+                    table_name: "orders_by_id".to_string(),
+                    schema_bin: SchemaVec::serialize_schema(&schema),
+                    url: location.clone(),
+                    partition_spec: Vec::new(),
+                    properties: BTreeMap::new(),
+                })
+            )
+            .unwrap();
+        let schema_id = catalogue.get_table_schema_id(&table_id).unwrap();
+        let files = BlobWriter::scan_data_files(engine.back_end(), &location, schema_id).await
+            .unwrap();
+        catalogue
+            .commit_snapshot(&Snapshot::next(table_id, None, Operation::Append, files))
+            .unwrap();
+
+        let batches = engine
+            .sql("SELECT order_id, total FROM orders_by_id ORDER BY order_id").await
+            .unwrap();
+        let batch = &batches[0];
+
+        let order_ids = batch.column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(order_ids.values(), &[1, 2, 3, 4, 5, 6]);
+
+        let totals = batch.column(1).as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(totals.value(5), 60.0);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn s3_store_honours_endpoint_and_prefix() {
+        // A stand-in S3 endpoint that records every request path and accepts any upload
+        let requests = Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+        let recorded = requests.clone();
+        let router = Router::new().fallback(move |uri: axum::http::Uri| {
+            let recorded = recorded.clone();
+            async move {
+                recorded.lock().unwrap().push(uri.path().to_string());
+                (StatusCode::OK, [("ETag", "\"etag\"")])
+            }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let back_end = (Storage::S3 {
+            bucket: "lake-bucket".to_string(),
+            region: "us-east-1".to_string(),
+            access_key_id: Some("minio".to_string()),
+            secret_access_key: Some("minio-secret".to_string()),
+            endpoint: Some(endpoint),
+            prefix: Some("/warehouse/".to_string()),
+        })
+            .get_store().await
+            .unwrap();
+
+        assert_eq!(back_end.object_store_url().as_str(), "s3://lake-bucket/");
+        assert_eq!(back_end.url_for("orders/"), "s3://lake-bucket/warehouse/orders/");
+        assert_eq!(
+            back_end.path_for("s3://lake-bucket/warehouse/orders/part-0.parquet").unwrap(),
+            Path::from("orders/part-0.parquet")
+        );
+
+        back_end
+            .store()
+            .put(&Path::from("orders/part-0.parquet"), bytes::Bytes::from("data").into()).await
+            .unwrap();
+        assert_eq!(*requests.lock().unwrap(), vec![
+            "/lake-bucket/warehouse/orders/part-0.parquet".to_string()
+        ]);
+
+        let half_configured = Storage::S3 {
+            bucket: "lake-bucket".to_string(),
+            region: "us-east-1".to_string(),
+            access_key_id: Some("minio".to_string()),
+            secret_access_key: None,
+            endpoint: None,
+            prefix: None,
+        };
+        assert!(half_configured.get_store().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn engine_attaches_named_catalogues() {
+        let dir = scratch_dir("engine_attaches_named_catalogues");
+        let scratch_uri = format!("file:{}?mode=rwc", dir.join("scratch.db").display());
+
+        let engine = EngineOptions::new()
+            .provider(Storage::LocalFileSystem { base_path: dir.join("lake") })
+            .catalogue(PathBuf::from(IN_MEMORY_CATALOGUE))
+            .attach_catalogue("scratch", PathBuf::from(scratch_uri))
+            .build().await
+            .unwrap();
+
+        engine.sql("CREATE TABLE events AS SELECT 1 AS id").await.unwrap();
+        engine.sql("CREATE TABLE scratch.default.events AS SELECT 2 AS id").await.unwrap();
+        engine.sql("INSERT INTO scratch.default.events VALUES (3)").await.unwrap();
+
+        let batches = engine
+            .sql(
+                "SELECT p.id, COUNT(*) FROM lake.default.events p \
+                 CROSS JOIN scratch.default.events s GROUP BY p.id"
+            ).await
+            .unwrap();
+        let counts = batches[0].column(1).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(counts.value(0), 2);
+
+        // Same table name, separate catalogue entries and separate files
+        assert_eq!(engine.snapshots("events").unwrap().len(), 1);
+        assert_eq!(engine.snapshots("scratch.default.events").unwrap().len(), 2);
+        assert!(table_dir(&dir.join("lake/scratch"), "events").is_dir());
+        assert!(dir.join("scratch.db").is_file());
+
+        let first = engine.sql_at("scratch.default.events", 1).await.unwrap();
+        let ids = first[0].column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(ids.values(), &[2]);
+
+        assert!(engine.sql("SELECT * FROM missing.default.events").await.is_err());
+        assert!(engine.snapshots("scratch.other.events").is_err());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn namespaces_scope_tables() {
+        let engine = EngineOptions::new()
+            .provider(Storage::InMemory)
+            .catalogue(PathBuf::from(IN_MEMORY_CATALOGUE))
+            .build().await
+            .unwrap();
+
+        let catalog = engine.catalog(DEFAULT_CATALOGUE_NAME).unwrap();
+
+        let properties = BTreeMap::from([("owner".to_string(), "analytics".to_string())]);
+        catalog.create_namespace("team", &properties).unwrap();
+        catalog.create_namespace("team.analytics", &BTreeMap::new()).unwrap();
+        assert!(catalog.create_namespace("team", &BTreeMap::new()).is_err());
+        assert!(catalog.create_namespace("missing.child", &BTreeMap::new()).is_err());
+
+        assert_eq!(catalog.list_namespaces(None).unwrap(), vec!["default", "team"]);
+        assert_eq!(catalog.list_namespaces(Some("team")).unwrap(), vec!["team.analytics"]);
+
+        let updated = catalog
+            .update_namespace_properties(
+                "team",
+                &BTreeMap::from([("retention".to_string(), "30d".to_string())]),
+                &["owner".to_string()]
+            )
+            .unwrap();
+        assert_eq!(updated, BTreeMap::from([("retention".to_string(), "30d".to_string())]));
+        assert_eq!(catalog.get_namespace_properties("team").unwrap(), updated);
+
+        // Two `events` tables, one per namespace
+        engine.sql("CREATE TABLE events AS SELECT 1 AS id").await.unwrap();
+        engine.sql("CREATE TABLE team.events AS SELECT 2 AS id").await.unwrap();
+        engine.sql("CREATE TABLE lake.\"team.analytics\".events AS SELECT 3 AS id").await.unwrap();
+
+        let batches = engine.sql("SELECT id FROM lake.team.events").await.unwrap();
+        let ids = batches[0].column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(ids.values(), &[2]);
+
+        assert_eq!(engine.snapshots("events").unwrap().len(), 1);
+        assert!(catalog.get_table_id("team.analytics", "events").unwrap().is_some());
+
+        let snapshots = engine.snapshots("team.events").unwrap();
+        assert!(snapshots[0].data_files[0].file_path.starts_with("memory://local/team/events-"));
+
+        // A table named like the namespace doesn't pick up the namespace's files
+        engine.sql("CREATE TABLE team AS SELECT 4 AS id").await.unwrap();
+        let team = catalog.load_table(&TableIdent::new("default", "team")).unwrap();
+        let files = BlobWriter::scan_data_files(engine.back_end(), &team.location, 1).await
+            .unwrap();
+        assert_eq!(files.len(), 1);
+        let batches = engine.sql("SELECT id FROM team").await.unwrap();
+        let ids = batches[0].column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(ids.values(), &[4]);
+
+        // SQL schemas are namespaces too
+        engine.sql("CREATE SCHEMA staging").await.unwrap();
+        assert!(catalog.namespace_exists("staging").unwrap());
+        engine.sql("DROP SCHEMA staging").await.unwrap();
+        assert!(!catalog.namespace_exists("staging").unwrap());
+
+        assert!(engine.sql("DROP SCHEMA team").await.is_err());
+        assert!(catalog.drop_namespace("team.analytics").is_err());
+        assert!(engine.sql("CREATE TABLE nowhere.events AS SELECT 1 AS id").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn catalog_loads_renames_and_lists_tables() {
+        let engine = EngineOptions::new()
+            .provider(Storage::InMemory)
+            .catalogue(PathBuf::from(IN_MEMORY_CATALOGUE))
+            .build().await
+            .unwrap();
+
+        let catalog = engine.catalog(DEFAULT_CATALOGUE_NAME).unwrap();
+        catalog.create_namespace("archive", &BTreeMap::new()).unwrap();
+
+        engine.sql("CREATE TABLE orders AS SELECT 1 AS id, 'east' AS region").await.unwrap();
+        engine.sql("CREATE TABLE customers AS SELECT 1 AS id").await.unwrap();
+
+        let orders = TableIdent::new("default", "orders");
+        assert!(catalog.table_exists(&orders).unwrap());
+        assert!(!catalog.table_exists(&TableIdent::new("archive", "orders")).unwrap());
+
+        assert_eq!(
+            catalog.list_tables("default").unwrap(),
+            vec![TableIdent::new("default", "customers"), orders.clone()]
+        );
+        assert!(catalog.list_tables("missing").is_err());
+
+        let metadata = catalog.load_table(&orders).unwrap();
+        assert_eq!(metadata.ident, orders);
+        assert!(metadata.location.starts_with("memory://local/orders-"));
+        assert_eq!(metadata.schema.column_by_name("region").unwrap().datatype, DataType::Utf8);
+        assert!(metadata.partition_spec.is_empty());
+        assert!(metadata.properties.is_empty());
+        assert_eq!(metadata.current_snapshot.unwrap().row_count(), 1);
+
+        let updates = BTreeMap::from([("owner".to_string(), "sales".to_string())]);
+        catalog.update_table_properties(&metadata.table_id, &updates, &[]).unwrap();
+        assert_eq!(catalog.load_table(&orders).unwrap().properties, updates);
+
+        // Renaming moves the catalogue entry, the data stays where it was written
+        let archived = TableIdent::new("archive", "orders_2024");
+        catalog.rename_table(&orders, &archived).unwrap();
+
+        assert!(!catalog.table_exists(&orders).unwrap());
+        let renamed = catalog.load_table(&archived).unwrap();
+        assert_eq!(renamed.table_id, metadata.table_id);
+        assert_eq!(renamed.location, metadata.location);
+        assert_eq!(renamed.properties, updates);
+
+        let batches = engine.sql("SELECT id FROM archive.orders_2024").await.unwrap();
+        assert_eq!(batches[0].num_rows(), 1);
+
+        let customers = TableIdent::new("default", "customers");
+        assert!(catalog.rename_table(&customers, &archived).is_err());
+        assert!(catalog.rename_table(&orders, &customers).is_err());
+        assert!(catalog.rename_table(&customers, &TableIdent::new("missing", "c")).is_err());
+        assert!(catalog.load_table(&orders).is_err());
+
+        // A new table under the old name gets its own directory
+        engine.sql("CREATE TABLE orders AS SELECT 2 AS id").await.unwrap();
+        let recreated = catalog.load_table(&orders).unwrap();
+        assert_ne!(recreated.location, renamed.location);
+
+        let batches = engine.sql("SELECT id FROM orders").await.unwrap();
+        let ids = batches[0].column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(ids.values(), &[2]);
+        let batches = engine.sql("SELECT id FROM archive.orders_2024").await.unwrap();
+        assert_eq!(batches[0].num_rows(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn rest_catalog_serves_the_catalogue() {
+        let dir = scratch_dir("rest-catalog");
+        let catalogue = dir.join("lake.db");
+
+        let engine = EngineOptions::new()
+            .provider(Storage::InMemory)
+            .catalogue(catalogue.clone())
+            .build().await
+            .unwrap();
+        engine.sql("CREATE TABLE orders AS SELECT 1 AS id, 'east' AS region").await.unwrap();
+
+        let server = RestCatalogServer::new(catalogue, Storage::InMemory).await.unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}/v1", listener.local_addr().unwrap());
+        tokio::spawn(server.serve(listener));
+
+        let written = engine.catalog(DEFAULT_CATALOGUE_NAME).unwrap();
+        let written = written.load_table(&TableIdent::new("default", "orders")).unwrap();
+
+        let (orders, schema_id) = {
+            let base = base.clone();
+            tokio::task
+                ::spawn_blocking(move || {
+                    let agent: ureq::Agent = ureq::Agent
+                        ::config_builder()
+                        .http_status_as_error(false)
+                        .build()
+                        .into();
+                    let get = |path: &str| {
+                        let mut response = agent.get(&format!("{}{}", base, path)).call().unwrap();
+                        let status = response.status().as_u16();
+                        (status, response.body_mut().read_json::<Value>().unwrap())
+                    };
+                    let post = |path: &str, body: Value| {
+                        let mut response = agent
+                            .post(&format!("{}{}", base, path))
+                            .send_json(&body)
+                            .unwrap();
+                        let status = response.status().as_u16();
+                        let body = match status {
+                            204 => Value::Null,
+                            _ => response.body_mut().read_json::<Value>().unwrap(),
+                        };
+                        (status, body)
+                    };
+
+                    assert_eq!(get("/config").0, 200);
+
+                    let (_, namespaces) = get("/namespaces");
+                    assert_eq!(namespaces["namespaces"], json!([["default"]]));
+
+                    let team = json!({ "namespace": ["team"], "properties": { "owner": "data" } });
+                    assert_eq!(post("/namespaces", team.clone()).0, 200);
+                    let (status, error) = post("/namespaces", team);
+                    assert_eq!(status, 409);
+                    assert_eq!(error["error"]["type"], "AlreadyExistsException");
+
+                    post("/namespaces", json!({ "namespace": ["team", "analytics"] }));
+                    let (_, children) = get("/namespaces?parent=team");
+                    assert_eq!(children["namespaces"], json!([["team", "analytics"]]));
+                    assert_eq!(get("/namespaces/team").1["properties"]["owner"], "data");
+
+                    let head = |path: &str| {
+                        agent.head(&format!("{}{}", base, path)).call().unwrap().status().as_u16()
+                    };
+                    assert_eq!(head("/namespaces/team%1Fanalytics"), 204);
+                    assert_eq!(head("/namespaces/missing"), 404);
+
+                    // Tables written by the engine are discoverable
+                    let (_, tables) = get("/namespaces/default/tables");
+                    assert_eq!(
+                        tables["identifiers"],
+                        json!([{ "namespace": ["default"], "name": "orders" }])
+                    );
+
+                    let (status, orders) = get("/namespaces/default/tables/orders");
+                    assert_eq!(status, 200);
+
+                    // Tables created over REST are usable by the engine
+                    let schema = json!({
+                        "type": "struct",
+                        "fields": [
+                            { "id": 1, "name": "id", "required": true, "type": "long" },
+                            { "id": 2, "name": "name", "required": false, "type": "string" },
+                        ],
+                    });
+                    let (status, events) = post(
+                        "/namespaces/team%1Fanalytics/tables",
+                        json!({ "name": "events", "schema": schema })
+                    );
+                    assert_eq!(status, 200);
+                    let (status, error) = post(
+                        "/namespaces/team%1Fanalytics/tables",
+                        json!({ "name": "events", "schema": schema })
+                    );
+                    assert_eq!(status, 409);
+                    assert_eq!(error["error"]["type"], "AlreadyExistsException");
+                    let location = events["metadata"]["location"].as_str().unwrap();
+                    assert!(location.starts_with("memory://local/team/analytics/events-"));
+                    let created = events["metadata-location"].as_str().unwrap();
+                    assert!(created.starts_with(&format!("{}metadata/00001-", location)));
+
+                    let schema_id = events["metadata"]["current-schema-id"].clone();
+                    let evolve = json!({
+                        "requirements": [
+                            { "type": "assert-current-schema-id", "current-schema-id": schema_id },
+                        ],
+                        "updates": [
+                            {
+                                "action": "add-schema",
+                                "schema": {
+                                    "type": "struct",
+                                    "fields": [
+                                        { "id": 1, "name": "id", "required": true, "type": "long" },
+                                        {
+                                            "id": 2,
+                                            "name": "name",
+                                            "required": false,
+                                            "type": "string",
+                                        },
+                                        {
+                                            "id": 3,
+                                            "name": "score",
+                                            "required": false,
+                                            "type": "double",
+                                        },
+                                    ],
+                                },
+                            },
+                            { "action": "set-current-schema", "schema-id": -1 },
+                            { "action": "set-properties", "updates": { "owner": "data" } },
+                        ],
+                    });
+                    let (status, committed) = post(
+                        "/namespaces/team%1Fanalytics/tables/events",
+                        evolve.clone()
+                    );
+                    assert_eq!(status, 200);
+                    assert_eq!(committed["metadata"]["schemas"].as_array().unwrap().len(), 2);
+                    assert_eq!(committed["metadata"]["last-column-id"], 3);
+                    assert_eq!(committed["metadata"]["properties"]["owner"], "data");
+                    let published = committed["metadata-location"].as_str().unwrap();
+                    assert!(published.starts_with(&format!("{}metadata/00002-", location)));
+
+                    // The same commit again was made against a stale schema
+                    let (status, error) = post(
+                        "/namespaces/team%1Fanalytics/tables/events",
+                        evolve
+                    );
+                    assert_eq!(status, 409);
+                    assert_eq!(error["error"]["type"], "CommitFailedException");
+
+                    let snapshot = json!({
+                        "updates": [{ "action": "add-snapshot", "snapshot": {} }],
+                    });
+                    assert_eq!(post("/namespaces/default/tables/orders", snapshot).0, 406);
+
+                    let rename = json!({
+                        "source": { "namespace": ["default"], "name": "orders" },
+                        "destination": { "namespace": ["team"], "name": "orders" },
+                    });
+                    assert_eq!(post("/tables/rename", rename).0, 204);
+                    let (status, error) = get("/namespaces/default/tables/orders");
+                    assert_eq!(status, 404);
+                    assert_eq!(error["error"]["type"], "NoSuchTableException");
+
+                    let delete = |path: &str| {
+                        agent.delete(&format!("{}{}", base, path)).call().unwrap().status().as_u16()
+                    };
+                    assert_eq!(delete("/namespaces/team"), 409);
+                    assert_eq!(delete("/namespaces/team/tables/orders"), 204);
+                    assert_eq!(head("/namespaces/team/tables/orders"), 404);
+
+                    (orders, committed["metadata"]["current-schema-id"].clone())
+                }).await
+                .unwrap()
+        };
+
+        assert_eq!(orders["metadata-location"], json!(written.metadata_location.unwrap()));
+
+        let metadata = &orders["metadata"];
+        assert_eq!(metadata["format-version"], 2);
+        assert!(metadata["location"].as_str().unwrap().starts_with("memory://local/orders-"));
+        assert_eq!(metadata["schemas"][0]["fields"][0]["type"], "long");
+        assert_eq!(metadata["schemas"][0]["fields"][1]["type"], "string");
+        assert_eq!(metadata["snapshots"][0]["summary"]["operation"], "append");
+        assert_eq!(metadata["current-snapshot-id"], metadata["snapshots"][0]["snapshot-id"]);
+        assert_eq!(metadata["refs"]["main"]["snapshot-id"], metadata["current-snapshot-id"]);
+
+        let events = engine.catalog(DEFAULT_CATALOGUE_NAME).unwrap();
+        let events = events.load_table(&TableIdent::new("team.analytics", "events")).unwrap();
+        assert_eq!(json!(events.schema_id), schema_id);
+
+        engine
+            .sql("INSERT INTO lake.\"team.analytics\".events VALUES (1, 'a', 0.5)").await
+            .unwrap();
+        let batches = engine
+            .sql("SELECT score FROM lake.\"team.analytics\".events").await
+            .unwrap();
+        let scores = batches[0].column(0).as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(scores.values(), &[0.5]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn rest_catalogue_backs_the_engine() {
+        let dir = scratch_dir("rest-catalogue");
+        let catalogue = dir.join("lake.db");
+
+        // Both engines read and write the same files
+        let writer = EngineOptions::new()
+            .provider(Storage::LocalFileSystem { base_path: dir.join("lake") })
+            .catalogue(catalogue.clone())
+            .build().await
+            .unwrap();
+        writer.sql("CREATE TABLE orders AS SELECT 1 AS id, 'east' AS region").await.unwrap();
+
+        let server = RestCatalogServer::new(catalogue, Storage::InMemory).await.unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(server.serve(listener));
+
+        let engine = EngineOptions::new()
+            .provider(Storage::LocalFileSystem { base_path: dir.join("lake") })
+            .catalogue(PathBuf::from(IN_MEMORY_CATALOGUE))
+            .attach_catalogue("iceberg", CatalogueLocation::Rest { uri, token: None })
+            .build().await
+            .unwrap();
+        let catalog = engine.catalog("iceberg").unwrap();
+
+        assert_eq!(catalog.list_namespaces(None).unwrap(), vec!["default"]);
+        let owner = BTreeMap::from([("owner".to_string(), "data".to_string())]);
+        catalog.create_namespace("team", &owner).unwrap();
+        catalog.create_namespace("team.analytics", &BTreeMap::new()).unwrap();
+        assert_eq!(catalog.list_namespaces(Some("team")).unwrap(), vec!["team.analytics"]);
+        assert_eq!(catalog.get_namespace_properties("team").unwrap(), owner);
+        assert!(catalog.namespace_exists("team.analytics").unwrap());
+        assert!(!catalog.namespace_exists("missing").unwrap());
+
+        let orders = TableIdent::new("default", "orders");
+        assert_eq!(catalog.list_tables("default").unwrap(), vec![orders.clone()]);
+        let metadata = catalog.load_table(&orders).unwrap();
+        assert!(metadata.location.starts_with(&writer.back_end().url_for("orders-")));
+        assert_eq!(metadata.schema.column_by_name("region").unwrap().datatype, DataType::Utf8);
+        assert_eq!(metadata.current_snapshot.unwrap().operation, Operation::Append);
+
+        let events = TableIdent::new("team.analytics", "events");
+        catalog
+            .create_sys_table(
+                &(Table {
+                    namespace: events.namespace.clone(),
+                    table_name: events.name.clone(),
+                    schema_bin: SchemaVec::serialize_schema(&metadata.schema),
+                    url: String::from("memory://local/team/analytics/events/"),
+                    partition_spec: vec![String::from("region")],
+                    properties: BTreeMap::new(),
+                })
+            )
+            .unwrap();
+        let created = catalog.load_table(&events).unwrap();
+        assert_eq!(created.partition_spec, vec!["region"]);
+        assert!(created.current_snapshot.is_none());
+        catalog.del_sys_table(created.table_id).unwrap();
+        assert!(!catalog.table_exists(&events).unwrap());
+
+        // Schema changes are committed through the catalog, which the writer sees
+        engine.sql("ALTER TABLE iceberg.default.orders ADD COLUMN score DOUBLE").await.unwrap();
+        assert_eq!(writer.schema_versions("orders").unwrap().len(), 2);
+        assert_eq!(engine.schema_versions("iceberg.default.orders").unwrap().len(), 2);
+
+        let schema_id = catalog.get_table_schema_id(&metadata.table_id).unwrap();
+        let schema = catalog.get_table_schema(&metadata.table_id).unwrap();
+        assert!(schema.column_by_name("score").is_some());
+        let stale = catalog.commit_schema(&metadata.table_id, schema_id + 1, &schema).unwrap_err();
+        assert!(stale.downcast_ref::<CommitConflict>().is_some());
+
+        // So are partition spec changes
+        engine.sql("ALTER TABLE iceberg.default.orders ADD PARTITION FIELD region").await.unwrap();
+        let written = writer.catalog(DEFAULT_CATALOGUE_NAME).unwrap();
+        let partitioned = written.load_table(&orders).unwrap();
+        assert_eq!(partitioned.partition_spec, vec!["region"]);
+        assert_eq!(partitioned.default_spec_id, 1);
+
+        let error = engine
+            .sql("INSERT INTO iceberg.default.orders VALUES (2, 'west', 0.5)").await
+            .unwrap_err();
+        assert!(error.to_string().contains("not supported"));
+
+        catalog.update_table_properties(&metadata.table_id, &owner, &[]).unwrap();
+        let moved = TableIdent::new("team", "orders");
+        catalog.rename_table(&orders, &moved).unwrap();
+        assert!(!catalog.table_exists(&orders).unwrap());
+        assert_eq!(catalog.load_table(&moved).unwrap().properties, owner);
+        assert_eq!(engine.snapshots("iceberg.team.orders").unwrap().len(), 1);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn rest_catalogue_reads_iceberg_manifests() {
+        let dir = scratch_dir("rest-manifests");
+        fs::create_dir_all(dir.join("data")).unwrap();
+        fs::create_dir_all(dir.join("metadata")).unwrap();
+        let url = |path: &str| format!("file://{}", dir.join(path).display());
+
+        // Iceberg writers give every parquet column its schema field id
+        let field = |name: &str, datatype: DataType, id: &str| {
+            Field::new(name, datatype, id != "1").with_metadata(
+                HashMap::from([("PARQUET:field_id".to_string(), id.to_string())])
+            )
+        };
+        let schema = Arc::new(
+            Schema::new(vec![field("id", DataType::Int64, "1"), field("name", DataType::Utf8, "2")])
+        );
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2, 3])),
+                Arc::new(StringArray::from(vec!["a", "b", "c"]))
+            ]
+        ).unwrap();
+        let mut parquet = ArrowWriter::try_new(
+            fs::File::create(dir.join("data/part-0.parquet")).unwrap(),
+            schema,
+            None
+        ).unwrap();
+        parquet.write(&batch).unwrap();
+        parquet.close().unwrap();
+        let file_size = fs::metadata(dir.join("data/part-0.parquet")).unwrap().len();
+
+        let data_file = |path: String, size: i64| {
+            AvroValue::Record(
+                vec![
+                    ("content".to_string(), AvroValue::Int(0)),
+                    ("file_path".to_string(), AvroValue::String(path)),
+                    ("file_format".to_string(), AvroValue::String("PARQUET".to_string())),
+                    ("record_count".to_string(), AvroValue::Long(3)),
+                    ("file_size_in_bytes".to_string(), AvroValue::Long(size))
+                ]
+            )
+        };
+        let entry = |status: i32, data_file: AvroValue| {
+            AvroValue::Record(
+                vec![
+                    ("status".to_string(), AvroValue::Int(status)),
+                    ("snapshot_id".to_string(), AvroValue::Union(1, Box::new(AvroValue::Long(7)))),
+                    ("data_file".to_string(), data_file)
+                ]
+            )
+        };
+        write_avro(
+            &dir.join("metadata/manifest-0.avro"),
+            r#"{
+                "type": "record",
+                "name": "manifest_entry",
+                "fields": [
+                    { "name": "status", "type": "int" },
+                    { "name": "snapshot_id", "type": ["null", "long"] },
+                    {
+                        "name": "data_file",
+                        "type": {
+                            "type": "record",
+                            "name": "r2",
+                            "fields": [
+                                { "name": "content", "type": "int" },
+                                { "name": "file_path", "type": "string" },
+                                { "name": "file_format", "type": "string" },
+                                { "name": "record_count", "type": "long" },
+                                { "name": "file_size_in_bytes", "type": "long" }
+                            ]
+                        }
+                    }
+                ]
+            }"#,
+            vec![
+                entry(1, data_file(url("data/part-0.parquet"), file_size as i64)),
+                // Removed by this snapshot, never read
+                entry(2, data_file(url("data/removed.parquet"), 1))
+            ]
+        );
+        write_avro(
+            &dir.join("metadata/snap-7.avro"),
+            r#"{
+                "type": "record",
+                "name": "manifest_file",
+                "fields": [
+                    { "name": "manifest_path", "type": "string" },
+                    { "name": "manifest_length", "type": "long" },
+                    { "name": "content", "type": "int" }
+                ]
+            }"#,
+            vec![
+                AvroValue::Record(
+                    vec![
+                        (
+                            "manifest_path".to_string(),
+                            AvroValue::String(url("metadata/manifest-0.avro")),
+                        ),
+                        ("manifest_length".to_string(), AvroValue::Long(0)),
+                        ("content".to_string(), AvroValue::Int(0))
+                    ]
+                )
+            ]
+        );
+
+        let table = json!({
+            "metadata": {
+                "format-version": 2,
+                "table-uuid": "9c12d441-03fe-4693-9a96-a0705ddf69c1",
+                "location": url(""),
+                "last-sequence-number": 1,
+                "last-updated-ms": 1700000000000i64,
+                "last-column-id": 2,
+                "schemas": [
+                    {
+                        "type": "struct",
+                        "schema-id": 0,
+                        "fields": [
+                            { "id": 1, "name": "id", "required": true, "type": "long" },
+                            { "id": 2, "name": "name", "required": false, "type": "string" },
+                        ],
+                    },
+                ],
+                "current-schema-id": 0,
+                "partition-specs": [{ "spec-id": 0, "fields": [] }],
+                "default-spec-id": 0,
+                "last-partition-id": 999,
+                "properties": { "owner": "analytics" },
+                "current-snapshot-id": 7,
+                "snapshots": [
+                    {
+                        "snapshot-id": 7,
+                        "sequence-number": 1,
+                        "timestamp-ms": 1700000000000i64,
+                        "manifest-list": url("metadata/snap-7.avro"),
+                        "summary": { "operation": "append" },
+                        "schema-id": 0,
+                    },
+                ],
+                "refs": { "main": { "snapshot-id": 7, "type": "branch" } },
+                "sort-orders": [{ "order-id": 0, "fields": [] }],
+                "default-sort-order-id": 0,
+            },
+        });
+        let router = Router::new()
+            .route("/v1/config", get(|| async { Json(json!({ "defaults": {}, "overrides": {} })) }))
+            .route("/v1/namespaces/db", get(|| async { Json(json!({ "namespace": ["db"] })) }))
+            .route(
+                "/v1/namespaces/db/tables/events",
+                get(move |headers: HeaderMap| async move {
+                    match headers.get("Authorization").and_then(|value| value.to_str().ok()) {
+                        Some("Bearer secret") => (StatusCode::OK, Json(table)),
+                        _ => (StatusCode::UNAUTHORIZED, Json(json!({}))),
+                    }
+                })
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let engine = EngineOptions::new()
+            .provider(Storage::InMemory)
+            .catalogue(PathBuf::from(IN_MEMORY_CATALOGUE))
+            .attach_catalogue(
+                "iceberg",
+                CatalogueLocation::Rest { uri, token: Some("secret".to_string()) }
+            )
+            .build().await
+            .unwrap();
+
+        let batches = engine
+            .sql("SELECT id, name FROM iceberg.db.events ORDER BY id").await
+            .unwrap();
+        let ids = batches[0].column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+        let names = batches[0].column(1).as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(ids.values(), &[1, 2, 3]);
+        assert_eq!(names.value(2), "c");
+
+        let snapshots = engine.snapshots("iceberg.db.events").unwrap();
+        assert_eq!(snapshots[0].manifest_list, Some(url("metadata/snap-7.avro")));
+
+        let catalog = engine.catalog("iceberg").unwrap();
+        let metadata = catalog.load_table(&TableIdent::new("db", "events")).unwrap();
+        assert_eq!(metadata.properties["owner"], "analytics");
+        assert!(engine.sql("SELECT * FROM iceberg.db.missing").await.is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn iceberg_metadata_is_written_and_read_back() {
+        let dir = scratch_dir("iceberg-metadata");
+        let lake = dir.join("lake");
+        let local = |url: &str| PathBuf::from(url.trim_start_matches("file://"));
+        let read_avro = |url: &str| {
+            let bytes = fs::read(local(url)).unwrap();
+            apache_avro::Reader
+                ::new(&bytes[..])
+                .unwrap()
+                .map(|record| apache_avro::from_value::<Value>(&record.unwrap()).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let engine = EngineOptions::new()
+            .provider(Storage::LocalFileSystem { base_path: lake.clone() })
+            .catalogue(PathBuf::from(IN_MEMORY_CATALOGUE))
+            .build().await
+            .unwrap();
+        engine.sql("CREATE TABLE orders AS SELECT 1 AS id UNION ALL SELECT 2").await.unwrap();
+        engine.sql("INSERT INTO orders VALUES (3)").await.unwrap();
+
+        let catalog = engine.catalog(DEFAULT_CATALOGUE_NAME).unwrap();
+        let orders = catalog.load_table(&TableIdent::new("default", "orders")).unwrap();
+        let metadata_location = orders.metadata_location.unwrap();
+        assert!(metadata_location.starts_with(&format!("{}metadata/00002-", orders.location)));
+
+        let metadata: Value = serde_json
+            ::from_slice(&fs::read(local(&metadata_location)).unwrap())
+            .unwrap();
+        let snapshots = engine.snapshots("orders").unwrap();
+        assert_eq!(metadata["format-version"], 2);
+        assert_eq!(metadata["table-uuid"], orders.table_uuid);
+        assert_eq!(metadata["current-snapshot-id"], snapshots[1].snapshot_id);
+        assert_eq!(metadata["snapshots"].as_array().unwrap().len(), 2);
+        assert_eq!(metadata["snapshots"][1]["manifest-list"], json!(snapshots[1].manifest_list));
+
+        // The append carries the first snapshot's manifest over next to its own
+        let manifests = read_avro(snapshots[1].manifest_list.as_ref().unwrap());
+        assert_eq!(manifests.len(), 2);
+        assert_eq!(manifests[0]["added_snapshot_id"], snapshots[1].snapshot_id);
+        assert_eq!(manifests[0]["added_rows_count"], 1);
+        assert_eq!(manifests[1]["added_snapshot_id"], snapshots[0].snapshot_id);
+        assert_eq!(manifests[1]["sequence_number"], 1);
+
+        let mut listed = manifests
+            .iter()
+            .flat_map(|manifest| read_avro(manifest["manifest_path"].as_str().unwrap()))
+            .map(|entry| {
+                assert_eq!(entry["status"], 1);
+                entry["data_file"]["file_path"].as_str().unwrap().to_string()
+            })
+            .collect::<Vec<_>>();
+        let mut written = snapshots[1].data_files
+            .iter()
+            .map(|file| file.file_path.clone())
+            .collect::<Vec<_>>();
+        listed.sort();
+        written.sort();
+        assert_eq!(listed, written);
+
+        // Other Iceberg readers find the files at their real location on disk
+        let lake_url = format!("file://{}/", fs::canonicalize(&lake).unwrap().display());
+        assert!(orders.location.starts_with(&lake_url));
+        assert!(listed.iter().all(|path| path.starts_with(&lake_url)));
+
+        // Overwrites record the replaced files as deleted
+        engine.sql("INSERT OVERWRITE orders VALUES (4)").await.unwrap();
+        let overwrite = engine.snapshots("orders").unwrap().pop().unwrap();
+        let manifests = read_avro(overwrite.manifest_list.as_ref().unwrap());
+        assert_eq!(manifests.len(), 2);
+        assert_eq!(manifests[1]["deleted_files_count"], 2);
+        assert_eq!(manifests[1]["deleted_rows_count"], 3);
+
+        // Another catalogue adopts the table from its files alone
+        let reader = EngineOptions::new()
+            .provider(Storage::LocalFileSystem { base_path: lake.clone() })
+            .catalogue(PathBuf::from(IN_MEMORY_CATALOGUE))
+            .build().await
+            .unwrap();
+        let copy = reader.register_iceberg_table("copy", &orders.location).await.unwrap();
+        assert!(copy.metadata_location.unwrap().contains("/00003-"));
+        assert_eq!(copy.schema.column_by_name("id").unwrap().field_id, 1);
+
+        let registered = reader.snapshots("copy").unwrap();
+        assert_eq!(registered.len(), 1);
+        assert_eq!(registered[0].snapshot_id, overwrite.snapshot_id);
+        assert!(registered[0].data_files.is_empty());
+
+        let ids = |batches: Vec<RecordBatch>| {
+            batches
+                .iter()
+                .flat_map(|batch| {
+                    let ids = batch.column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+                    ids.values().to_vec()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(reader.sql("SELECT id FROM copy").await.unwrap()), vec![4]);
+
+        reader.sql("INSERT INTO copy VALUES (5)").await.unwrap();
+        assert_eq!(ids(reader.sql("SELECT id FROM copy ORDER BY id").await.unwrap()), vec![4, 5]);
+
+        let appended = reader.snapshots("copy").unwrap().pop().unwrap();
+        assert_eq!(appended.sequence_number, overwrite.sequence_number + 1);
+        assert_eq!(appended.data_files.len(), 2);
+
+        let copied = reader.catalog(DEFAULT_CATALOGUE_NAME).unwrap();
+        let copied = copied.load_table(&TableIdent::new("default", "copy")).unwrap();
+        assert!(copied.metadata_location.unwrap().contains("/00004-"));
+
+        // Schema, partition and property changes are published as well
+        reader.sql("ALTER TABLE copy ADD COLUMN note VARCHAR").await.unwrap();
+        reader.sql("ALTER TABLE copy ADD PARTITION FIELD bucket(4, id)").await.unwrap();
+        let owner = BTreeMap::from([("owner".to_string(), "data".to_string())]);
+        reader.update_table_properties("copy", &owner, &[]).await.unwrap();
+
+        let copied = reader.catalog(DEFAULT_CATALOGUE_NAME).unwrap();
+        let copied = copied.load_table(&TableIdent::new("default", "copy")).unwrap();
+        let published = copied.metadata_location.unwrap();
+        assert!(published.contains("/00007-"));
+
+        let metadata: Value = serde_json
+            ::from_slice(&fs::read(local(&published)).unwrap())
+            .unwrap();
+        assert_eq!(metadata["properties"]["owner"], "data");
+        assert_eq!(metadata["current-schema-id"], json!(copied.schema_id));
+        assert_eq!(metadata["default-spec-id"], json!(copied.default_spec_id));
+
+        let republished = reader.publish_metadata("copy").await.unwrap().unwrap();
+        assert!(republished.contains("/00008-"));
+
+        // Explicit metadata files register as they are
+        let pinned = reader.register_iceberg_table("pinned", &metadata_location).await.unwrap();
+        assert_eq!(pinned.current_snapshot.unwrap().snapshot_id, snapshots[1].snapshot_id);
+        let pinned = reader.sql("SELECT id FROM pinned ORDER BY id").await.unwrap();
+        assert_eq!(ids(pinned), vec![1, 2, 3]);
+
+        // A table that fails to register leaves nothing catalogued
+        let mut broken = metadata.clone();
+        for snapshot in broken["snapshots"].as_array_mut().unwrap() {
+            snapshot.as_object_mut().unwrap().remove("manifest-list");
+        }
+        let broken_location = published.replace("/00007-", "/00099-");
+        fs::write(local(&broken_location), serde_json::to_vec(&broken).unwrap()).unwrap();
+        assert!(reader.register_iceberg_table("broken", &broken_location).await.is_err());
+        assert!(reader.snapshots("broken").is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    /// The ids in the first, `BIGINT`, column of `batches`.
+    fn first_ids(batches: &[RecordBatch]) -> Vec<i64> {
+        batches
+            .iter()
+            .flat_map(|batch| {
+                let ids = batch.column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+                ids.values().to_vec()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn delta_log_is_written_and_read_back() {
+        let dir = scratch_dir("delta-log");
+        let lake = dir.join("lake");
+        let commit = |version: i64| {
+            let name = format!("{:020}.json", version);
+            fs::read_to_string(table_dir(&lake, "events").join("_delta_log").join(name))
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str::<Value>(line).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let engine = EngineOptions::new()
+            .provider(Storage::LocalFileSystem { base_path: lake.clone() })
+            .catalogue(PathBuf::from(IN_MEMORY_CATALOGUE))
+            .build().await
+            .unwrap();
+        engine
+            .sql(
+                "CREATE TABLE events TBLPROPERTIES ('table-format' = 'delta', 'owner' = 'ops') \
+                 AS SELECT 1 AS id UNION ALL SELECT 2"
+            ).await
+            .unwrap();
+        engine.sql("INSERT INTO events VALUES (3)").await.unwrap();
+
+        let catalog = engine.catalog(DEFAULT_CATALOGUE_NAME).unwrap();
+        let events = catalog.load_table(&TableIdent::new("default", "events")).unwrap();
+        assert_eq!(events.format().unwrap(), TableFormat::Delta);
+        assert_eq!(events.properties["owner"], "ops");
+        assert_eq!(events.metadata_location, None);
+        assert!(!table_dir(&lake, "events").join("metadata").exists());
+
+        let created = commit(0);
+        assert_eq!(created[0]["protocol"], json!({ "minReaderVersion": 2, "minWriterVersion": 5 }));
+
+        let metadata = &created[1]["metaData"];
+        assert_eq!(metadata["id"], events.table_uuid);
+        assert_eq!(metadata["configuration"]["owner"], "ops");
+        assert_eq!(metadata["configuration"]["delta.columnMapping.mode"], "id");
+        assert_eq!(metadata["configuration"]["delta.columnMapping.maxColumnId"], "1");
+        assert!(metadata["configuration"].get("table-format").is_none());
+
+        let schema = metadata["schemaString"].as_str().unwrap();
+        let schema: Value = serde_json::from_str(schema).unwrap();
+        assert_eq!(schema["fields"][0]["name"], "id");
+        assert_eq!(schema["fields"][0]["type"], "long");
+        assert_eq!(schema["fields"][0]["metadata"]["delta.columnMapping.id"], 1);
+
+        assert!(created[2]["add"]["path"].as_str().unwrap().starts_with("part-"));
+        assert_eq!(created[2]["add"]["stats"], json!({ "numRecords": 2 }).to_string());
+        assert_eq!(created[3]["commitInfo"]["operationParameters"]["mode"], "Append");
+
+        // Appends only add files
+        let appended = commit(1);
+        assert_eq!(appended.len(), 2);
+        assert_eq!(appended[0]["add"]["stats"], json!({ "numRecords": 1 }).to_string());
+
+        // Schema changes are restated by the next write, overwrites remove the old files
+        engine.sql("ALTER TABLE events RENAME COLUMN id TO event_id").await.unwrap();
+        engine.sql("INSERT OVERWRITE events VALUES (4)").await.unwrap();
+
+        let overwritten = commit(2);
+        let schema = overwritten[0]["metaData"]["schemaString"].as_str().unwrap();
+        assert!(schema.contains("event_id"));
+        assert_eq!(overwritten[0]["metaData"]["id"], events.table_uuid);
+        let removed = overwritten
+            .iter()
+            .filter(|action| action.get("remove").is_some())
+            .count();
+        assert_eq!(removed, 2);
+
+        // Another catalogue adopts the table from its log alone
+        let reader = EngineOptions::new()
+            .provider(Storage::LocalFileSystem { base_path: lake.clone() })
+            .catalogue(PathBuf::from(IN_MEMORY_CATALOGUE))
+            .build().await
+            .unwrap();
+        let copy = reader.register_delta_table("copy", &events.location).await.unwrap();
+        assert_eq!(copy.format().unwrap(), TableFormat::Delta);
+        assert_eq!(copy.properties[DELTA_TABLE_ID_PROPERTY], events.table_uuid);
+        assert_eq!(copy.schema.column_by_name("event_id").unwrap().field_id, 1);
+        assert_eq!(copy.current_snapshot.unwrap().sequence_number, 3);
+
+        let rows = reader.sql("SELECT event_id FROM copy").await.unwrap();
+        assert_eq!(first_ids(&rows), vec![4]);
+
+        // Writes through the copy continue the same log
+        reader.sql("INSERT INTO copy VALUES (5)").await.unwrap();
+        let continued = commit(3);
+        assert_eq!(continued.len(), 2);
+        assert!(continued[0]["add"].is_object());
+
+        let rows = reader.sql("SELECT event_id FROM copy ORDER BY event_id").await.unwrap();
+        assert_eq!(first_ids(&rows), vec![4, 5]);
+
+        // The first catalogue can't claim the version the copy took, nor moves ahead of the log
+        assert!(engine.sql("INSERT INTO events VALUES (6)").await.is_err());
+        assert_eq!(engine.snapshots("events").unwrap().len(), 3);
+        assert_eq!(commit(3), continued);
+        let log = table_dir(&lake, "events").join("_delta_log");
+        assert!(!log.join(format!("{:020}.json", 4)).exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn delta_tables_register_from_checkpoints() {
+        let dir = scratch_dir("delta-checkpoint");
+        let table = dir.join("lake/feed");
+        let log = table.join("_delta_log");
+        fs::create_dir_all(&log).unwrap();
+
+        // Written elsewhere, without field ids or column mapping
+        let write_parquet = |name: &str, ids: Vec<i64>, names: Vec<&str>| {
+            let schema = Arc::new(
+                Schema::new(
+                    vec![
+                        Field::new("id", DataType::Int64, true),
+                        Field::new("name", DataType::Utf8, true)
+                    ]
+                )
+            );
+            let batch = RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(Int64Array::from(ids)), Arc::new(StringArray::from(names))]
+            ).unwrap();
+
+            let mut buffer = Vec::new();
+            let mut writer = ArrowWriter::try_new(&mut buffer, schema, None).unwrap();
+            writer.write(&batch).unwrap();
+            writer.close().unwrap();
+            fs::write(table.join(name), &buffer).unwrap();
+
+            buffer.len()
+        };
+        let a = write_parquet("a.parquet", vec![1, 2], vec!["a", "b"]);
+        let b = write_parquet("b.parquet", vec![3], vec!["c"]);
+        let c = write_parquet("c.parquet", vec![4], vec!["d"]);
+
+        let schema = json!({
+            "type": "struct",
+            "fields": [
+                { "name": "id", "type": "long", "nullable": true, "metadata": {} },
+                { "name": "name", "type": "string", "nullable": true, "metadata": {} },
+            ],
+        });
+
+        // Versions 0 and 1 survive only in the checkpoint, `a` keeps no statistics
+        let checkpointed = [
+            json!({ "protocol": { "minReaderVersion": 1, "minWriterVersion": 2 } }),
+            json!({
+                "metaData": {
+                    "id": "feed-id",
+                    "format": { "provider": "parquet" },
+                    "schemaString": schema.to_string(),
+                    "configuration": { "delta.appendOnly": "true" },
+                    "createdTime": 0,
+                },
+            }),
+            json!({ "add": { "path": "a.parquet", "size": a, "dataChange": true } }),
+            json!({
+                "add": {
+                    "path": "b.parquet",
+                    "size": b,
+                    "dataChange": true,
+                    "stats": json!({ "numRecords": 1 }).to_string(),
+                },
+            })
+        ]
+            .iter()
+            .map(Value::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let (checkpoint_schema, _) = arrow_json::reader
+            ::infer_json_schema(checkpointed.as_bytes(), None)
+            .unwrap();
+        let checkpoint_schema = Arc::new(checkpoint_schema);
+        let rows = arrow_json::ReaderBuilder
+            ::new(checkpoint_schema.clone())
+            .build(checkpointed.as_bytes())
+            .unwrap();
+
+        let mut checkpoint = Vec::new();
+        let mut writer = ArrowWriter::try_new(&mut checkpoint, checkpoint_schema, None).unwrap();
+        for batch in rows {
+            writer.write(&batch.unwrap()).unwrap();
+        }
+        writer.close().unwrap();
+        fs::write(log.join(format!("{:020}.checkpoint.parquet", 1)), checkpoint).unwrap();
+
+        let latest = [
+            json!({ "add": { "path": "c.parquet", "size": c, "dataChange": true } }),
+            json!({ "commitInfo": { "operation": "WRITE" } })
+        ];
+        let latest = latest.iter().map(Value::to_string).collect::<Vec<_>>().join("\n");
+        fs::write(log.join(format!("{:020}.json", 2)), latest).unwrap();
+
+        let engine = EngineOptions::new()
+            .provider(Storage::LocalFileSystem { base_path: dir.join("lake") })
+            .catalogue(PathBuf::from(IN_MEMORY_CATALOGUE))
+            .build().await
+            .unwrap();
+        let missing = engine.back_end().url_for("missing/");
+        assert!(engine.register_delta_table("missing", &missing).await.is_err());
+
+        // A log whose files can't be read leaves nothing catalogued
+        let gone_log = dir.join("lake/gone/_delta_log");
+        fs::create_dir_all(&gone_log).unwrap();
+        let gone = [
+            json!({ "protocol": { "minReaderVersion": 1, "minWriterVersion": 2 } }),
+            json!({
+                "metaData": {
+                    "id": "gone-id",
+                    "format": { "provider": "parquet" },
+                    "schemaString": schema.to_string(),
+                    "configuration": {},
+                    "createdTime": 0,
+                },
+            }),
+            json!({ "add": { "path": "gone.parquet", "size": 10, "dataChange": true } })
+        ];
+        let gone = gone.iter().map(Value::to_string).collect::<Vec<_>>().join("\n");
+        fs::write(gone_log.join(format!("{:020}.json", 0)), gone).unwrap();
+        let gone_url = engine.back_end().url_for("gone/");
+        assert!(engine.register_delta_table("gone", &gone_url).await.is_err());
+        assert!(engine.snapshots("gone").is_err());
+
+        let feed_url = engine.back_end().url_for("feed/");
+        let feed = engine.register_delta_table("feed", &feed_url).await.unwrap();
+        assert_eq!(feed.properties[DELTA_TABLE_ID_PROPERTY], "feed-id");
+        assert_eq!(feed.schema.column_by_name("name").unwrap().field_id, 2);
+
+        let snapshot = feed.current_snapshot.unwrap();
+        assert_eq!(snapshot.sequence_number, 3);
+        assert_eq!(snapshot.data_files.len(), 3);
+        assert_eq!(snapshot.row_count(), 4);
+        assert!(snapshot.data_files.iter().all(|file| file.schema_id == feed.schema_id));
+
+        let rows = engine.sql("SELECT id, name FROM feed ORDER BY id").await.unwrap();
+        assert_eq!(first_ids(&rows), vec![1, 2, 3, 4]);
+
+        // The table's configuration is honoured by later writes, refused files are removed
+        let files = files_under(&table).len();
+        assert!(engine.sql("INSERT OVERWRITE feed VALUES (5, 'e')").await.is_err());
+        assert_eq!(files_under(&table).len(), files);
+        engine.sql("INSERT INTO feed VALUES (5, 'e')").await.unwrap();
+        assert!(log.join(format!("{:020}.json", 3)).exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn partition_transforms_follow_iceberg() {
+        let apply = |transform: &str, column: ArrayRef| {
+            Transform::parse(transform).unwrap().apply(&column).unwrap()
+        };
+        let text = |values: &[&str]| {
+            values
+                .iter()
+                .map(|value| Some(value.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        // Hash values from the Iceberg spec, a bucket count of i32::MAX leaves them as is
+        let hashes = apply("bucket[2147483647]", Arc::new(Int64Array::from(vec![34])));
+        assert_eq!(hashes, text(&["2017239379"]));
+        let hashes = apply("bucket[2147483647]", Arc::new(StringArray::from(vec!["iceberg"])));
+        assert_eq!(hashes, text(&["1210000089"]));
+        let hashes = apply("bucket[2147483647]", Arc::new(Date32Array::from(vec![17486])));
+        assert_eq!(hashes, text(&["1494153226"]));
+
+        // 2017-11-16T22:31:08
+        let ts: ArrayRef = Arc::new(TimestampMicrosecondArray::from(vec![1510871468000000]));
+        assert_eq!(apply("year", ts.clone()), text(&["47"]));
+        assert_eq!(apply("month", ts.clone()), text(&["574"]));
+        assert_eq!(apply("day", ts.clone()), text(&["17486"]));
+        assert_eq!(apply("hour", ts.clone()), text(&["419686"]));
+
+        let ints = Arc::new(Int64Array::from(vec![Some(-1), Some(19), None]));
+        let ints = apply("truncate[10]", ints);
+        assert_eq!(ints, vec![Some("-10".to_string()), Some("10".to_string()), None]);
+        let names = apply("truncate[3]", Arc::new(StringArray::from(vec!["iceberg"])));
+        assert_eq!(names, text(&["ice"]));
+
+        // November 2017 spans days 17471 to 17500
+        let (first, last) = Transform::Month.source_bounds(&DataType::Date32, "574");
+        assert_eq!(first, Some(ScalarValue::Date32(Some(17471))));
+        assert_eq!(last, Some(ScalarValue::Date32(Some(17500))));
+
+        assert!(Transform::parse("hour").unwrap().result_type(&DataType::Date32).is_err());
+        assert!(Transform::parse("bucket[0]").is_err());
+
+        for term in ["region", "day(ts)", "bucket(16, user_id)", "truncate(10, name)"] {
+            let (transform, column) = Transform::parse_term(term).unwrap();
+            assert_eq!(transform.term(&column), term);
+        }
+        assert_eq!(
+            Transform::parse_term("bucket[16](user_id)").unwrap(),
+            (Transform::Bucket(16), "user_id".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn hidden_partitioning_splits_writes() {
+        let dir = scratch_dir("hidden-partitioning");
+        let lake = dir.join("lake");
+        let read_avro = |url: &str| {
+            let bytes = fs::read(PathBuf::from(url.trim_start_matches("file://"))).unwrap();
+            apache_avro::Reader
+                ::new(&bytes[..])
+                .unwrap()
+                .map(|record| apache_avro::from_value::<Value>(&record.unwrap()).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let engine = EngineOptions::new()
+            .provider(Storage::LocalFileSystem { base_path: lake.clone() })
+            .catalogue(PathBuf::from(IN_MEMORY_CATALOGUE))
+            .build().await
+            .unwrap();
+
+        engine
+            .sql(
+                "CREATE TABLE events PARTITION BY (day(ts), bucket(4, user_id)) AS \
+                 SELECT CAST(column1 AS BIGINT) AS user_id, CAST(column2 AS TIMESTAMP) AS ts \
+                 FROM (VALUES (1, '2024-03-05 10:00:00'), (34, '2024-03-06 01:00:00'), \
+                 (34, '2024-03-06 23:59:59'))"
+            ).await
+            .unwrap();
+
+        let catalog = engine.catalog(DEFAULT_CATALOGUE_NAME).unwrap();
+        let events = catalog.load_table(&TableIdent::new("default", "events")).unwrap();
+        assert_eq!(events.partition_spec, vec!["day(ts)", "bucket(4, user_id)"]);
+
+        // One file per partition, 34 hashes to 2017239379, bucket 3 of 4
+        let created = engine.snapshots("events").unwrap().pop().unwrap();
+        assert_eq!(created.data_files.len(), 2);
+        let second_day = created.data_files
+            .iter()
+            .find(|file| file.partition_values == vec![Some("19788".into()), Some("3".into())])
+            .unwrap();
+        assert_eq!(second_day.row_count, 2);
+        assert!(second_day.file_path.contains("/ts_day=2024-03-06/user_id_bucket=3/"));
+        assert!(table_dir(&lake, "events").join("ts_day=2024-03-05").is_dir());
+
+        // Manifests carry the typed partition values
+        let manifests = read_avro(created.manifest_list.as_ref().unwrap());
+        let entries = read_avro(manifests[0]["manifest_path"].as_str().unwrap());
+        let entry = entries
+            .iter()
+            .find(|entry| entry["data_file"]["file_path"] == json!(second_day.file_path))
+            .unwrap();
+        let partition = json!({ "ts_day": 19788, "user_id_bucket": 3 });
+        assert_eq!(entry["data_file"]["partition"], partition);
+
+        engine.sql("INSERT INTO events VALUES (2, '2024-03-05 11:00:00')").await.unwrap();
+        let inserted = engine.snapshots("events").unwrap().pop().unwrap();
+        assert_eq!(inserted.data_files.len(), 3);
+        let counted = engine.sql("SELECT COUNT(*) FROM events").await.unwrap();
+        let count = counted[0].column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(count.value(0), 4);
+
+        // Renaming the source column keeps the spec attached to it
+        engine.sql("ALTER TABLE events RENAME COLUMN ts TO happened_at").await.unwrap();
+        let events = catalog.load_table(&TableIdent::new("default", "events")).unwrap();
+        assert_eq!(events.partition_spec, vec!["day(happened_at)", "bucket(4, user_id)"]);
+
+        assert!(
+            engine
+                .sql("CREATE TABLE bad PARTITION BY (hour(user_id)) AS SELECT 1 AS user_id").await
+                .is_err()
+        );
+        let delta = engine
+            .sql(
+                "CREATE TABLE log TBLPROPERTIES ('table-format' = 'delta') PARTITION BY (id) \
+                 AS SELECT 1 AS id"
+            ).await
+            .unwrap_err();
+        assert!(delta.to_string().contains("can't be partitioned"));
+
+        // CSV ingest derives partition values without derived columns in the file
+        let input = dir.join("clicks.csv");
+        fs::write(
+            &input,
+            "ts,region\n2024-03-05T10:00:00,east\n2024-03-05T12:00:00,west\n\
+             2024-03-07T09:00:00,east\n"
+        ).unwrap();
+        let partitioned = BlobWriterOps::make()
+            .path(input)
+            .make_paritions(vec![String::from("day(ts)"), String::from("truncate(1, region)")])
+            .buiild();
+        let clicks = engine.ingest_with(&partitioned).await.unwrap();
+        assert_eq!(clicks.partition_spec, vec!["day(ts)", "truncate(1, region)"]);
+        assert_eq!(engine.snapshots("clicks").unwrap()[0].data_files.len(), 3);
+
+        let clicks = table_dir(&lake, "clicks");
+        assert!(clicks.join("ts_day=2024-03-05/region_trunc=w").is_dir());
+        assert!(clicks.join("ts_day=2024-03-07/region_trunc=e").is_dir());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn reads_skip_files_by_partition_and_statistics() {
+        let dir = scratch_dir("pruning");
+        let lake = dir.join("lake");
+
+        let engine = EngineOptions::new()
+            .provider(Storage::LocalFileSystem { base_path: lake.clone() })
+            .catalogue(PathBuf::from(IN_MEMORY_CATALOGUE))
+            .build().await
+            .unwrap();
+
+        engine
+            .sql(
+                "CREATE TABLE events PARTITION BY (day(ts)) AS \
+                 SELECT CAST(column1 AS BIGINT) AS user_id, CAST(column2 AS TIMESTAMP) AS ts, \
+                 column3 AS name FROM (VALUES (1, '2024-03-05 10:00:00', 'ann'), \
+                 (34, '2024-03-06 01:00:00', 'bob'), (2, '2024-03-07 12:00:00', NULL))"
+            ).await
+            .unwrap();
+
+        let created = engine.snapshots("events").unwrap().pop().unwrap();
+        let second_day = created.data_files
+            .iter()
+            .find(|file| file.partition_values == vec![Some("19788".to_string())])
+            .unwrap();
+
+        let user_id = second_day.column_stats[&1].clone();
+        assert_eq!((user_id.min.as_deref(), user_id.max.as_deref()), (Some("34"), Some("34")));
+        assert_eq!(second_day.column_stats[&3].null_count, 0);
+
+        // Queries that can't match the missing file never open it
+        fs::remove_file(second_day.file_path.trim_start_matches("file://")).unwrap();
+
+        let count = |sql: &'static str| {
+            let engine = &engine;
+            async move {
+                let batches = engine.sql(sql).await?;
+                let count = batches[0].column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+                anyhow::Ok(count.value(0))
+            }
+        };
+
+        assert_eq!(count("SELECT COUNT(*) FROM events WHERE ts < '2024-03-06'").await.unwrap(), 1);
+        assert_eq!(
+            count("SELECT COUNT(*) FROM events WHERE ts >= '2024-03-07 00:00:00'").await.unwrap(),
+            1
+        );
+        assert_eq!(count("SELECT COUNT(*) FROM events WHERE user_id IN (1, 2)").await.unwrap(), 2);
+        assert_eq!(count("SELECT COUNT(*) FROM events WHERE name = 'ann'").await.unwrap(), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM events WHERE name IS NULL").await.unwrap(), 1);
+
+        assert!(count("SELECT COUNT(*) FROM events").await.is_err());
+        assert!(count("SELECT COUNT(*) FROM events WHERE user_id > 10").await.is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn partition_specs_evolve_without_rewriting() {
+        let dir = scratch_dir("spec-evolution");
+        let lake = dir.join("lake");
+        let local = |url: &str| PathBuf::from(url.trim_start_matches("file://"));
+
+        let engine = EngineOptions::new()
+            .provider(Storage::LocalFileSystem { base_path: lake.clone() })
+            .catalogue(PathBuf::from(IN_MEMORY_CATALOGUE))
+            .build().await
+            .unwrap();
+        let catalog = engine.catalog(DEFAULT_CATALOGUE_NAME).unwrap();
+        let events = TableIdent::new("default", "events");
+
+        engine
+            .sql(
+                "CREATE TABLE events PARTITION BY (day(ts)) AS \
+                 SELECT CAST(column1 AS BIGINT) AS id, CAST(column2 AS TIMESTAMP) AS ts \
+                 FROM (VALUES (1, '2024-03-05 10:00:00'), (2, '2024-03-06 11:00:00'))"
+            ).await
+            .unwrap();
+        let daily = engine.snapshots("events").unwrap().pop().unwrap().data_files;
+
+        engine
+            .sql("ALTER TABLE events REPLACE PARTITION FIELD day(ts) WITH hour(ts)").await
+            .unwrap();
+        engine
+            .sql(
+                "INSERT INTO events SELECT CAST(column1 AS BIGINT), CAST(column2 AS TIMESTAMP) \
+                 FROM (VALUES (3, '2024-03-07 09:30:00'), (4, '2024-03-07 12:15:00'))"
+            ).await
+            .unwrap();
+
+        let metadata = catalog.load_table(&events).unwrap();
+        assert_eq!(metadata.partition_spec, vec!["hour(ts)"]);
+        assert_eq!(metadata.default_spec_id, 1);
+        assert_eq!(metadata.partition_specs.len(), 2);
+        assert_eq!(metadata.partition_specs[0].fields[0].field_id, Some(1000));
+        assert_eq!(metadata.partition_specs[1].fields[0].field_id, Some(1001));
+
+        // Earlier files stay where they were, new ones are split by hour
+        let files = engine.snapshots("events").unwrap().pop().unwrap().data_files;
+        assert_eq!(files.len(), 4);
+        assert!(daily.iter().all(|file| file.spec_id == 0 && files.contains(file)));
+        let hourly: Vec<_> = files
+            .iter()
+            .filter(|file| file.spec_id == 1)
+            .collect();
+        assert_eq!(hourly.len(), 2);
+        assert!(hourly.iter().any(|file| file.file_path.contains("/ts_hour=2024-03-07-12/")));
+
+        // Iceberg metadata lists both specs
+        let iceberg: Value = serde_json
+            ::from_slice(&fs::read(local(metadata.metadata_location.as_ref().unwrap())).unwrap())
+            .unwrap();
+        assert_eq!(iceberg["default-spec-id"], 1);
+        assert_eq!(iceberg["last-partition-id"], 1001);
+        assert_eq!(iceberg["partition-specs"].as_array().unwrap().len(), 2);
+
+        // Files of either spec are skipped by what their partition tells
+        let first_day = daily
+            .iter()
+            .find(|file| file.partition_values == vec![Some("19787".to_string())])
+            .unwrap();
+        let noon = hourly
+            .iter()
+            .find(|file| file.file_path.contains("ts_hour=2024-03-07-12"))
+            .unwrap();
+        fs::remove_file(local(&first_day.file_path)).unwrap();
+        fs::remove_file(local(&noon.file_path)).unwrap();
+
+        let ids = engine
+            .sql(
+                "SELECT id FROM events \
+                 WHERE ts >= '2024-03-06' AND ts < '2024-03-07 12:00:00' ORDER BY id"
+            ).await
+            .unwrap();
+        assert_eq!(first_ids(&ids), vec![2, 3]);
+        assert!(engine.sql("SELECT id FROM events").await.is_err());
+
+        // Dropping the last field unpartitions, going back to an earlier spec reuses it
+        engine.sql("ALTER TABLE events DROP PARTITION FIELD ts_hour").await.unwrap();
+        assert!(catalog.load_table(&events).unwrap().current_spec().is_unpartitioned());
+
+        let spec = engine
+            .alter_partitioning("events", &[PartitionChange::AddField { term: "day(ts)".into() }])
+            .await
+            .unwrap();
+        assert_eq!(spec.spec_id, 0);
+        let metadata = catalog.load_table(&events).unwrap();
+        assert_eq!((metadata.default_spec_id, metadata.partition_specs.len()), (0, 3));
+
+        let error = engine.sql("ALTER TABLE events ADD PARTITION FIELD day(ts)").await.unwrap_err();
+        assert!(error.to_string().contains("already exists"));
+        let error = engine.sql("ALTER TABLE events DROP PARTITION FIELD region").await.unwrap_err();
+        assert!(error.to_string().contains("not found"));
+        assert!(engine.sql("ALTER TABLE events REPLACE PARTITION FIELD day(ts)").await.is_err());
+
+        // Table names are read like in any other statement
+        let (table, change) = PartitionChange::from_sql(
+            "ALTER TABLE lake.\"team.analytics\".\"Daily Events\"\n  \
+             ADD PARTITION FIELD bucket(4, id);"
+        )
+            .unwrap()
+            .unwrap();
+        assert_eq!(table, "lake.\"team.analytics\".\"Daily Events\"");
+        assert_eq!(change, PartitionChange::AddField { term: "bucket(4, id)".into() });
+        assert!(PartitionChange::from_sql("ALTER TABLE events ADD COLUMN region TEXT").is_none());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
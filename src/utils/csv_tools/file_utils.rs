@@ -26,103 +26,292 @@
 
 use anyhow::Ok;
 
-use arrow_schema::Schema;
-use bytes::Bytes;
+use arrow_array::{
+    Array,
+    ArrayRef,
+    Date32Array,
+    RecordBatch,
+    StringArray,
+    TimestampNanosecondArray,
+};
+use arrow_schema::{ DataType, Field, Schema, SchemaRef };
 
-use datafusion::dataframe::DataFrameWriteOptions;
-use datafusion::execution::object_store::ObjectStoreUrl;
-use datafusion::prelude::{ ParquetReadOptions, SessionContext };
+use chrono::{ Datelike, NaiveDate, NaiveDateTime };
+use datafusion::arrow::compute::cast;
 
-use glob::{ MatchOptions, glob_with };
-use object_store::local::LocalFileSystem;
-use object_store::path::Path;
-use object_store::ObjectStore;
+use futures::executor::block_on;
 
-use parquet::arrow::ArrowWriter;
-use parquet::basic::Compression;
-use parquet::file::properties::WriterProperties;
+use glob::{ MatchOptions, glob_with };
 
 use std::collections::HashMap;
 
-use std::fs::{ self };
 use std::fs::File;
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use tokio::sync::mpsc::channel;
+use tokio::task::spawn_blocking;
+
+use crate::blob_writer::{ upload_parts, PartitionedWriter, UPLOAD_QUEUE_LENGTH };
+use crate::catalogue::{ partitioning::PartitionSpec, snapshots::DataFile, tables::SchemaVec };
 use crate::utils::csv_tools::reader::BlobWriter;
+use crate::utils::storage::storage::BackEnd;
 
 pub const DEFAULT_SAMPLING_SIZE: usize = 5;
 pub const LOCAL_DB_ROOT: &str = "db://";
+pub const IN_MEMORY_ROOT: &str = "memory://";
 pub struct Empty {}
 
+/// The rows of a CSV file, as batches of the schema it is read as.
+pub(crate) struct CsvRows {
+    csv: arrow_csv::Reader<File>,
+    schema: SchemaRef,
+    /// Date and timestamp columns read as text, with the format they are parsed with.
+    formatted: Vec<(usize, String)>,
+}
+
+impl CsvRows {
+    fn parse_formatted(&self, batch: RecordBatch) -> anyhow::Result<RecordBatch> {
+        let mut columns = batch.columns().to_vec();
+
+        for (index, format) in &self.formatted {
+            let field = self.schema.field(*index);
+            let text = columns[*index]
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .ok_or_else(|| anyhow::anyhow!("Column '{}' wasn't read as text", field.name()))?;
+
+            let parse_error = |value: &str| {
+                anyhow::anyhow!(
+                    "Value '{}' of column '{}' doesn't match format '{}'",
+                    value,
+                    field.name(),
+                    format
+                )
+            };
+
+            let parsed: ArrayRef = match field.data_type() {
+                DataType::Date32 | DataType::Date64 => {
+                    let days = text
+                        .iter()
+                        .map(|value| {
+                            value
+                                .map(|value| {
+                                    let date = NaiveDate::parse_from_str(value, format).map_err(
+                                        |_| parse_error(value)
+                                    )?;
+
+                                    anyhow::Ok(date.num_days_from_ce() - UNIX_EPOCH_DAYS_FROM_CE)
+                                })
+                                .transpose()
+                        })
+                        .collect::<anyhow::Result<Date32Array>>()?;
+
+                    Arc::new(days)
+                }
+                _ => {
+                    let nanos = text
+                        .iter()
+                        .map(|value| {
+                            value
+                                .map(|value| {
+                                    let parsed = NaiveDateTime::parse_from_str(value, format)
+                                        .map_err(|_| parse_error(value))?;
+
+                                    parsed
+                                        .and_utc()
+                                        .timestamp_nanos_opt()
+                                        .ok_or_else(|| {
+                                            anyhow::anyhow!(
+                                                "Value '{}' of column '{}' is outside the years \
+                                                 1677 to 2262 timestamps can hold",
+                                                value,
+                                                field.name()
+                                            )
+                                        })
+                                })
+                                .transpose()
+                        })
+                        .collect::<anyhow::Result<TimestampNanosecondArray>>()?;
+
+                    Arc::new(nanos)
+                }
+            };
+
+            columns[*index] = cast(&parsed, field.data_type())?;
+        }
+
+        Ok(RecordBatch::try_new(self.schema.clone(), columns)?)
+    }
+}
+
+impl Iterator for CsvRows {
+    type Item = anyhow::Result<RecordBatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let batch = self.csv.next()?;
+
+        Some(
+            batch
+                .map_err(anyhow::Error::from)
+                .and_then(|batch| self.parse_formatted(batch))
+        )
+    }
+}
+
+/// Days from the start of the common era to 1970-01-01.
+const UNIX_EPOCH_DAYS_FROM_CE: i32 = 719_163;
+
 impl BlobWriter {
-    /// Helper function to onvert a CSV file to Parquet format.
-    ///
-    /// # Arguments
-    ///
-    /// * `self` - An immutable refernce to self
-    ///
-    /// * `Store` - Object storage interface
-    /// # Returns
-    ///
-    /// Returns `Ok` if the conversion is successful, otherwise returns an `Err`.
-    ///
-    ///
-    ///
-    /// ```
+    /// The schema the CSV file is read as, the explicit one or one inferred from the
+    /// sampled rows with the column type overrides applied.
+    ///
+    /// Inferred columns are deduplicated and numbered, explicit ones keep their ids,
+    /// so written files record a `PARQUET:field_id` for each of them.
+    pub(crate) fn infer_schema(&self) -> anyhow::Result<SchemaRef> {
+        if let Some(schema) = &self.schema {
+            if !self.column_types.is_empty() {
+                return Err(
+                    anyhow::anyhow!("Column type hints can't be combined with an explicit schema")
+                );
+            }
+
+            let numbered = schema.to_arrow_schema();
+            SchemaVec::from_numbered_arrow_schema(&numbered)?;
+
+            return Ok(Arc::new(numbered));
+        }
 
-    pub async fn to_parquet(&self, store: Arc<dyn ObjectStore>) -> anyhow::Result<Arc<Schema>> {
         let file = File::open(self.input.clone())?;
 
         let (csv_schema, _) = arrow_csv::reader::Format
             ::default()
             .with_header(self.has_header)
             .with_delimiter(self.delimiter as u8)
-            .infer_schema(file, Some(DEFAULT_SAMPLING_SIZE as usize))?;
+            .infer_schema(file, self.sample_size)?;
 
         let schema_ref = BlobWriter::remove_deduplicate_columns(csv_schema);
 
-        let file = File::open(self.input.clone())?;
-        let mut csv = arrow_csv::ReaderBuilder
-            ::new(schema_ref.clone())
-            .with_delimiter(self.delimiter as u8)
-            .with_header(self.has_header)
-            .build(file)?;
+        let mut fields: Vec<Field> = schema_ref
+            .fields()
+            .iter()
+            .map(|field| field.as_ref().clone())
+            .collect();
+
+        for (column, datatype) in &self.column_types {
+            let field = fields
+                .iter_mut()
+                .find(|field| field.name() == column)
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Column '{}' not found in {}", column, self.input.display())
+                })?;
+
+            *field = field.clone().with_data_type(datatype.clone());
+        }
 
-        let props = WriterProperties::builder()
-            .set_compression(Compression::SNAPPY)
-            .set_created_by("cc2p".to_string())
-            .build();
+        let schema_ref = Schema::new(fields);
 
-        let mut buffer = Vec::new();
+        Ok(Arc::new(SchemaVec::from_arrow_schema(&schema_ref).to_arrow_schema()))
+    }
 
-        let mut writer = ArrowWriter::try_new(&mut buffer, schema_ref.clone(), Some(props))?;
+    /// Opens a reader parsing the CSV file's rows as `schema`.
+    ///
+    /// Date and timestamp columns are read as text and parsed with the configured
+    /// format when one is set. Unless the writer sets a schema, matched by position,
+    /// the file's header has to name the schema's columns in order.
+    pub(crate) fn open_csv(&self, schema: SchemaRef) -> anyhow::Result<CsvRows> {
+        if self.has_header && self.schema.is_none() {
+            self.check_header(&schema)?;
+        }
 
-        for maybe_batch in csv.by_ref() {
-            let batch = maybe_batch?;
-            let _ = writer.write(&batch)?;
+        let formatted: Vec<(usize, String)> = schema
+            .fields()
+            .iter()
+            .enumerate()
+            .filter_map(|(index, field)| {
+                let format = match field.data_type() {
+                    DataType::Date32 | DataType::Date64 => self.date_format.as_ref(),
+                    DataType::Timestamp(_, _) => self.timestamp_format.as_ref(),
+                    _ => None,
+                };
+
+                Some((index, format?.clone()))
+            })
+            .collect();
+
+        let mut read_fields: Vec<Field> = schema
+            .fields()
+            .iter()
+            .map(|field| field.as_ref().clone())
+            .collect();
+
+        for (index, _) in &formatted {
+            read_fields[*index] = read_fields[*index].clone().with_data_type(DataType::Utf8);
         }
 
-        writer.close()?;
+        let file = File::open(self.input.clone())?;
 
-        let file_stem = self.input.file_stem().unwrap().to_string_lossy();
-        let file_name = self.input.with_extension("parquet").clone();
+        let csv = arrow_csv::ReaderBuilder
+            ::new(Arc::new(Schema::new(read_fields)))
+            .with_delimiter(self.delimiter as u8)
+            .with_header(self.has_header)
+            .build(file)?;
 
-        let mut upload = store.put_multipart(
-            &Path::from(
-                format!("{}/{}", file_stem, file_name.file_name().unwrap().to_string_lossy())
-            )
-        ).await?;
+        Ok(CsvRows { csv, schema, formatted })
+    }
+
+    /// Fails unless the file's header names the columns of `schema`, in order and
+    /// cleaned as when inferring a schema. Every file matching a glob is read with
+    /// the schema inferred from the first one.
+    fn check_header(&self, schema: &Schema) -> anyhow::Result<()> {
+        let file = File::open(self.input.clone())?;
 
-        let chunk_size = 10 * 1024 * 1024;
+        let (header, _) = arrow_csv::reader::Format
+            ::default()
+            .with_header(true)
+            .with_delimiter(self.delimiter as u8)
+            .infer_schema(file, Some(0))?;
+        let header = BlobWriter::remove_deduplicate_columns(header);
 
-        for chunk in buffer.chunks(chunk_size) {
-            upload.put_part(Bytes::copy_from_slice(chunk).into()).await?;
+        let names = |schema: &Schema| {
+            schema
+                .fields()
+                .iter()
+                .map(|field| field.name().clone())
+                .collect::<Vec<_>>()
+        };
+
+        if names(&header) != names(schema) {
+            return Err(
+                anyhow::anyhow!(
+                    "The columns of {} are {:?}, expected {:?}",
+                    self.input.display(),
+                    names(&header),
+                    names(schema)
+                )
+            );
         }
 
-        upload.complete().await?;
+        Ok(())
+    }
 
-        Ok(schema_ref)
+    /// Helper function to onvert a CSV file to Parquet format.
+    ///
+    /// Rows are streamed from the CSV file into uniquely named files under
+    /// `<file stem>/`, e.g. `orders/part-<uuid>.parquet`, so converting the same file
+    /// twice never overwrites earlier output. A new file is started whenever one
+    /// reaches the target file size or row count.
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - An immutable refernce to self
+    ///
+    /// * `Store` - The backend the parquet files are written to.
+    /// # Returns
+    ///
+    /// Returns `Ok` if the conversion is successful, otherwise returns an `Err`.
+    pub async fn to_parquet(&self, store: &BackEnd) -> anyhow::Result<Arc<Schema>> {
+        self.partion_parquet(Vec::new(), store).await
     }
 
     /// Converts a CSV file to Parquet format.
@@ -130,80 +319,132 @@ impl BlobWriter {
     /// # Arguments
     ///
     /// * `self` - An immutable refernce to self
-    /// * `partitions` - The vecotr containing to the partitions predicates.
+    /// * `partitions` - The partition terms, column names or transforms of them such
+    ///   as `day(ts)`, `bucket(16, user_id)` or `truncate(10, name)`.
+    /// * `store` - The backend the parquet files are written to.
     ///
     ///
     /// # Returns
     ///
     /// Returns `Ok` if the conversion is successful, otherwise returns an `Err` with a `Box<dyn std::error::Error>`.
-    ///
-    ///
-    ///
-    /// ```
-
     pub async fn store(
         &self,
         partitions: Option<Vec<String>>,
-        store: Arc<dyn ObjectStore>
+        store: &BackEnd
     ) -> anyhow::Result<Arc<Schema>> {
-        let schema: Arc<Schema>;
-        if partitions.is_some() {
-            let partitions = partitions.unwrap();
-            let object_store_url = ObjectStoreUrl::parse("sidebuffer://").unwrap();
-            let object_store = Arc::new(LocalFileSystem::new());
+        match partitions {
+            Some(partitions) => self.partion_parquet(partitions, store).await,
+            None => self.to_parquet(store).await,
+        }
+    }
 
-            schema = self.to_parquet(object_store.clone()).await?;
+    /// Converts a CSV file into parquet files split by partition, one directory per
+    /// partition field under `<file stem>/`, e.g. `events/ts_day=2024-03-05/`.
+    /// Without partition terms every file is written to `<file stem>/` itself.
+    ///
+    /// Partition values are derived from the rows while writing, the files keep
+    /// every column of the CSV file.
+    pub async fn partion_parquet(
+        &self,
+        partitions: Vec<String>,
+        store: &BackEnd
+    ) -> anyhow::Result<Arc<Schema>> {
+        let schema_ref = self.infer_schema()?;
+        let spec = PartitionSpec::from_terms(
+            &partitions,
+            &SchemaVec::from_numbered_arrow_schema(&schema_ref)?
+        )?;
 
-            let ctx = Arc::new(SessionContext::new());
+        let file_stem = self.input.file_stem().unwrap().to_string_lossy();
 
-            let file_stem = self.input.file_stem().unwrap().to_string_lossy();
+        // Files written without a table are catalogued by scanning their location
+        self.write_data_files(
+            schema_ref.clone(),
+            &spec,
+            store,
+            &store.url_for(&format!("{}/", file_stem)),
+            0
+        ).await?;
 
-            let parquet_path = format!("sidebuffer://{}/", file_stem);
+        Ok(schema_ref)
+    }
 
-            // Local store for reading
-            ctx.register_object_store(object_store_url.as_ref(), object_store);
-            ctx.register_parquet("sidebufer", parquet_path, ParquetReadOptions::default()).await?;
+    /// Streams the rows of the CSV file, read as `schema`, into new data files of a
+    /// table under `location`, split by the table's partition spec and rolled over
+    /// at the writer's target file size or row count.
+    ///
+    /// The file is read and its rows encoded on a blocking thread, the calling task
+    /// only uploads the encoded files.
+    pub(crate) async fn write_data_files(
+        &self,
+        schema: SchemaRef,
+        spec: &PartitionSpec,
+        store: &BackEnd,
+        location: &str,
+        schema_id: i64
+    ) -> anyhow::Result<Vec<DataFile>> {
+        let (uploads, queued) = channel(UPLOAD_QUEUE_LENGTH);
+
+        let writer = self.clone();
+        let (spec, encoding_store, location) = (spec.clone(), store.clone(), location.to_string());
+
+        let encoded = spawn_blocking(move || {
+            let csv = writer.open_csv(schema.clone())?;
+            let mut files = PartitionedWriter::try_new(
+                &encoding_store,
+                &location,
+                schema_id,
+                schema,
+                &spec,
+                writer.write_options,
+                Some(uploads)
+            )?;
+
+            block_on(async {
+                for maybe_batch in csv {
+                    files.write(&maybe_batch?).await?;
+                }
 
-            // Register target store for writing with a different scheme
-            let target_store_url = ObjectStoreUrl::parse(LOCAL_DB_ROOT).unwrap();
-            ctx.register_object_store(target_store_url.as_ref(), store);
+                files.finish().await
+            })
+        });
 
-            self.partion_parquet(ctx.clone(), partitions).await?;
+        let uploaded = upload_parts(store, queued).await;
+        let written = encoded.await?;
 
-            std::fs::remove_dir_all("sidebuffer://")?;
-        } else {
-            schema = self.to_parquet(store.clone()).await?;
-        }
-        Ok(schema);
-    }
+        // A failed upload stops the encoding too, its own error is the one to report
+        uploaded?;
 
-    pub async fn partion_parquet(
-        &self,
-        ctx: Arc<SessionContext>,
-        partitions: Vec<String>
-    ) -> anyhow::Result<()> {
-        let df = ctx.sql("SELECT * FROM directory").await?;
-        let schema = df.schema();
+        written
+    }
 
-        for partition in partitions.clone().into_iter() {
-            schema
-                .index_of_column_by_name(None, &partition)
-                .ok_or_else(|| anyhow::anyhow!("Column '{}' not found in schema", partition))?;
+    /// The name the file is ingested as, the configured table name or the file's stem.
+    /// Glob ingests have no single file to name the table after.
+    pub(crate) fn table_name(&self) -> anyhow::Result<String> {
+        match (&self.table_name, &self.glob) {
+            (Some(name), _) => Ok(name.clone()),
+            (None, Some(pattern)) => {
+                Err(anyhow::anyhow!("Ingesting '{}' needs a table name", pattern))
+            }
+            (None, None) => {
+                Ok(self.input.file_stem().unwrap_or_default().to_string_lossy().to_string())
+            }
         }
+    }
 
-        let file_stem = self.input.file_stem().unwrap().to_string_lossy();
+    /// The CSV files ingested, every file matching the glob pattern or the input file.
+    pub(crate) fn inputs(&self) -> anyhow::Result<Vec<PathBuf>> {
+        let Some(pattern) = &self.glob else {
+            return Ok(vec![self.input.clone()]);
+        };
 
-        let target_parquet_path = format!("db://{}/", file_stem);
+        let files = BlobWriter::find_files(pattern)?;
 
-        df.write_parquet(
-            &target_parquet_path,
-            DataFrameWriteOptions::new()
-                .with_partition_by(partitions)
-                .with_single_file_output(false),
-            None
-        ).await?;
+        if files.is_empty() {
+            return Err(anyhow::anyhow!("No CSV files match '{}'", pattern));
+        }
 
-        Ok(())
+        Ok(files)
     }
 
     /// Removes duplicate columns from a given Arrow schema, and returns a new schema with deduplicated columns.
@@ -261,25 +502,23 @@ impl BlobWriter {
     ///
     /// # Returns
     ///
-    /// A vector of `PathBuf` representing the paths of the matching files.
-    ///
-    /// # Panics
-    ///
-    /// This function will panic if it fails to read the file search pattern.
+    /// A vector of `PathBuf` representing the paths of the matching `.csv` files, in
+    /// path order, or an `Err` if the pattern is invalid or a matching path can't be
+    /// read. Extensions are matched in any case, `.CSV` files are found too.
     ///
     /// # Examples
     ///
-    /// ```rust
-    /// use std::path::PathBuf;
+    /// ```no_run
+    /// use unakite::utils::csv_tools::reader::BlobWriter;
     ///
-    /// let pattern = "testdata/sample*.csv";
-    /// let files = find_files(pattern);
+    /// let files = BlobWriter::find_files("landing/2025-*/*.csv")?;
     ///
     /// for file in files {
     ///     println!("{:?}", file);
     /// }
+    /// # Ok::<(), anyhow::Error>(())
     /// ```
-    pub(self) fn find_files(pattern: &str) -> Vec<PathBuf> {
+    pub fn find_files(pattern: &str) -> anyhow::Result<Vec<PathBuf>> {
         let mut files = vec![];
         let options = MatchOptions {
             case_sensitive: false,
@@ -287,22 +526,19 @@ impl BlobWriter {
             require_literal_leading_dot: false,
         };
 
-        for entry in glob_with(pattern, options).expect("failed to read file search pattern") {
-            match entry {
-                anyhow::Result::Ok(p) => {
-                    if p.is_file() {
-                        if let Some(ext) = p.extension() {
-                            if ext == "csv" {
-                                files.push(p);
-                            }
-                        }
+        for entry in glob_with(pattern, options)? {
+            let p = entry?;
+
+            if p.is_file() {
+                if let Some(ext) = p.extension() {
+                    if ext.eq_ignore_ascii_case("csv") {
+                        files.push(p);
                     }
                 }
-                Err(e) => eprintln!("{:?}", e),
             }
         }
 
-        files
+        Ok(files)
     }
 
     /// Cleans a given string by removing any characters that are not alphanumeric or whitespace.
@@ -313,7 +549,7 @@ impl BlobWriter {
     ///
     /// # Examples
     ///
-    /// ```rust
+    /// ```ignore
     /// use cc2p::*;
     ///
     /// let name = clean_column_name("John!Doe");
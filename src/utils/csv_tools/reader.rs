@@ -1,21 +1,62 @@
 use std::path::PathBuf;
 
+use arrow_schema::DataType;
+
+use crate::blob_writer::WriteOptions;
+use crate::catalogue::tables::SchemaVec;
+use crate::utils::csv_tools::file_utils::DEFAULT_SAMPLING_SIZE;
+
+/// Files of a glob ingest written in parallel by default.
+pub const DEFAULT_INGEST_CONCURRENCY: usize = 8;
+
+#[derive(Debug, Clone)]
 pub struct BlobWriter {
     pub(crate) input: PathBuf,
+    /// Pattern of the CSV files ingested together in place of `input`.
+    pub(crate) glob: Option<String>,
+    /// How many files of a glob ingest are written at once.
+    pub(crate) concurrency: usize,
 
     pub(crate) make_partiotion_on: Option<Vec<String>>,
+    /// Name of the table the file is ingested as, the file's stem when unset.
+    pub(crate) table_name: Option<String>,
     pub(crate) has_header: bool,
 
     pub(crate) delimiter: char,
+
+    /// Schema the rows are read as, in place of an inferred one.
+    pub(crate) schema: Option<SchemaVec>,
+    /// Types replacing inferred ones, by column name.
+    pub(crate) column_types: Vec<(String, DataType)>,
+    /// Rows the schema is inferred from, every row when `None`.
+    pub(crate) sample_size: Option<usize>,
+    /// `strftime` format of date columns, ISO 8601 when unset.
+    pub(crate) date_format: Option<String>,
+    /// `strftime` format of timestamp columns, ISO 8601 when unset.
+    pub(crate) timestamp_format: Option<String>,
+
+    pub(crate) write_options: WriteOptions,
 }
 
+#[derive(Debug, Clone)]
 pub struct BlobWriterOps {
     input: PathBuf,
+    glob: Option<String>,
+    concurrency: usize,
 
     make_partiotion_on: Option<Vec<String>>,
+    table_name: Option<String>,
     has_header: bool,
 
     delimiter: char,
+
+    schema: Option<SchemaVec>,
+    column_types: Vec<(String, DataType)>,
+    sample_size: Option<usize>,
+    date_format: Option<String>,
+    timestamp_format: Option<String>,
+
+    write_options: WriteOptions,
 }
 
 impl BlobWriterOps {
@@ -28,6 +69,20 @@ impl BlobWriterOps {
         self
     }
 
+    /// Ingests every CSV file matching `pattern`, e.g. `landing/2025-*/*.csv`, into
+    /// one table in a single commit. The table has to be named with [`Self::table`],
+    /// its schema is inferred from the first matching file.
+    pub fn glob(mut self, pattern: &str) -> Self {
+        self.glob = Some(pattern.to_string());
+        self
+    }
+
+    /// How many files of a glob ingest are read and written in parallel.
+    pub fn concurrency(mut self, files: usize) -> Self {
+        self.concurrency = files;
+        self
+    }
+
     pub fn set_delimiter(mut self, delimiter: char) -> Self {
         self.delimiter = delimiter;
         self
@@ -38,18 +93,98 @@ impl BlobWriterOps {
         self
     }
 
+    /// Ingests the file as `name`, which may be qualified with a catalogue and
+    /// namespace, e.g. `scratch.default.orders`.
+    pub fn table(mut self, name: String) -> Self {
+        self.table_name = Some(name);
+        self
+    }
+
     pub fn has_header(mut self, boolean: bool) -> Self {
         self.has_header = boolean;
 
         self
     }
 
+    /// Reads the rows as `schema` instead of inferring one, its columns are matched
+    /// to the file's by position and keep their field ids. Can't be combined with
+    /// [`BlobWriterOps::column_type`].
+    pub fn schema(mut self, schema: SchemaVec) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Reads `column` as `datatype` whatever type is inferred for it. Only applies
+    /// to inferred schemas, see [`BlobWriterOps::schema`].
+    pub fn column_type(mut self, column: &str, datatype: DataType) -> Self {
+        self.column_types.push((column.to_string(), datatype));
+        self
+    }
+
+    /// Infers the schema from the first `rows` rows.
+    pub fn sample_size(mut self, rows: usize) -> Self {
+        self.sample_size = Some(rows);
+        self
+    }
+
+    /// Infers the schema from every row of the file, which reads it twice.
+    pub fn infer_full_file(mut self) -> Self {
+        self.sample_size = None;
+        self
+    }
+
+    /// Parses date columns with a `strftime` format, e.g. `%d/%m/%Y`.
+    pub fn date_format(mut self, format: &str) -> Self {
+        self.date_format = Some(format.to_string());
+        self
+    }
+
+    /// Parses timestamp columns with a `strftime` format, e.g. `%d/%m/%Y %H:%M`.
+    pub fn timestamp_format(mut self, format: &str) -> Self {
+        self.timestamp_format = Some(format.to_string());
+        self
+    }
+
+    /// Size in bytes data files are rolled over at.
+    pub fn target_file_size(mut self, bytes: u64) -> Self {
+        self.write_options.target_file_size = bytes;
+        self
+    }
+
+    /// Rows data files are rolled over at.
+    pub fn target_file_rows(mut self, rows: u64) -> Self {
+        self.write_options.target_file_rows = Some(rows);
+        self
+    }
+
+    /// Rows per parquet row group, at most one row group per file is held in memory.
+    pub fn row_group_size(mut self, rows: usize) -> Self {
+        self.write_options.row_group_size = rows;
+        self
+    }
+
+    /// Bytes the files of a partitioned write may buffer together before the one
+    /// buffering most is completed early.
+    pub fn max_buffered_bytes(mut self, bytes: usize) -> Self {
+        self.write_options.max_buffered_bytes = bytes;
+        self
+    }
+
     pub fn buiild(self) -> BlobWriter {
         BlobWriter {
             input: self.input,
+            glob: self.glob,
+            concurrency: self.concurrency,
             make_partiotion_on: self.make_partiotion_on,
+            table_name: self.table_name,
             has_header: self.has_header,
             delimiter: self.delimiter,
+            schema: self.schema,
+            column_types: self.column_types,
+            sample_size: self.sample_size,
+            date_format: self.date_format,
+            timestamp_format: self.timestamp_format,
+            write_options: self.write_options,
         }
     }
 }
@@ -58,10 +193,19 @@ impl Default for BlobWriterOps {
     fn default() -> Self {
         BlobWriterOps {
             input: PathBuf::new(),
+            glob: None,
+            concurrency: DEFAULT_INGEST_CONCURRENCY,
 
             make_partiotion_on: None,
+            table_name: None,
             has_header: true,
             delimiter: ',',
+            schema: None,
+            column_types: Vec::new(),
+            sample_size: Some(DEFAULT_SAMPLING_SIZE),
+            date_format: None,
+            timestamp_format: None,
+            write_options: WriteOptions::default(),
         }
     }
 }
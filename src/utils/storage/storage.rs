@@ -1,15 +1,118 @@
-use std::{ path::PathBuf, sync::Arc };
+use std::{ fs::{ canonicalize, create_dir_all }, path::PathBuf, sync::Arc };
 
 use anyhow::Ok;
-use object_store::{ local::LocalFileSystem, ObjectStore };
+use futures::{ StreamExt, TryStreamExt };
+use datafusion::datasource::listing::ListingTableUrl;
+use datafusion::execution::object_store::ObjectStoreUrl;
+use datafusion::prelude::SessionContext;
+use object_store::{
+    aws::AmazonS3Builder,
+    local::LocalFileSystem,
+    memory::InMemory,
+    path::Path,
+    prefix::PrefixStore,
+    ObjectStore,
+};
 
-use crate::utils::csv_tools::file_utils::LOCAL_DB_ROOT;
+use crate::utils::csv_tools::file_utils::{ IN_MEMORY_ROOT, LOCAL_DB_ROOT };
 
+#[derive(Clone)]
 pub struct BackEnd {
     store: Arc<dyn ObjectStore>,
+    /// The store urls are resolved against, `store` without the base path or prefix.
+    root: Arc<dyn ObjectStore>,
+    /// Url of the lake itself, e.g. `file:///data/lake/` or `s3://bucket/prefix/`.
+    base_url: String,
     store_meta: Storage,
 }
 
+impl BackEnd {
+    /// Handle to the underlying object store client.
+    pub fn store(&self) -> Arc<dyn ObjectStore> {
+        self.store.clone()
+    }
+
+    /// The url the store was registered under before urls named the lake's real
+    /// location. Tables catalogued back then still record it.
+    pub fn object_store_url(&self) -> ObjectStoreUrl {
+        match &self.store_meta {
+            Storage::LocalFileSystem { .. } => {
+                ObjectStoreUrl::parse(format!("{}local", LOCAL_DB_ROOT)).unwrap()
+            }
+            Storage::S3 { bucket, .. } => {
+                ObjectStoreUrl::parse(format!("s3://{}", bucket)).unwrap()
+            }
+            Storage::InMemory => ObjectStoreUrl::parse(format!("{}local", IN_MEMORY_ROOT)).unwrap(),
+        }
+    }
+
+    /// The url the store's root is registered under.
+    fn root_url(&self) -> ObjectStoreUrl {
+        match &self.store_meta {
+            Storage::LocalFileSystem { .. } => ObjectStoreUrl::local_filesystem(),
+            _ => self.object_store_url(),
+        }
+    }
+
+    /// Registers the store with a session, so it resolves the urls of [`BackEnd::url_for`]
+    /// as well as the ones recorded under [`BackEnd::object_store_url`].
+    pub fn register(&self, ctx: &SessionContext) {
+        let root_url = self.root_url();
+
+        if root_url != self.object_store_url() {
+            ctx.register_object_store(self.object_store_url().as_ref(), self.store());
+        }
+
+        ctx.register_object_store(root_url.as_ref(), self.root.clone());
+    }
+
+    /// Full url of a path inside the store, e.g. `file:///data/lake/orders/` or
+    /// `s3://bucket/prefix/orders/`. Other engines read these urls as they are.
+    pub fn url_for(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path.trim_start_matches('/'))
+    }
+
+    /// The path inside the store a url of [`BackEnd::url_for`] names, urls recorded
+    /// under [`BackEnd::object_store_url`] are accepted too.
+    pub fn path_for(&self, url: &str) -> anyhow::Result<Path> {
+        let path = url
+            .strip_prefix(&self.base_url)
+            .or_else(|| url.strip_prefix(self.object_store_url().as_str()))
+            .ok_or_else(|| anyhow::anyhow!("{} is not a location of this store", url))?;
+
+        Ok(Path::from_url_path(path)?)
+    }
+
+    /// Deletes every object under a url of [`BackEnd::url_for`].
+    pub async fn delete_all(&self, url: &str) -> anyhow::Result<()> {
+        let prefix = self.path_for(url)?;
+        let locations = self.store
+            .list(Some(&prefix))
+            .map_ok(|object| object.location)
+            .boxed();
+
+        self.store.delete_stream(locations).try_collect::<Vec<_>>().await?;
+
+        Ok(())
+    }
+
+    /// Deletes the objects at urls of [`BackEnd::url_for`], e.g. data files that were
+    /// written but never committed.
+    pub async fn delete_each(&self, urls: &[String]) -> anyhow::Result<()> {
+        let locations = urls
+            .iter()
+            .map(|url| self.path_for(url))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let locations = futures::stream::iter(locations).map(Result::Ok).boxed();
+
+        self.store.delete_stream(locations).try_collect::<Vec<_>>().await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum Storage {
     LocalFileSystem {
         base_path: PathBuf,
@@ -21,27 +124,106 @@ pub enum Storage {
         access_key_id: Option<String>,
         secret_access_key: Option<String>,
         endpoint: Option<String>, // For MinIO or custom endpoints
+        /// Key prefix the lake lives under inside the bucket, urls are relative to it.
         prefix: Option<String>,
     },
+
+    /// Keeps data files in memory, they are lost when the engine is dropped.
+    InMemory,
 }
 
 impl Storage {
     pub async fn get_store(self) -> anyhow::Result<BackEnd> {
         match self {
             Self::LocalFileSystem { ref base_path } => {
+                create_dir_all(base_path)?;
+
                 let store: Arc<dyn ObjectStore> = Arc::new(
-                    LocalFileSystem::new_with_prefix(LOCAL_DB_ROOT)?
+                    LocalFileSystem::new_with_prefix(base_path)?
                 );
+                let base_path = canonicalize(base_path)?;
+                let base_url = ListingTableUrl::parse(format!("{}/", base_path.display()))?;
 
-                return Ok(BackEnd {
+                Ok(BackEnd {
                     store,
+                    root: Arc::new(LocalFileSystem::new()),
+                    base_url: base_url.as_str().to_string(),
                     store_meta: self,
-                });
+                })
             }
 
-            _ => {
-                // TODO
-                unimplemented!();
+            Self::S3 {
+                ref bucket,
+                ref region,
+                ref access_key_id,
+                ref secret_access_key,
+                ref endpoint,
+                ref prefix,
+            } => {
+                // Anything not configured explicitly, e.g. session tokens, comes from the
+                // usual AWS_* environment variables
+                let mut builder = AmazonS3Builder::from_env()
+                    .with_bucket_name(bucket)
+                    .with_region(region);
+
+                match (access_key_id, secret_access_key) {
+                    (Some(access_key_id), Some(secret_access_key)) => {
+                        builder = builder
+                            .with_access_key_id(access_key_id)
+                            .with_secret_access_key(secret_access_key);
+                    }
+                    (None, None) => {}
+                    _ => {
+                        return Err(
+                            anyhow::anyhow!(
+                                "S3 access_key_id and secret_access_key must be set together"
+                            )
+                        );
+                    }
+                }
+
+                if let Some(endpoint) = endpoint {
+                    // S3-compatible stores such as MinIO are addressed by path and are
+                    // often served over plain http
+                    builder = builder
+                        .with_endpoint(endpoint)
+                        .with_virtual_hosted_style_request(false)
+                        .with_allow_http(endpoint.starts_with("http://"));
+                }
+
+                let s3: Arc<dyn ObjectStore> = Arc::new(builder.build()?);
+
+                let prefix = prefix
+                    .as_deref()
+                    .map(|prefix| prefix.trim_matches('/'))
+                    .filter(|prefix| !prefix.is_empty());
+                let (store, base_url): (Arc<dyn ObjectStore>, _) = match prefix {
+                    Some(prefix) => {
+                        (
+                            Arc::new(PrefixStore::new(s3.clone(), prefix)),
+                            format!("s3://{}/{}/", bucket, prefix),
+                        )
+                    }
+                    None => (s3.clone(), format!("s3://{}/", bucket)),
+                };
+
+                Ok(BackEnd {
+                    store,
+                    root: s3,
+                    base_url,
+                    store_meta: self,
+                })
+            }
+
+            Self::InMemory => {
+                let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+
+                Ok(BackEnd {
+                    store: store.clone(),
+                    root: store,
+                    base_url: format!("{}local/", IN_MEMORY_ROOT),
+                    store_meta: self,
+                })
             }
         }
     }